@@ -18,6 +18,21 @@ pub fn parse() {
     Vdf::parse(black_box(VDF_TEXT)).unwrap();
 }
 
+#[bench(bytes_count = VDF_TEXT.len())]
+pub fn tape() {
+    keyvalues_parser::Parser::new()
+        .tape(black_box(VDF_TEXT))
+        .unwrap();
+}
+
+#[cfg(feature = "pest")]
+#[bench(bytes_count = VDF_TEXT.len())]
+pub fn parse_pest() {
+    keyvalues_parser::Parser::new()
+        .parse_pest(black_box(VDF_TEXT))
+        .unwrap();
+}
+
 #[bench]
 pub fn render(bencher: Bencher) {
     let vdf = Vdf::parse(VDF_TEXT).unwrap();
@@ -26,3 +41,14 @@ pub fn render(bencher: Bencher) {
 
     bencher.counter(bytes).bench(|| vdf.to_string())
 }
+
+#[bench]
+pub fn render_to_io(bencher: Bencher) {
+    let vdf = Vdf::parse(VDF_TEXT).unwrap();
+    let rendered = vdf.to_string();
+    let bytes = BytesCount::of_str(&rendered);
+
+    bencher
+        .counter(bytes)
+        .bench(|| vdf.render_to_io(&mut std::io::sink()).unwrap())
+}
@@ -1,9 +1,13 @@
 #![no_main]
-use keyvalues_parser::Vdf;
+use keyvalues_parser::{Parser, Vdf};
 use libfuzzer_sys::fuzz_target;
 
 fuzz_target!(|input: (bool, &str)| {
     let (raw, text) = input;
+    // The hand-written parser should always agree with the original `pest` one
+    let parser = Parser::new().literal_special_chars(raw).with_spans(true);
+    assert_eq!(parser.parse(text), parser.parse_pest(text));
+
     if raw {
         let _ = Vdf::parse_raw(text).map(|parsed| {
             let mut rendered = String::new();
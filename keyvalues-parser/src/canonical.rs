@@ -0,0 +1,194 @@
+//! Comparing and hashing documents by what they mean instead of how they're written
+//!
+//! Parsing already throws away whitespace, comments, quoting, and escape sequences, and the
+//! sorted [`Obj`] always keeps its keys in the same order. [`Vdf::canonicalize()`] builds on that
+//! by optionally folding the case of keys like the engine does, and [`Vdf::semantic_eq()`] and
+//! [`Vdf::content_hash()`] compare and hash the canonical form
+//!
+//! ```
+//! use keyvalues_parser::{canonical::CanonicalOptions, Vdf};
+//!
+//! let a = Vdf::parse(r#"
+//! "AppState"
+//! {
+//!     "name"  "Portal 2"
+//!     "appid" "620" // comment
+//! }
+//! "#)?;
+//! let b = Vdf::parse(r#"AppState{appid 620 NAME "Portal 2"}"#)?;
+//! let options = CanonicalOptions::new();
+//! assert!(!a.semantic_eq(&b, &options));
+//!
+//! let options = options.fold_keys(true);
+//! assert!(a.semantic_eq(&b, &options));
+//! assert_eq!(a.content_hash(&options), b.content_hash(&options));
+//! # Ok::<(), keyvalues_parser::error::Error>(())
+//! ```
+//!
+//! # Hash stability
+//!
+//! [`Vdf::content_hash()`] is guaranteed to stay the same across versions of this crate, Rust
+//! itself, and platforms, so it's safe to persist in things like build caches. It's the 64-bit
+//! [FNV-1a](http://www.isthe.com/chongo/tech/comp/fnv/index.html) hash of the canonical tree
+//! encoded to bytes as follows where lengths and counts are `u64` little-endian
+//!
+//! - A string is a `0x00` byte followed by its length in bytes and then its UTF-8 bytes
+//! - An object is a `0x01` byte followed by its number of pairs and then each pair in order
+//! - A pair is its key as a string followed by its value
+//!
+//! The top-level pair is encoded as a pair. Pairs within an object are ordered by key and keys
+//! with several values get a pair for each value in their original order
+
+use std::borrow::Cow;
+
+use crate::{Key, Obj, Value, Vdf};
+
+/// Options for how [`Vdf::canonicalize()`] normalizes a tree
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CanonicalOptions {
+    fold_keys: bool,
+}
+
+impl CanonicalOptions {
+    /// Constructs the default options
+    ///
+    /// Currently this consists of:
+    ///
+    /// | Option | Description | Default |
+    /// | :---: | :--- | :---: |
+    /// | [`CanonicalOptions::fold_keys()`] | Whether keys get lowercased | `false` |
+    pub const fn new() -> Self {
+        Self { fold_keys: false }
+    }
+
+    /// Toggle lowercasing every key
+    ///
+    /// By default (`false`) keys are kept as is. When `true` ASCII letters in keys get lowercased
+    /// and the values of keys within an object that only differ in case are merged together
+    /// (ordered by the original spelling of their keys). This matches how the engine compares
+    /// keys
+    pub const fn fold_keys(mut self, yes: bool) -> Self {
+        self.fold_keys = yes;
+        self
+    }
+}
+
+impl<'text> Vdf<'text> {
+    /// Returns the canonical form of the pair according to `options`
+    ///
+    /// ```
+    /// use keyvalues_parser::{canonical::CanonicalOptions, Vdf};
+    /// let vdf = Vdf::parse("Root { Key a key b }")?;
+    /// let canonical = vdf.canonicalize(&CanonicalOptions::new().fold_keys(true));
+    /// assert_eq!(canonical, Vdf::parse("root { key a key b }")?);
+    /// # Ok::<(), keyvalues_parser::error::Error>(())
+    /// ```
+    pub fn canonicalize(&self, options: &CanonicalOptions) -> Vdf<'text> {
+        Vdf::new(
+            canonical_key(&self.key, options),
+            canonical_value(&self.value, options),
+        )
+    }
+
+    /// Returns if both pairs have the same canonical form
+    pub fn semantic_eq(&self, other: &Vdf<'_>, options: &CanonicalOptions) -> bool {
+        if options.fold_keys {
+            self.canonicalize(options) == other.canonicalize(options)
+        } else {
+            // The sorted representation is already canonical
+            self == other
+        }
+    }
+
+    /// Returns a hash of the canonical form that never changes between versions
+    ///
+    /// See the [module docs][self#hash-stability] for exactly how it's calculated
+    pub fn content_hash(&self, options: &CanonicalOptions) -> u64 {
+        let canonical = if options.fold_keys {
+            Cow::Owned(self.canonicalize(options))
+        } else {
+            Cow::Borrowed(self)
+        };
+
+        let mut hasher = Fnv1a::new();
+        hasher.write_str(&canonical.key);
+        hasher.write_value(&canonical.value);
+        hasher.finish()
+    }
+}
+
+fn canonical_key<'text>(key: &Key<'text>, options: &CanonicalOptions) -> Key<'text> {
+    if options.fold_keys && key.bytes().any(|b| b.is_ascii_uppercase()) {
+        Cow::Owned(key.to_ascii_lowercase())
+    } else {
+        key.clone()
+    }
+}
+
+fn canonical_value<'text>(value: &Value<'text>, options: &CanonicalOptions) -> Value<'text> {
+    match value {
+        Value::Str(s) => Value::Str(s.clone()),
+        Value::Obj(obj) => {
+            let mut canonical = Obj::new();
+            for (key, values) in obj.iter() {
+                canonical
+                    .entry(canonical_key(key, options))
+                    .or_default()
+                    .extend(values.iter().map(|value| canonical_value(value, options)));
+            }
+            Value::Obj(canonical)
+        }
+    }
+}
+
+/// The 64-bit FNV-1a hash which is simple enough to pin down forever
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    const STR_TAG: u8 = 0x00;
+    const OBJ_TAG: u8 = 0x01;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_len(&mut self, len: usize) {
+        self.write(&(len as u64).to_le_bytes());
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write(&[Self::STR_TAG]);
+        self.write_len(s.len());
+        self.write(s.as_bytes());
+    }
+
+    fn write_value(&mut self, value: &Value<'_>) {
+        match value {
+            Value::Str(s) => self.write_str(s),
+            Value::Obj(obj) => {
+                self.write(&[Self::OBJ_TAG]);
+                self.write_len(obj.values().map(Vec::len).sum());
+                for (key, values) in obj.iter() {
+                    for value in values {
+                        self.write_str(key);
+                        self.write_value(value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
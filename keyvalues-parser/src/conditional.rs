@@ -0,0 +1,211 @@
+//! Conditional tags that can trail a pair e.g. `"key" "value" [$WIN32]`
+//!
+//! Valve's KeyValues allows for a conditional after a value or object which determines whether
+//! the pair is included based on which symbols are defined. The supported syntax is
+//!
+//! - `[$NAME]` which is true when `NAME` is defined
+//! - `[!$NAME]` which is true when `NAME` isn't defined
+//! - Symbols joined with `&&` and `||` where `&&` binds tighter than `||` e.g.
+//!   `[$WIN32 && !$X360 || $OSX]`
+//!
+//! Conditionals are kept on the pairs of the [`ordered`][crate::ordered] representation and can
+//! be applied with methods like [`ordered::PartialVdf::evaluate_conditionals()`][
+//! crate::ordered::PartialVdf::evaluate_conditionals]. The sorted representation keeps every
+//! pair and drops the conditionals
+//!
+//! ```
+//! use keyvalues_parser::Parser;
+//!
+//! let vdf_text = r#"
+//! "Settings"
+//! {
+//!     "font"  "Tahoma"    [$WIN32]
+//!     "font"  "Verdana"   [!$WIN32]
+//! }
+//! "#;
+//! let vdf = Parser::new().parse_ordered(vdf_text)?;
+//! let vdf = vdf.evaluate_conditionals(&["WIN32"]).unwrap();
+//! let settings = vdf.value.get_obj().unwrap();
+//! assert_eq!(settings.len(), 1);
+//! assert_eq!(settings.get("font").unwrap().get_str(), Some("Tahoma"));
+//! # Ok::<(), keyvalues_parser::error::Error>(())
+//! ```
+
+use std::{borrow::Cow, fmt};
+
+/// A single, possibly negated, symbol within a [`Conditional`] e.g. `$WIN32` or `!$X360`
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol<'text> {
+    /// The name of the symbol without the leading `$`
+    pub name: Cow<'text, str>,
+    pub negated: bool,
+}
+
+impl<'text> Symbol<'text> {
+    /// A symbol that is true when `name` is defined
+    pub fn new(name: impl Into<Cow<'text, str>>) -> Self {
+        Self {
+            name: name.into(),
+            negated: false,
+        }
+    }
+
+    /// A symbol that is true when `name` isn't defined
+    pub fn negated(name: impl Into<Cow<'text, str>>) -> Self {
+        Self {
+            name: name.into(),
+            negated: true,
+        }
+    }
+
+    /// Symbols are matched case-insensitively like Valve's implementation
+    pub fn evaluate<S: AsRef<str>>(&self, defined: &[S]) -> bool {
+        let is_defined = defined
+            .iter()
+            .any(|symbol| symbol.as_ref().eq_ignore_ascii_case(&self.name));
+        is_defined != self.negated
+    }
+}
+
+impl fmt::Display for Symbol<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            f.write_str("!")?;
+        }
+        write!(f, "${}", self.name)
+    }
+}
+
+/// A conditional tag stored as `||`ed groups of `&&`ed symbols
+///
+/// This mirrors the syntax directly since `&&` binds tighter than `||` and there is no support
+/// for grouping with parentheses, so `[$A && $B || !$C]` is stored as `[[$A, $B], [!$C]]`
+///
+/// ```
+/// use keyvalues_parser::conditional::{Conditional, Symbol};
+///
+/// let cond = Conditional::parse("[$WIN32 && !$X360 || $OSX]").unwrap();
+/// assert_eq!(
+///     cond.any,
+///     [
+///         vec![Symbol::new("WIN32"), Symbol::negated("X360")],
+///         vec![Symbol::new("OSX")],
+///     ],
+/// );
+/// assert!(cond.evaluate(&["win32"]));
+/// assert!(!cond.evaluate(&["WIN32", "X360"]));
+/// assert!(cond.evaluate(&["OSX", "X360"]));
+/// assert_eq!(cond.to_string(), "[$WIN32 && !$X360 || $OSX]");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Conditional<'text> {
+    pub any: Vec<Vec<Symbol<'text>>>,
+}
+
+impl<'text> Conditional<'text> {
+    /// Parses a full conditional tag including the surrounding `[]`s
+    pub fn parse(s: &'text str) -> Option<Self> {
+        Self::parse_prefix(s).and_then(|(cond, len)| (len == s.len()).then_some(cond))
+    }
+
+    /// Parses a conditional tag from the start of `s` returning it along with its length in bytes
+    pub(crate) fn parse_prefix(s: &'text str) -> Option<(Self, usize)> {
+        if !s.starts_with('[') {
+            return None;
+        }
+        let mut cursor = Cursor { s, pos: 1 };
+        let mut any = vec![vec![cursor.symbol()?]];
+        loop {
+            if cursor.eat("&&").is_some() {
+                any.last_mut().unwrap().push(cursor.symbol()?);
+            } else if cursor.eat("||").is_some() {
+                any.push(vec![cursor.symbol()?]);
+            } else {
+                cursor.eat("]")?;
+                return Some((Self { any }, cursor.pos));
+            }
+        }
+    }
+
+    /// Returns if the conditional holds when only the symbols in `defined` are defined
+    ///
+    /// Symbols should be passed without the leading `$` and are matched case-insensitively
+    pub fn evaluate<S: AsRef<str>>(&self, defined: &[S]) -> bool {
+        self.any
+            .iter()
+            .any(|all| all.iter().all(|symbol| symbol.evaluate(defined)))
+    }
+}
+
+impl<'text> From<Symbol<'text>> for Conditional<'text> {
+    fn from(symbol: Symbol<'text>) -> Self {
+        Self {
+            any: vec![vec![symbol]],
+        }
+    }
+}
+
+impl fmt::Display for Conditional<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[")?;
+        for (i, all) in self.any.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" || ")?;
+            }
+            for (j, symbol) in all.iter().enumerate() {
+                if j != 0 {
+                    f.write_str(" && ")?;
+                }
+                write!(f, "{symbol}")?;
+            }
+        }
+        f.write_str("]")
+    }
+}
+
+/// Keeps in sync with the `conditional` rule from the grammars
+struct Cursor<'text> {
+    s: &'text str,
+    pos: usize,
+}
+
+impl<'text> Cursor<'text> {
+    fn skip_spaces(&mut self) {
+        let rest = &self.s[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    }
+
+    /// Consumes `token` after any leading spaces
+    fn eat(&mut self, token: &str) -> Option<()> {
+        self.skip_spaces();
+        self.s[self.pos..].starts_with(token).then(|| {
+            self.pos += token.len();
+        })
+    }
+
+    fn symbol(&mut self) -> Option<Symbol<'text>> {
+        self.skip_spaces();
+        let negated = self.s[self.pos..].starts_with('!');
+        if negated {
+            self.pos += 1;
+        }
+        if !self.s[self.pos..].starts_with('$') {
+            return None;
+        }
+        self.pos += 1;
+
+        let rest = &self.s[self.pos..];
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return None;
+        }
+        self.pos += len;
+
+        Some(Symbol {
+            name: Cow::from(&rest[..len]),
+            negated,
+        })
+    }
+}
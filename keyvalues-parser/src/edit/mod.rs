@@ -0,0 +1,612 @@
+//! A format-preserving representation of VDF text for editing files in place
+//!
+//! Unlike [`Vdf`][crate::Vdf], a [`Document`] keeps track of all of the formatting in the
+//! original text (comments, whitespace, quoting style, `#base`/`#include` directives, conditional
+//! tags, trailing `\0`s, and the ordering of pairs). Rendering a document gives back the original
+//! text byte-for-byte, and any edits only affect the regions that were actually changed
+//!
+//! ```
+//! use keyvalues_parser::edit::Document;
+//!
+//! let vdf_text = r#"// Steam keeps track of your library here
+//! "libraryfolders"
+//! {
+//!     "0"
+//!     {
+//!         "path"    "/home/user/.steam/steam"  // the default library
+//!         "label"   ""
+//!     }
+//! }
+//! "#;
+//! let mut doc = Document::parse(vdf_text)?;
+//! assert_eq!(doc.to_string(), vdf_text);
+//!
+//! let library = doc
+//!     .root_mut()
+//!     .value_mut()
+//!     .as_obj_mut()
+//!     .unwrap()
+//!     .get_mut("0")
+//!     .unwrap()
+//!     .as_obj_mut()
+//!     .unwrap();
+//! library.insert("label", "Games");
+//! library.insert("contentid", "1234");
+//! library.remove("path");
+//!
+//! assert_eq!(
+//!     doc.to_string(),
+//!     r#"// Steam keeps track of your library here
+//! "libraryfolders"
+//! {
+//!     "0"
+//!     {
+//!         "label"   "Games"
+//!         "contentid"   "1234"
+//!     }
+//! }
+//! "#,
+//! );
+//! # Ok::<(), keyvalues_parser::error::Error>(())
+//! ```
+
+mod parse;
+mod render;
+
+use std::{borrow::Cow, fmt};
+
+use crate::{conditional::Conditional, error::ParseError, Key, PartialVdf};
+
+/// A parsed VDF document that remembers how it was formatted
+///
+/// See the [module level docs][self] for more info
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Document<'text> {
+    directives: Vec<Directive<'text>>,
+    root: Pair<'text>,
+    /// Everything after the top-level pair (trivia, `\0`, etc.)
+    trailer: Cow<'text, str>,
+    escaped: bool,
+}
+
+impl<'text> Document<'text> {
+    /// Parses VDF text into a format-preserving [`Document`]
+    ///
+    /// This interprets escape sequences in quoted strings the same as [`Vdf::parse()`]
+    ///
+    /// [`Vdf::parse()`]: crate::Vdf::parse
+    pub fn parse(text: &'text str) -> Result<Self, ParseError> {
+        parse::parse(text, true)
+    }
+
+    /// Parses VDF text into a format-preserving [`Document`] without interpreting escape sequences
+    ///
+    /// This is the equivalent of [`Vdf::parse_raw()`]
+    ///
+    /// [`Vdf::parse_raw()`]: crate::Vdf::parse_raw
+    pub fn parse_raw(text: &'text str) -> Result<Self, ParseError> {
+        parse::parse(text, false)
+    }
+
+    /// Returns the top-level pair
+    pub fn root(&self) -> &Pair<'text> {
+        &self.root
+    }
+
+    /// Returns the top-level pair mutably
+    pub fn root_mut(&mut self) -> &mut Pair<'text> {
+        &mut self.root
+    }
+
+    /// Returns an iterator over the paths from all of the `#base` directives
+    pub fn bases(&self) -> impl Iterator<Item = &str> + '_ {
+        self.directive_paths(BASE)
+    }
+
+    /// Returns an iterator over the paths from all of the `#include` directives
+    pub fn includes(&self) -> impl Iterator<Item = &str> + '_ {
+        self.directive_paths(INCLUDE)
+    }
+
+    /// Adds a new `#base` directive after any existing directives
+    ///
+    /// ```
+    /// # use keyvalues_parser::edit::Document;
+    /// let mut doc = Document::parse("\"key\" \"value\"\n")?;
+    /// doc.push_base("base.vdf");
+    /// assert_eq!(doc.to_string(), "#base \"base.vdf\"\n\n\"key\" \"value\"\n");
+    /// # Ok::<(), keyvalues_parser::error::Error>(())
+    /// ```
+    pub fn push_base(&mut self, path: impl Into<Cow<'text, str>>) {
+        self.push_directive(BASE, path.into());
+    }
+
+    /// Adds a new `#include` directive after any existing directives
+    pub fn push_include(&mut self, path: impl Into<Cow<'text, str>>) {
+        self.push_directive(INCLUDE, path.into());
+    }
+
+    /// Removes all of the `#base` directives with a matching path returning if any were removed
+    pub fn remove_base(&mut self, path: &str) -> bool {
+        self.remove_directive(BASE, path)
+    }
+
+    /// Removes all of the `#include` directives with a matching path returning if any were
+    /// removed
+    pub fn remove_include(&mut self, path: &str) -> bool {
+        self.remove_directive(INCLUDE, path)
+    }
+
+    fn directive_paths(&self, keyword: &'static str) -> impl Iterator<Item = &str> + '_ {
+        self.directives
+            .iter()
+            .filter(move |directive| directive.keyword == keyword)
+            .map(|directive| directive.path.value())
+    }
+
+    fn cloned_directive_paths(&self, keyword: &str) -> Vec<Cow<'text, str>> {
+        self.directives
+            .iter()
+            .filter(|directive| directive.keyword == keyword)
+            .map(|directive| directive.path.value.clone())
+            .collect()
+    }
+
+    fn push_directive(&mut self, keyword: &'static str, path: Cow<'text, str>) {
+        let prefix = if self.directives.is_empty() {
+            // The new directive takes the place of the top-level pair's leading trivia
+            let prefix = self.root.prefix.take().unwrap_or_default();
+            self.root.prefix = Some(Cow::from("\n\n"));
+            prefix
+        } else {
+            Cow::from("\n")
+        };
+
+        self.directives.push(Directive {
+            prefix,
+            keyword: Cow::from(keyword),
+            gap: Cow::from(" "),
+            path: Str::new(path),
+        });
+    }
+
+    fn remove_directive(&mut self, keyword: &str, path: &str) -> bool {
+        let len = self.directives.len();
+        let first_prefix = self.directives.first().map(|first| first.prefix.clone());
+        self.directives
+            .retain(|directive| directive.keyword != keyword || directive.path.value() != path);
+        // Whatever is first now takes over the leading trivia from the start of the document
+        if let Some((first, prefix)) = self.directives.first_mut().zip(first_prefix) {
+            first.prefix = prefix;
+        }
+        len != self.directives.len()
+    }
+
+    /// Converts the document to the loosely typed representation, dropping all formatting
+    pub fn to_partial_vdf(&self) -> PartialVdf<'text> {
+        PartialVdf {
+            key: self.root.key.value.clone(),
+            value: self.root.value.to_value(),
+            bases: self.cloned_directive_paths(BASE),
+            includes: self.cloned_directive_paths(INCLUDE),
+            spans: None,
+        }
+    }
+}
+
+impl fmt::Display for Document<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self._render(f)
+    }
+}
+
+const BASE: &str = "#base";
+const INCLUDE: &str = "#include";
+
+/// A `#base` or `#include` directive along with its formatting
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Directive<'text> {
+    prefix: Cow<'text, str>,
+    keyword: Cow<'text, str>,
+    gap: Cow<'text, str>,
+    path: Str<'text>,
+}
+
+/// A string along with how it was originally written
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Str<'text> {
+    value: Cow<'text, str>,
+    /// The exact source text when the string hasn't been modified
+    repr: Option<Cow<'text, str>>,
+    quoted: bool,
+}
+
+impl<'text> Str<'text> {
+    /// Creates a new quoted string
+    pub fn new(value: impl Into<Cow<'text, str>>) -> Self {
+        Self {
+            value: value.into(),
+            repr: None,
+            quoted: true,
+        }
+    }
+
+    /// Returns the string with any escape sequences already interpreted
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Returns the text that will be rendered for this string if it's unmodified
+    pub fn repr(&self) -> Option<&str> {
+        self.repr.as_deref()
+    }
+
+    /// Returns if the string is (or will be) wrapped in quotes
+    pub fn is_quoted(&self) -> bool {
+        self.quoted
+    }
+
+    /// Replaces the string's value while trying to keep the same quoting style
+    ///
+    /// Strings that were originally unquoted stay unquoted as long as the new value can be
+    /// represented without quotes
+    pub fn set_value(&mut self, value: impl Into<Cow<'text, str>>) {
+        let value = value.into();
+        self.quoted = self.quoted || !crate::text::is_unquoted_safe(&value);
+        self.value = value;
+        self.repr = None;
+    }
+
+    fn restyle(mut self, like: &Self) -> Self {
+        self.quoted = like.quoted || !crate::text::is_unquoted_safe(&self.value);
+        self
+    }
+}
+
+impl<'text> From<&'text str> for Str<'text> {
+    fn from(s: &'text str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl From<String> for Str<'_> {
+    fn from(s: String) -> Self {
+        Self::new(s)
+    }
+}
+
+impl<'text> From<Cow<'text, str>> for Str<'text> {
+    fn from(s: Cow<'text, str>) -> Self {
+        Self::new(s)
+    }
+}
+
+/// A key-value pair along with the formatting that surrounds it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pair<'text> {
+    /// Trivia before the key (typically a newline, indentation, and any comments on the lines
+    /// above)
+    prefix: Option<Cow<'text, str>>,
+    key: Str<'text>,
+    /// Trivia between the key and the value
+    gap: Option<Cow<'text, str>>,
+    value: Value<'text>,
+    conditional: Option<Conditional<'text>>,
+    /// The original text of the conditional including the trivia before it
+    conditional_repr: Option<Cow<'text, str>>,
+    /// Trivia following the value on the same line (typically a trailing comment)
+    suffix: Cow<'text, str>,
+}
+
+impl<'text> Pair<'text> {
+    /// Creates a new pair that will use default formatting when rendered
+    pub fn new(key: impl Into<Str<'text>>, value: impl Into<Value<'text>>) -> Self {
+        Self {
+            prefix: None,
+            key: key.into(),
+            gap: None,
+            value: value.into(),
+            conditional: None,
+            conditional_repr: None,
+            suffix: Cow::default(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        self.key.value()
+    }
+
+    /// Returns the key along with its formatting
+    pub fn key_str(&self) -> &Str<'text> {
+        &self.key
+    }
+
+    /// Renames the pair while trying to keep the key's quoting style
+    pub fn set_key(&mut self, key: impl Into<Cow<'text, str>>) {
+        self.key.set_value(key);
+    }
+
+    pub fn value(&self) -> &Value<'text> {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut Value<'text> {
+        &mut self.value
+    }
+
+    /// Replaces the pair's value returning the old one
+    ///
+    /// Replacing a string with another string keeps the original quoting style where possible
+    /// while switching between a string and an object resets the spacing between the key and
+    /// value
+    pub fn set_value(&mut self, value: impl Into<Value<'text>>) -> Value<'text> {
+        let value = match (value.into(), &self.value) {
+            (Value::Str(new), Value::Str(old)) => Value::Str(new.restyle(old)),
+            (new, old) => {
+                if new.is_str() != old.is_str() {
+                    self.gap = None;
+                }
+                new
+            }
+        };
+
+        std::mem::replace(&mut self.value, value)
+    }
+
+    /// Returns the conditional tag trailing the value if there is one e.g. `[$WIN32]`
+    pub fn conditional(&self) -> Option<&Conditional<'text>> {
+        self.conditional.as_ref()
+    }
+
+    /// Replaces the pair's conditional returning the old one
+    pub fn set_conditional(
+        &mut self,
+        conditional: Option<Conditional<'text>>,
+    ) -> Option<Conditional<'text>> {
+        self.conditional_repr = None;
+        std::mem::replace(&mut self.conditional, conditional)
+    }
+
+    /// Returns the comment trailing the value on the same line if there is one
+    pub fn trailing_comment(&self) -> Option<&str> {
+        self.suffix
+            .find("//")
+            .map(|idx| self.suffix[idx..].trim_end())
+    }
+}
+
+/// Either a string or an object along with its formatting
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value<'text> {
+    Str(Str<'text>),
+    Obj(Obj<'text>),
+}
+
+impl<'text> Value<'text> {
+    pub fn is_str(&self) -> bool {
+        self.as_str().is_some()
+    }
+
+    pub fn is_obj(&self) -> bool {
+        self.as_obj().is_some()
+    }
+
+    /// Gets the string's value if this is a `Value::Str`
+    pub fn as_str(&self) -> Option<&str> {
+        if let Self::Str(s) = self {
+            Some(s.value())
+        } else {
+            None
+        }
+    }
+
+    /// Gets the inner [`Str`] mutably if this is a `Value::Str`
+    pub fn as_str_mut(&mut self) -> Option<&mut Str<'text>> {
+        if let Self::Str(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_obj(&self) -> Option<&Obj<'text>> {
+        if let Self::Obj(obj) = self {
+            Some(obj)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_obj_mut(&mut self) -> Option<&mut Obj<'text>> {
+        if let Self::Obj(obj) = self {
+            Some(obj)
+        } else {
+            None
+        }
+    }
+
+    /// Converts to the loosely typed [`Value`][crate::Value], dropping all formatting
+    pub fn to_value(&self) -> crate::Value<'text> {
+        match self {
+            Self::Str(s) => crate::Value::Str(s.value.clone()),
+            Self::Obj(obj) => crate::Value::Obj(obj.to_obj()),
+        }
+    }
+}
+
+impl<'text> From<Str<'text>> for Value<'text> {
+    fn from(s: Str<'text>) -> Self {
+        Self::Str(s)
+    }
+}
+
+impl<'text> From<&'text str> for Value<'text> {
+    fn from(s: &'text str) -> Self {
+        Self::Str(Str::new(s))
+    }
+}
+
+impl From<String> for Value<'_> {
+    fn from(s: String) -> Self {
+        Self::Str(Str::new(s))
+    }
+}
+
+impl<'text> From<Cow<'text, str>> for Value<'text> {
+    fn from(s: Cow<'text, str>) -> Self {
+        Self::Str(Str::new(s))
+    }
+}
+
+impl<'text> From<Obj<'text>> for Value<'text> {
+    fn from(obj: Obj<'text>) -> Self {
+        Self::Obj(obj)
+    }
+}
+
+/// An object holding its pairs in their original order
+///
+/// Keys can be repeated within an object. Methods that take a key operate on the first matching
+/// pair unless stated otherwise
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Obj<'text> {
+    pairs: Vec<Pair<'text>>,
+    /// Trivia between the last pair and the closing `}`
+    trailing: Option<Cow<'text, str>>,
+}
+
+impl<'text> Obj<'text> {
+    /// Creates an empty object that will use default formatting when rendered
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Iterates over all of the pairs in order
+    pub fn iter(&self) -> std::slice::Iter<'_, Pair<'text>> {
+        self.pairs.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Pair<'text>> {
+        self.pairs.iter_mut()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    /// Returns the value of the first pair with a matching key
+    pub fn get(&self, key: &str) -> Option<&Value<'text>> {
+        self.get_pair(key).map(Pair::value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value<'text>> {
+        self.get_pair_mut(key).map(Pair::value_mut)
+    }
+
+    /// Returns the values of all of the pairs with a matching key in order
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a Value<'text>> + 'a {
+        self.pairs
+            .iter()
+            .filter(move |pair| pair.key() == key)
+            .map(Pair::value)
+    }
+
+    pub fn get_pair(&self, key: &str) -> Option<&Pair<'text>> {
+        self.pairs.iter().find(|pair| pair.key() == key)
+    }
+
+    pub fn get_pair_mut(&mut self, key: &str) -> Option<&mut Pair<'text>> {
+        self.pairs.iter_mut().find(|pair| pair.key() == key)
+    }
+
+    /// Sets the value for the first pair matching `key` or appends a new pair if there is none
+    ///
+    /// Returns the old value if there was one
+    pub fn insert(
+        &mut self,
+        key: impl Into<Str<'text>>,
+        value: impl Into<Value<'text>>,
+    ) -> Option<Value<'text>> {
+        let key = key.into();
+        match self.get_pair_mut(key.value()) {
+            Some(pair) => Some(pair.set_value(value)),
+            None => {
+                self.push(Pair::new(key, value));
+                None
+            }
+        }
+    }
+
+    /// Appends a pair to the end of the object even if the key is already present
+    ///
+    /// Pairs without any formatting of their own copy the formatting of their last sibling
+    pub fn push(&mut self, mut pair: Pair<'text>) {
+        if let Some(last) = self.pairs.last() {
+            if pair.prefix.is_none() {
+                pair.prefix = last.prefix.as_deref().map(|prefix| {
+                    // Keep the indentation, but leave behind any comments
+                    match prefix.rfind('\n') {
+                        Some(idx) => Cow::from(prefix[idx..].to_owned()),
+                        None => Cow::from(prefix.to_owned()),
+                    }
+                });
+            }
+            if pair.gap.is_none() && pair.value.is_str() && last.value.is_str() {
+                pair.gap = last.gap.clone();
+            }
+            pair.key = pair.key.restyle(&last.key);
+        } else if self
+            .trailing
+            .as_deref()
+            .map_or(false, |trailing| !trailing.contains('\n'))
+        {
+            // Expand out a compact empty object like `{}`
+            self.trailing = None;
+        }
+
+        self.pairs.push(pair);
+    }
+
+    /// Removes the first pair matching `key` along with its formatting
+    pub fn remove(&mut self, key: &str) -> Option<Pair<'text>> {
+        self.position(key).map(|idx| self.pairs.remove(idx))
+    }
+
+    /// Removes all of the pairs matching `key` returning how many were removed
+    pub fn remove_all(&mut self, key: &str) -> usize {
+        let len = self.pairs.len();
+        self.pairs.retain(|pair| pair.key() != key);
+        len - self.pairs.len()
+    }
+
+    /// Converts to the loosely typed [`Obj`][crate::Obj], dropping all formatting
+    pub fn to_obj(&self) -> crate::Obj<'text> {
+        let mut obj = crate::Obj::new();
+        for pair in &self.pairs {
+            let key: Key<'text> = pair.key.value.clone();
+            obj.entry(key).or_default().push(pair.value.to_value());
+        }
+        obj
+    }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        self.pairs.iter().position(|pair| pair.key() == key)
+    }
+}
+
+impl<'a, 'text> IntoIterator for &'a Obj<'text> {
+    type Item = &'a Pair<'text>;
+    type IntoIter = std::slice::Iter<'a, Pair<'text>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
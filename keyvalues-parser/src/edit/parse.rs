@@ -0,0 +1,253 @@
+use std::borrow::Cow;
+
+use super::{Directive, Document, Obj, Pair, Str, Value};
+use crate::{
+    conditional::Conditional,
+    error::{Expected, ParseError, ParseErrorKind},
+    span::LineIndex,
+    text::{
+        lex::{Lexeme, Scanner, TokenKind},
+        parse::{found, invalid_escapes, unescape},
+    },
+};
+
+/// Parses `text` reporting the same errors as [`crate::Parser::parse()`] with default settings
+pub(super) fn parse(text: &str, escaped: bool) -> Result<Document<'_>, ParseError> {
+    let mut parser = DocParser {
+        scanner: Scanner::new(text, escaped),
+        trivia_start: 0,
+        escaped,
+    };
+
+    let mut directives = Vec::new();
+    while let Some(directive) = parser.directive() {
+        directives.push(directive);
+    }
+    let prefix = parser.trivia();
+    let root = parser.root(prefix)?;
+    parser.end()?;
+    let trailer = Cow::from(&text[parser.trivia_start..]);
+
+    Ok(Document {
+        directives,
+        root,
+        trailer,
+        escaped,
+    })
+}
+
+struct DocParser<'text> {
+    scanner: Scanner<'text>,
+    /// Where the trivia that hasn't been claimed by anything yet starts
+    trivia_start: usize,
+    escaped: bool,
+}
+
+impl<'text> DocParser<'text> {
+    fn text(&self) -> &'text str {
+        self.scanner.text()
+    }
+
+    /// Skips over trivia returning the next significant token without consuming it
+    fn peek(&mut self) -> Option<Lexeme> {
+        loop {
+            let pos = self.scanner.pos();
+            let token = self.scanner.next()?;
+            if !token.kind.is_trivia() {
+                self.scanner.reset(pos);
+                return Some(token);
+            }
+        }
+    }
+
+    /// Consumes the token that was just peeked
+    fn bump(&mut self, token: &Lexeme) {
+        self.scanner.reset(token.range.end);
+        self.trivia_start = token.range.end;
+    }
+
+    fn unexpected(&self, token: Option<&Lexeme>, expected: Vec<Expected>) -> ParseError {
+        let end = self.text().len();
+        let range = token.map_or(end..end, |token| token.range.clone());
+        let kind = ParseErrorKind::Unexpected {
+            found: found(token),
+            expected,
+        };
+        ParseError::new(self.text(), kind, range)
+    }
+
+    /// Only trivia and an optional trailing null byte can come after the top-level pair
+    fn end(&self) -> Result<(), ParseError> {
+        match self.scanner.clone().end() {
+            Some(token) => Err(self.unexpected(Some(&token), vec![Expected::EndOfInput])),
+            None => Ok(()),
+        }
+    }
+
+    /// Claims all of the trivia up to the next significant token
+    fn trivia(&mut self) -> Cow<'text, str> {
+        let end = self
+            .peek()
+            .map_or(self.text().len(), |token| token.range.start);
+        let trivia = &self.text()[self.trivia_start..end];
+        self.trivia_start = end;
+        Cow::from(trivia)
+    }
+
+    /// Claims the trivia following a value up to the end of its line
+    fn suffix(&mut self) -> Cow<'text, str> {
+        let end = self
+            .peek()
+            .map_or(self.text().len(), |token| token.range.start);
+        let trivia = &self.text()[self.trivia_start..end];
+        match trivia.find('\n') {
+            Some(newline) => {
+                self.trivia_start += newline;
+                Cow::from(&trivia[..newline])
+            }
+            // Trivia that doesn't end the line is left for whatever follows
+            None => Cow::default(),
+        }
+    }
+
+    fn directive(&mut self) -> Option<Directive<'text>> {
+        let directive = self.scanner.directive()?;
+        let text = self.text();
+        let prefix = Cow::from(&text[self.trivia_start..directive.keyword.start]);
+        let keyword = Cow::from(&text[directive.keyword.clone()]);
+        // Empty when the path is glued onto the keyword e.g. `#basefoo.vdf`
+        let gap = Cow::from(&text[directive.keyword.end..directive.path.range.start]);
+        // Base paths are never escaped
+        let path_str = &text[directive.path.range.clone()];
+        let path = self.str_from(path_str, directive.path.kind, false);
+        self.trivia_start = directive.path.range.end;
+
+        Some(Directive {
+            prefix,
+            keyword,
+            gap,
+            path,
+        })
+    }
+
+    fn root(&mut self, prefix: Cow<'text, str>) -> Result<Pair<'text>, ParseError> {
+        match self.peek() {
+            Some(token) if !matches!(token.kind, TokenKind::ObjOpen | TokenKind::ObjClose) => {
+                self.pair(prefix)
+            }
+            token => Err(self.unexpected(token.as_ref(), vec![Expected::Key])),
+        }
+    }
+
+    fn pair(&mut self, prefix: Cow<'text, str>) -> Result<Pair<'text>, ParseError> {
+        let key = self.str()?;
+        let gap = self.trivia();
+        let value = match self.peek() {
+            Some(token) if token.kind == TokenKind::ObjOpen => Value::Obj(self.obj(token)?),
+            Some(token) if token.kind != TokenKind::ObjClose => Value::Str(self.str()?),
+            token => return Err(self.unexpected(token.as_ref(), vec![Expected::Value])),
+        };
+        let (conditional_repr, conditional) = self.conditional().unzip();
+        let suffix = self.suffix();
+
+        Ok(Pair {
+            prefix: Some(prefix),
+            key,
+            gap: Some(gap),
+            value,
+            conditional,
+            conditional_repr,
+            suffix,
+        })
+    }
+
+    /// Claims a conditional trailing a value along with the trivia before it
+    fn conditional(&mut self) -> Option<(Cow<'text, str>, Conditional<'text>)> {
+        let (conditional, range) = self.scanner.conditional()?;
+        let repr = Cow::from(&self.text()[self.trivia_start..range.end]);
+        self.trivia_start = range.end;
+        Some((repr, conditional))
+    }
+
+    fn obj(&mut self, open: Lexeme) -> Result<Obj<'text>, ParseError> {
+        self.bump(&open);
+
+        let mut pairs = Vec::new();
+        loop {
+            let prefix = self.trivia();
+            match self.peek() {
+                None => {
+                    let text = self.text();
+                    let lines = LineIndex::new(text);
+                    let open = lines.location(open.range.start);
+                    let kind = ParseErrorKind::UnclosedObject { open };
+                    return Err(ParseError::with_lines(&lines, kind, text.len()..text.len()));
+                }
+                Some(close) if close.kind == TokenKind::ObjClose => {
+                    self.bump(&close);
+                    return Ok(Obj {
+                        pairs,
+                        trailing: Some(prefix),
+                    });
+                }
+                Some(token) if token.kind == TokenKind::ObjOpen => {
+                    let expected = vec![Expected::Key, Expected::ObjClose];
+                    return Err(self.unexpected(Some(&token), expected));
+                }
+                Some(_) => pairs.push(self.pair(prefix)?),
+            }
+        }
+    }
+
+    /// Consumes the string that was just peeked
+    fn str(&mut self) -> Result<Str<'text>, ParseError> {
+        let token = self.peek().expect("Only called when a string comes next");
+        let text = self.text();
+        match token.kind {
+            TokenKind::UnterminatedStr => {
+                // The error stops at the end of the line like with the regular parser
+                let start = token.range.start;
+                let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+                return Err(ParseError::new(
+                    text,
+                    ParseErrorKind::UnclosedString,
+                    start..end,
+                ));
+            }
+            TokenKind::QuotedStr if self.escaped => {
+                let contents = token.contents();
+                if let Some(invalid) = invalid_escapes(&text[contents.clone()]).next() {
+                    let range = contents.start + invalid.start..contents.start + invalid.end;
+                    let sequence = text[range.clone()].to_owned();
+                    let kind = ParseErrorKind::InvalidEscape { sequence };
+                    return Err(ParseError::new(text, kind, range));
+                }
+            }
+            _ => {}
+        }
+
+        self.bump(&token);
+        let repr = &text[token.range];
+        Ok(self.str_from(repr, token.kind, self.escaped))
+    }
+
+    fn str_from(&self, repr: &'text str, kind: TokenKind, escaped: bool) -> Str<'text> {
+        let quoted = kind == TokenKind::QuotedStr;
+        let value = if quoted {
+            let inner = &repr[1..repr.len() - 1];
+            if escaped {
+                unescape(inner)
+            } else {
+                Cow::from(inner)
+            }
+        } else {
+            Cow::from(repr)
+        };
+
+        Str {
+            value,
+            repr: Some(Cow::from(repr)),
+            quoted,
+        }
+    }
+}
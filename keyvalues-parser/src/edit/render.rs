@@ -0,0 +1,149 @@
+use std::fmt::{self, Write};
+
+use super::{Document, Obj, Pair, Str, Value};
+use crate::{
+    error::RenderError,
+    text::{find_invalid_raw_char, write_str, RenderType},
+};
+
+fn write_indent(writer: &mut impl Write, num_indents: usize) -> fmt::Result {
+    writer.write_char('\n')?;
+    for _ in 0..num_indents {
+        writer.write_char('\t')?;
+    }
+    Ok(())
+}
+
+impl Document<'_> {
+    /// Renders the document to text, keeping the formatting of everything that wasn't modified
+    ///
+    /// # Errors
+    ///
+    /// Directive paths are never escaped and neither are any strings in documents parsed with
+    /// [`Document::parse_raw()`], so this will return an error if one containing a `"` was added
+    pub fn render(&self, writer: &mut impl Write) -> Result<(), RenderError> {
+        if let Some(invalid_char) = self.find_invalid_raw_char() {
+            return Err(RenderError::InvalidRawChar { invalid_char });
+        }
+
+        self._render(writer).map_err(Into::into)
+    }
+
+    pub(super) fn _render(&self, writer: &mut impl Write) -> fmt::Result {
+        for directive in &self.directives {
+            writer.write_str(&directive.prefix)?;
+            writer.write_str(&directive.keyword)?;
+            writer.write_str(&directive.gap)?;
+            // Base paths are never escaped
+            directive.path.write(writer, RenderType::Raw)?;
+        }
+
+        let render_type = self.render_type();
+        self.root.write(writer, 0, render_type)?;
+        writer.write_str(&self.trailer)
+    }
+
+    fn render_type(&self) -> RenderType {
+        if self.escaped {
+            RenderType::Escaped
+        } else {
+            RenderType::Raw
+        }
+    }
+
+    fn find_invalid_raw_char(&self) -> Option<char> {
+        let directives = self
+            .directives
+            .iter()
+            .find_map(|directive| directive.path.find_invalid_raw_char());
+        directives.or_else(|| {
+            if self.escaped {
+                None
+            } else {
+                self.root.find_invalid_raw_char()
+            }
+        })
+    }
+}
+
+impl Str<'_> {
+    fn write(&self, writer: &mut impl Write, render_type: RenderType) -> fmt::Result {
+        match (&self.repr, self.quoted) {
+            (Some(repr), _) => writer.write_str(repr),
+            (None, true) => write_str(writer, &self.value, render_type),
+            (None, false) => writer.write_str(&self.value),
+        }
+    }
+
+    fn find_invalid_raw_char(&self) -> Option<char> {
+        match self.repr {
+            Some(_) => None,
+            None => find_invalid_raw_char(&self.value),
+        }
+    }
+}
+
+impl Pair<'_> {
+    fn write(
+        &self,
+        writer: &mut impl Write,
+        num_indents: usize,
+        render_type: RenderType,
+    ) -> fmt::Result {
+        match &self.prefix {
+            Some(prefix) => writer.write_str(prefix)?,
+            // The top-level pair doesn't get anything before it by default
+            None if num_indents == 0 => {}
+            None => write_indent(writer, num_indents)?,
+        }
+
+        self.key.write(writer, render_type)?;
+
+        match (&self.gap, &self.value) {
+            (Some(gap), _) => writer.write_str(gap)?,
+            (None, Value::Str(_)) => writer.write_char('\t')?,
+            (None, Value::Obj(_)) => write_indent(writer, num_indents)?,
+        }
+
+        match &self.value {
+            Value::Str(s) => s.write(writer, render_type)?,
+            Value::Obj(obj) => obj.write(writer, num_indents, render_type)?,
+        }
+
+        match (&self.conditional_repr, &self.conditional) {
+            (Some(repr), _) => writer.write_str(repr)?,
+            (None, Some(conditional)) => write!(writer, " {conditional}")?,
+            (None, None) => {}
+        }
+
+        writer.write_str(&self.suffix)
+    }
+
+    fn find_invalid_raw_char(&self) -> Option<char> {
+        self.key
+            .find_invalid_raw_char()
+            .or_else(|| match &self.value {
+                Value::Str(s) => s.find_invalid_raw_char(),
+                Value::Obj(obj) => obj.iter().find_map(Pair::find_invalid_raw_char),
+            })
+    }
+}
+
+impl Obj<'_> {
+    fn write(
+        &self,
+        writer: &mut impl Write,
+        num_indents: usize,
+        render_type: RenderType,
+    ) -> fmt::Result {
+        writer.write_char('{')?;
+        for pair in &self.pairs {
+            pair.write(writer, num_indents + 1, render_type)?;
+        }
+        match &self.trailing {
+            Some(trailing) => writer.write_str(trailing)?,
+            None => write_indent(writer, num_indents)?,
+        }
+        writer.write_char('}')
+    }
+}
@@ -0,0 +1,210 @@
+//! Detecting and converting between the text encodings that VDF files show up in
+//!
+//! Most VDF text is UTF-8, but Valve's localization files are UTF-16 with a byte order mark
+//! (BOM) and older configs tend to be Windows-1252. [`Detected::detect()`] sniffs out the BOM
+//! and otherwise falls back to a legacy encoding when the text isn't valid UTF-8. This is what
+//! [`Parser::parse_bytes()`][crate::Parser::parse_bytes] uses under the hood
+//!
+//! ```
+//! use keyvalues_parser::encoding::{Detected, Encoding};
+//!
+//! // "key" "välue" in UTF-16 LE with a BOM
+//! let mut bytes = vec![0xff, 0xfe];
+//! bytes.extend("\"key\" \"välue\"".encode_utf16().flat_map(u16::to_le_bytes));
+//!
+//! let detected = Detected::detect(&bytes, Encoding::Windows1252);
+//! assert_eq!(detected, Detected { encoding: Encoding::Utf16Le, bom: true });
+//! let text = detected.decode(&bytes)?;
+//! assert_eq!(text, "\"key\" \"välue\"");
+//!
+//! // and encoding goes back to the exact same bytes
+//! assert_eq!(detected.encode(&text)?, bytes);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{borrow::Cow, fmt};
+
+use crate::error::{DecodeError, RenderError};
+
+/// What bytes `0x80` through `0x9f` map to in Windows-1252
+///
+/// The five bytes that are undefined map to the matching C1 control characters like browsers do
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+/// The text encodings that can be detected and converted between
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum Encoding {
+    Utf8,
+    /// UTF-16 little-endian which is what Valve's localization files use
+    Utf16Le,
+    /// UTF-16 big-endian
+    Utf16Be,
+    /// The legacy Windows code page for western languages
+    Windows1252,
+    /// ISO-8859-1 where every byte maps directly to the matching `char`
+    Latin1,
+}
+
+impl Encoding {
+    /// Returns the byte order mark for the encoding if it has one
+    pub const fn bom(self) -> Option<&'static [u8]> {
+        match self {
+            Self::Utf8 => Some(&[0xef, 0xbb, 0xbf]),
+            Self::Utf16Le => Some(&[0xff, 0xfe]),
+            Self::Utf16Be => Some(&[0xfe, 0xff]),
+            Self::Windows1252 | Self::Latin1 => None,
+        }
+    }
+
+    /// Decodes `bytes` which shouldn't include a BOM
+    ///
+    /// Only UTF-8 and single-byte encodings of ASCII text get borrowed
+    pub fn decode(self, bytes: &[u8]) -> Result<Cow<'_, str>, DecodeError> {
+        let error = |offset| DecodeError::new(self, offset);
+        match self {
+            Self::Utf8 => std::str::from_utf8(bytes)
+                .map(Cow::from)
+                .map_err(|err| error(err.valid_up_to())),
+            Self::Utf16Le | Self::Utf16Be => {
+                if bytes.len() % 2 != 0 {
+                    return Err(error(bytes.len() - 1));
+                }
+                let units = bytes.chunks_exact(2).map(|pair| {
+                    let pair = [pair[0], pair[1]];
+                    if self == Self::Utf16Le {
+                        u16::from_le_bytes(pair)
+                    } else {
+                        u16::from_be_bytes(pair)
+                    }
+                });
+
+                let mut text = String::with_capacity(bytes.len() / 2);
+                for c in char::decode_utf16(units) {
+                    // Surrogates that aren't paired up can't be represented
+                    let c = c.map_err(|_| error(text.encode_utf16().count() * 2))?;
+                    text.push(c);
+                }
+                Ok(Cow::from(text))
+            }
+            Self::Windows1252 | Self::Latin1 => {
+                if bytes.is_ascii() {
+                    let text = std::str::from_utf8(bytes).expect("ASCII is valid UTF-8");
+                    return Ok(Cow::from(text));
+                }
+                let text = bytes
+                    .iter()
+                    .map(|&b| match b {
+                        0x80..=0x9f if self == Self::Windows1252 => {
+                            WINDOWS_1252_HIGH[usize::from(b - 0x80)]
+                        }
+                        _ => char::from(b),
+                    })
+                    .collect();
+                Ok(Cow::Owned(text))
+            }
+        }
+    }
+
+    /// Encodes `text` without a BOM
+    ///
+    /// Fails with [`RenderError::UnencodableChar`] if a `char` can't be represented in a
+    /// single-byte encoding
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, RenderError> {
+        match self {
+            Self::Utf8 => Ok(text.as_bytes().to_vec()),
+            Self::Utf16Le => Ok(text.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            Self::Utf16Be => Ok(text.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+            Self::Windows1252 | Self::Latin1 => text
+                .chars()
+                .map(|c| {
+                    let high = WINDOWS_1252_HIGH.iter().position(|&high| high == c);
+                    match (u8::try_from(c), high) {
+                        (Ok(b), _) if self == Self::Latin1 || !(0x80..=0x9f).contains(&b) => Ok(b),
+                        (_, Some(pos)) if self == Self::Windows1252 => Ok(0x80 + pos as u8),
+                        _ => Err(RenderError::UnencodableChar {
+                            invalid_char: c,
+                            encoding: self,
+                        }),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Windows1252 => "Windows-1252",
+            Self::Latin1 => "ISO-8859-1",
+        })
+    }
+}
+
+/// An [`Encoding`] along with whether the text started with a BOM
+///
+/// Hold onto this to write text back out the same way that it was read
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Detected {
+    pub encoding: Encoding,
+    pub bom: bool,
+}
+
+impl Detected {
+    /// Figures out the encoding of `bytes`
+    ///
+    /// A UTF-8 or UTF-16 BOM always wins. Without one the text is UTF-8 when it's valid UTF-8
+    /// and `fallback` otherwise
+    pub fn detect(bytes: &[u8], fallback: Encoding) -> Self {
+        let with_bom = [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be]
+            .into_iter()
+            .find(|encoding| encoding.bom().map_or(false, |bom| bytes.starts_with(bom)));
+        match with_bom {
+            Some(encoding) => Self {
+                encoding,
+                bom: true,
+            },
+            None => Self {
+                encoding: if std::str::from_utf8(bytes).is_ok() {
+                    Encoding::Utf8
+                } else {
+                    fallback
+                },
+                bom: false,
+            },
+        }
+    }
+
+    /// Decodes `bytes` skipping over the BOM if there is one
+    ///
+    /// Offsets in errors are from the start of `bytes` including the BOM
+    pub fn decode(self, bytes: &[u8]) -> Result<Cow<'_, str>, DecodeError> {
+        let (bytes, skipped) = match bytes.strip_prefix(self.bom_bytes()) {
+            Some(rest) => (rest, self.bom_bytes().len()),
+            None => (bytes, 0),
+        };
+        self.encoding
+            .decode(bytes)
+            .map_err(|err| DecodeError::new(err.encoding(), err.offset() + skipped))
+    }
+
+    /// Encodes `text` starting with a BOM if there was one
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, RenderError> {
+        let mut bytes = self.bom_bytes().to_vec();
+        bytes.extend(self.encoding.encode(text)?);
+        Ok(bytes)
+    }
+
+    fn bom_bytes(self) -> &'static [u8] {
+        self.encoding.bom().filter(|_| self.bom).unwrap_or_default()
+    }
+}
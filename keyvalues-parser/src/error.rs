@@ -1,59 +1,487 @@
 //! All error information for parsing and rendering
 
-use std::fmt;
+use std::{fmt, io, ops::Range};
 
+#[cfg(feature = "pest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pest")))]
 #[doc(inline)]
 pub use crate::text::parse::{EscapedPestError, RawPestError};
+use crate::{
+    encoding::Encoding,
+    span::{LineIndex, Location, Span},
+};
 
-/// Just a type alias for `Result` with a [`Error`]
-pub type Result<T> = std::result::Result<T, Error>;
+/// Just a type alias for `Result` with an [`Error`] as the default error type
+pub type Result<T, E = Error> = std::result::Result<T, E>;
 
-// TODO: Swap out the `EscapedParseError` and `RawParseError` for an opaque `Error::Parse` variant
-// that handles displaying the error
-// TODO: should this whole thing be overhauled (future me here: yes)
-// TODO: split the `Error` into a separate parse and render error
-
-/// All possible errors when parsing or rendering VDF text
+/// Either a [`ParseError`], [`RenderError`], or [`DecodeError`]
 ///
-/// Currently the two variants are parse errors which currently only occurs when `pest` encounters
+/// Parsing and rendering return their own specific error types. This exists for when you want to
+/// `?` both within the same function
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Error {
-    EscapedParseError(EscapedPestError),
-    RawParseError(RawPestError),
-    RenderError(fmt::Error),
-    RawRenderError { invalid_char: char },
+    Parse(ParseError),
+    Render(RenderError),
+    Decode(DecodeError),
 }
 
-impl From<EscapedPestError> for Error {
-    fn from(e: EscapedPestError) -> Self {
-        Self::EscapedParseError(e)
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
     }
 }
 
-impl From<RawPestError> for Error {
-    fn from(e: RawPestError) -> Self {
-        Self::RawParseError(e)
+impl From<RenderError> for Error {
+    fn from(e: RenderError) -> Self {
+        Self::Render(e)
     }
 }
 
-impl From<std::fmt::Error> for Error {
-    fn from(e: std::fmt::Error) -> Self {
-        Self::RenderError(e)
+impl From<DecodeError> for Error {
+    fn from(e: DecodeError) -> Self {
+        Self::Decode(e)
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::EscapedParseError(e) => write!(f, "Failed parsing input Error: {e}"),
-            Self::RawParseError(e) => write!(f, "Failed parsing input Error: {e}"),
-            Self::RenderError(e) => write!(f, "Failed rendering input Error: {e}"),
-            Self::RawRenderError { invalid_char } => write!(
+            Self::Parse(e) => write!(f, "Failed parsing input Error: {e}"),
+            Self::Render(e) => write!(f, "Failed rendering input Error: {e}"),
+            Self::Decode(e) => write!(f, "Failed decoding input Error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(e) => Some(e),
+            Self::Render(e) => Some(e),
+            Self::Decode(e) => Some(e),
+        }
+    }
+}
+
+/// An error encountered while parsing VDF text
+///
+/// Along with the [kind][ParseErrorKind] of error this keeps track of where the error happened
+/// and a rendered snippet of the offending line
+///
+/// ```
+/// use keyvalues_parser::error::ParseErrorKind;
+///
+/// let err = keyvalues_parser::parse("outer\n{\n\tkey value\n").unwrap_err();
+/// assert_eq!((err.line(), err.column()), (4, 1));
+/// assert!(matches!(err.kind(), ParseErrorKind::UnclosedObject { .. }));
+/// assert_eq!(err.kind().to_string(), "unclosed object started at 2:1");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    span: Span,
+    snippet: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(text: &str, kind: ParseErrorKind, range: Range<usize>) -> Self {
+        Self::with_lines(&LineIndex::new(text), kind, range)
+    }
+
+    /// Same as [`ParseError::new()`] while re-using an existing [`LineIndex`]
+    pub(crate) fn with_lines(
+        lines: &LineIndex<'_>,
+        kind: ParseErrorKind,
+        range: Range<usize>,
+    ) -> Self {
+        let span = lines.span(range);
+        let snippet = render_snippet(lines.text(), &span);
+        Self {
+            kind,
+            span,
+            snippet,
+        }
+    }
+
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    /// The region of text the error points at
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The byte offset where the error starts
+    pub fn offset(&self) -> usize {
+        self.span.start.offset
+    }
+
+    /// The 1-based line where the error starts
+    pub fn line(&self) -> usize {
+        self.span.start.line
+    }
+
+    /// The 1-based column where the error starts counted in `char`s
+    pub fn column(&self) -> usize {
+        self.span.start.column
+    }
+
+    /// The offending line along with a caret pointing at the error
+    ///
+    /// ```
+    /// let err = keyvalues_parser::parse("key\n{\n\tinner }").unwrap_err();
+    /// assert_eq!(err.snippet(), "  |\n3 | \tinner }\n  | \t      ^");
+    /// ```
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}\n{}",
+            self.line(),
+            self.column(),
+            self.kind,
+            self.snippet
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn render_snippet(text: &str, span: &Span) -> String {
+    let line_start = text[..span.start.offset]
+        .rfind('\n')
+        .map_or(0, |idx| idx + 1);
+    let line_end = text[line_start..]
+        .find('\n')
+        .map_or(text.len(), |idx| line_start + idx);
+    let line = text[line_start..line_end].trim_end_matches('\r');
+
+    let line_num = span.start.line.to_string();
+    let gutter = " ".repeat(line_num.len());
+    // Keep tabs so that the caret lines up with the text above it
+    let caret_offset: String = line
+        .chars()
+        .take(span.start.column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    // Errors can point at the trimmed `\r` or the end of the text, so this can't use `clamp()`
+    let caret_end = span
+        .end
+        .offset
+        .min(line_start + line.len())
+        .max(span.start.offset);
+    let caret_len = text[span.start.offset..caret_end].chars().count().max(1);
+
+    format!(
+        "{gutter} |\n{line_num} | {line}\n{gutter} | {caret_offset}{}",
+        "^".repeat(caret_len)
+    )
+}
+
+/// The different kinds of [`ParseError`]s
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// The text ended before the closing `}` of the object that started at `open`
+    UnclosedObject { open: Location },
+    /// A quoted string is missing its closing `"`
+    UnclosedString,
+    /// An escape sequence other than `\n`, `\r`, `\t`, `\\`, or `\"` when parsing with escapes
+    InvalidEscape { sequence: String },
+    /// Found something other than one of the `expected` items
+    Unexpected {
+        found: Found,
+        expected: Vec<Expected>,
+    },
+    /// Objects were nested deeper than [`Parser::max_depth()`][crate::Parser::max_depth]
+    DepthLimitExceeded { limit: usize },
+    /// A string was longer than [`Parser::max_str_len()`][crate::Parser::max_str_len]
+    StrLenLimitExceeded { limit: usize },
+    /// There were more pairs than [`Parser::max_pairs()`][crate::Parser::max_pairs]
+    PairLimitExceeded { limit: usize },
+    /// The text was larger than [`Parser::max_input_len()`][crate::Parser::max_input_len]
+    InputLenLimitExceeded { limit: usize },
+    /// A key showed up more than once in the same object with
+    /// [`DuplicateKeys::Error`][crate::DuplicateKeys::Error]
+    ///
+    /// The error points at the later occurrence while `first` is where the key was first seen
+    DuplicateKey { key: String, first: Location },
+    /// Something other than a comment follows a `#base` or `#include` directive on the same line
+    ///
+    /// Only checked by [`Parser::strict()`][crate::Parser::strict]
+    DirectiveSharesLine,
+    /// An unquoted string contains `//` which looks like the start of a comment
+    ///
+    /// Only checked by [`Parser::strict()`][crate::Parser::strict]
+    CommentInUnquotedStr,
+    /// A string starts right where the previous token ends e.g. `"key"value`
+    ///
+    /// Only checked by [`Parser::strict()`][crate::Parser::strict]
+    MissingSeparator,
+    /// A string contains a control character other than a tab or newline
+    ///
+    /// Only checked by [`Parser::strict()`][crate::Parser::strict]
+    NonPrintableChar { invalid_char: char },
+    /// A conditional tag e.g. `[$WIN32]` was thrown away since the sorted representation has
+    /// nowhere to store it
+    ///
+    /// Only reported as a warning by
+    /// [`Parser::parse_with_warnings()`][crate::Parser::parse_with_warnings]
+    DroppedConditional,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnclosedObject { open } => write!(
+                f,
+                "unclosed object started at {}:{}",
+                open.line, open.column
+            ),
+            Self::UnclosedString => f.write_str("unclosed string"),
+            Self::InvalidEscape { sequence } => write!(f, "invalid escape sequence `{sequence}`"),
+            Self::Unexpected { found, expected } => match expected.as_slice() {
+                [] => write!(f, "unexpected {found}"),
+                [expected] => write!(f, "expected {expected}, found {found}"),
+                [rest @ .., last] => {
+                    f.write_str("expected one of ")?;
+                    for (i, item) in rest.iter().enumerate() {
+                        if i != 0 {
+                            f.write_str(", ")?;
+                        }
+                        write!(f, "{item}")?;
+                    }
+                    write!(f, " or {last}, found {found}")
+                }
+            },
+            Self::DepthLimitExceeded { limit } => {
+                write!(f, "objects are nested deeper than the limit of {limit}")
+            }
+            Self::StrLenLimitExceeded { limit } => {
+                write!(f, "string is longer than the limit of {limit} bytes")
+            }
+            Self::PairLimitExceeded { limit } => write!(f, "more pairs than the limit of {limit}"),
+            Self::InputLenLimitExceeded { limit } => {
+                write!(f, "input is larger than the limit of {limit} bytes")
+            }
+            Self::DuplicateKey { key, first } => write!(
+                f,
+                "duplicate key {key:?} first seen at {}:{}",
+                first.line, first.column
+            ),
+            Self::DirectiveSharesLine => f.write_str("directives must be on their own line"),
+            Self::CommentInUnquotedStr => f.write_str("unquoted string contains `//`"),
+            Self::MissingSeparator => {
+                f.write_str("expected whitespace or a brace before the start of the string")
+            }
+            Self::NonPrintableChar { invalid_char } => {
+                write!(
+                    f,
+                    "string contains the non-printable character {invalid_char:?}"
+                )
+            }
+            Self::DroppedConditional => f.write_str(
+                "conditional was dropped since only the ordered representation keeps them",
+            ),
+        }
+    }
+}
+
+/// What was found in place of what was [`Expected`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Found {
+    EndOfInput,
+    /// A `{`
+    ObjOpen,
+    /// A `}`
+    ObjClose,
+    /// A quoted or unquoted string
+    Str,
+}
+
+impl fmt::Display for Found {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::EndOfInput => "end of input",
+            Self::ObjOpen => "`{`",
+            Self::ObjClose => "`}`",
+            Self::Str => "a string",
+        })
+    }
+}
+
+/// Something that the parser would have accepted at the location of an error
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expected {
+    Key,
+    Value,
+    /// The `}` that closes an object
+    ObjClose,
+    EndOfInput,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Key => "a key",
+            Self::Value => "a value",
+            Self::ObjClose => "`}`",
+            Self::EndOfInput => "end of input",
+        })
+    }
+}
+
+/// An error encountered while rendering VDF text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenderError {
+    /// The underlying writer returned an error
+    Fmt(fmt::Error),
+    /// The underlying [`io::Write`] returned an error
+    ///
+    /// This holds onto the error's kind and message since [`io::Error`] can't be cloned or
+    /// compared
+    Io {
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// A string contained a character that can't be represented without escapes
+    ///
+    /// Use `validate_raw()` to find every string that can't be
+    InvalidRawChar { invalid_char: char },
+    /// The text contained a character that the [`Encoding`] can't represent
+    UnencodableChar {
+        invalid_char: char,
+        encoding: Encoding,
+    },
+}
+
+impl From<fmt::Error> for RenderError {
+    fn from(e: fmt::Error) -> Self {
+        Self::Fmt(e)
+    }
+}
+
+impl From<io::Error> for RenderError {
+    fn from(e: io::Error) -> Self {
+        Self::Io {
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fmt(e) => write!(f, "Failed writing output Error: {e}"),
+            Self::Io { message, .. } => write!(f, "Failed writing output Error: {message}"),
+            Self::InvalidRawChar { invalid_char } => write!(
                 f,
                 "Encountered invalid character in raw string: {invalid_char:?}"
             ),
+            Self::UnencodableChar {
+                invalid_char,
+                encoding,
+            } => write!(
+                f,
+                "Encountered character that {encoding} can't represent: {invalid_char:?}"
+            ),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for RenderError {}
+
+/// A string that can't be rendered raw along with where it is
+///
+/// Returned by the `validate_raw()` methods
+///
+/// ```
+/// use keyvalues_parser::Vdf;
+///
+/// let vdf = Vdf::parse(r#"game { "name" "\"Portal\"" }"#)?;
+/// let invalid = vdf.validate_raw();
+/// assert_eq!(invalid[0].path, ["game", "name"]);
+/// assert_eq!(
+///     invalid[0].to_string(),
+///     r#"The value at "game" > "name" contains '"' which can't be rendered raw"#,
+/// );
+/// # Ok::<(), keyvalues_parser::error::Error>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidRawStr {
+    /// The keys leading to the pair holding the string starting with the top-level key
+    ///
+    /// Pairs that share the same key within an object share the same path
+    pub path: Vec<String>,
+    /// Whether the string is the pair's key instead of its value
+    pub in_key: bool,
+    pub invalid_char: char,
+}
+
+impl fmt::Display for InvalidRawStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let part = if self.in_key { "key" } else { "value" };
+        write!(f, "The {part} at ")?;
+        for (i, key) in self.path.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" > ")?;
+            }
+            write!(f, "{key:?}")?;
+        }
+        write!(
+            f,
+            " contains {:?} which can't be rendered raw",
+            self.invalid_char
+        )
+    }
+}
+
+/// An error encountered while decoding bytes to text
+///
+/// ```
+/// use keyvalues_parser::encoding::Encoding;
+///
+/// let err = Encoding::Utf8.decode(b"key \xff").unwrap_err();
+/// assert_eq!(err.offset(), 4);
+/// assert_eq!(err.to_string(), "invalid UTF-8 at byte offset 4");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    encoding: Encoding,
+    offset: usize,
+}
+
+impl DecodeError {
+    pub(crate) fn new(encoding: Encoding, offset: usize) -> Self {
+        Self { encoding, offset }
+    }
+
+    /// The encoding that the bytes failed to decode as
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// The byte offset of the first byte that couldn't be decoded
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid {} at byte offset {}",
+            self.encoding, self.offset
+        )
+    }
+}
+
+impl std::error::Error for DecodeError {}
@@ -0,0 +1,376 @@
+//! A pull-based reader that walks through VDF text one event at a time
+//!
+//! Unlike [`Parser::parse()`] nothing gets built up while reading, so this is a good fit for
+//! pulling a few values out of large documents. Objects are tracked without any recursion and
+//! values that aren't needed can be passed over with [`EventReader::skip_value()`] and
+//! [`EventReader::skip_obj()`]
+//!
+//! ```
+//! use keyvalues_parser::{events::Event, Parser};
+//!
+//! let vdf_text = r#"
+//! "AppState"
+//! {
+//!     "name" "Portal 2"
+//!     "UserConfig" { "installdir" "Not this one" }
+//!     "installdir" "Portal 2"
+//! }
+//! "#;
+//! let mut reader = Parser::new().events(vdf_text);
+//! let mut installdir = None;
+//! while let Some(event) = reader.next() {
+//!     match event? {
+//!         Event::Key { key, .. } if key == "UserConfig" => reader.skip_value()?,
+//!         Event::Key { key, .. } if key == "installdir" => match reader.next().transpose()? {
+//!             Some(Event::Str { value, .. }) => installdir = Some(value),
+//!             _ => unreachable!("the value is a string"),
+//!         },
+//!         _ => {}
+//!     }
+//! }
+//! assert_eq!(installdir.as_deref(), Some("Portal 2"));
+//! # Ok::<(), keyvalues_parser::error::ParseError>(())
+//! ```
+
+use std::{borrow::Cow, iter::FusedIterator, ops::Range};
+
+use crate::{
+    conditional::Conditional,
+    error::{Expected, ParseError, ParseErrorKind},
+    span::{LineCursor, LineIndex, Span},
+    text::{
+        lex::{Lexeme, Scanner, TokenKind},
+        parse::{check_input_len, found, invalid_escapes, unescape},
+    },
+    Key, Parser,
+};
+
+/// A single piece of a VDF document
+///
+/// Every event comes with the [`Span`] of the text it was read from. Strings are kept borrowed
+/// from the original text unless they contain escape sequences
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Event<'text> {
+    /// The path of a `#base` directive, spanning the path including any quotes
+    Base { path: Cow<'text, str>, span: Span },
+    /// The path of an `#include` directive, spanning the path including any quotes
+    Include { path: Cow<'text, str>, span: Span },
+    /// The key of a pair which is always followed by either a [`Event::Str`] or an
+    /// [`Event::ObjBegin`]
+    Key { key: Key<'text>, span: Span },
+    /// A string value
+    Str { value: Cow<'text, str>, span: Span },
+    /// The `{` that opens an object
+    ObjBegin { span: Span },
+    /// The `}` that closes an object
+    ObjEnd { span: Span },
+    /// The conditional tag following a value e.g. `[$WIN32]`
+    Conditional {
+        conditional: Conditional<'text>,
+        span: Span,
+    },
+}
+
+impl Event<'_> {
+    /// Returns the span of the text the event was read from
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Base { span, .. }
+            | Self::Include { span, .. }
+            | Self::Key { span, .. }
+            | Self::Str { span, .. }
+            | Self::ObjBegin { span }
+            | Self::ObjEnd { span }
+            | Self::Conditional { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Start,
+    Directives,
+    Key,
+    Value,
+    AfterValue,
+    End,
+    Done,
+}
+
+/// An iterator over the [`Event`]s of some VDF text
+///
+/// Created with [`Parser::events()`]. Errors are the same as the ones returned by
+/// [`Parser::parse()`] and iteration stops after the first one
+#[derive(Clone, Debug)]
+pub struct EventReader<'text> {
+    scanner: Scanner<'text>,
+    cursor: LineCursor<'text>,
+    parser: Parser,
+    state: State,
+    /// The starting offset of each object that's currently open
+    opens: Vec<usize>,
+    num_pairs: usize,
+}
+
+impl<'text> EventReader<'text> {
+    pub(crate) fn new(parser: &Parser, text: &'text str) -> Self {
+        Self {
+            scanner: Scanner::new(text, !parser.literal_special_chars),
+            cursor: LineCursor::new(text),
+            parser: parser.clone(),
+            state: State::Start,
+            opens: Vec::new(),
+            num_pairs: 0,
+        }
+    }
+
+    /// Returns how many objects are currently open
+    ///
+    /// ```
+    /// use keyvalues_parser::Parser;
+    ///
+    /// let mut reader = Parser::new().events("outer { inner {} }");
+    /// let depths: Vec<_> = std::iter::from_fn(|| {
+    ///     reader.next().map(|event| (event.unwrap().span().start.column, reader.depth()))
+    /// })
+    /// .collect();
+    /// assert_eq!(depths, [(1, 0), (7, 1), (9, 1), (15, 2), (16, 1), (18, 0)]);
+    /// ```
+    pub fn depth(&self) -> usize {
+        self.opens.len()
+    }
+
+    /// Skips over the value of the [`Event::Key`] that was just read
+    ///
+    /// This passes over either a single [`Event::Str`] or everything from an [`Event::ObjBegin`]
+    /// through its matching [`Event::ObjEnd`]. Skipped text is still checked for errors and a
+    /// conditional following the value is still read afterwards. Does nothing when the reader
+    /// isn't right after a key
+    pub fn skip_value(&mut self) -> Result<(), ParseError> {
+        if self.state != State::Value {
+            return Ok(());
+        }
+
+        match self.next().transpose()? {
+            Some(Event::ObjBegin { .. }) => self.skip_obj(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Skips the rest of the innermost open object through its [`Event::ObjEnd`]
+    ///
+    /// Skipped text is still checked for errors. Does nothing when there isn't an open object
+    pub fn skip_obj(&mut self) -> Result<(), ParseError> {
+        let depth = self.depth();
+        while self.depth() >= depth && depth > 0 {
+            if self.next().transpose()?.is_none() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn text(&self) -> &'text str {
+        self.scanner.text()
+    }
+
+    fn span(&mut self, range: Range<usize>) -> Span {
+        self.cursor.span(range)
+    }
+
+    fn error(&self, kind: ParseErrorKind, range: Range<usize>) -> ParseError {
+        ParseError::new(self.text(), kind, range)
+    }
+
+    fn unexpected(&self, token: Option<&Lexeme>, expected: Vec<Expected>) -> ParseError {
+        let end = self.text().len();
+        let range = token.map_or(end..end, |token| token.range.clone());
+        let kind = ParseErrorKind::Unexpected {
+            found: found(token),
+            expected,
+        };
+        self.error(kind, range)
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event<'text>>, ParseError> {
+        loop {
+            match self.state {
+                State::Start => {
+                    check_input_len(&self.parser, self.text())?;
+                    self.state = State::Directives;
+                }
+                State::Directives => match self.directive() {
+                    Some(event) => return Ok(Some(event)),
+                    None => self.state = State::Key,
+                },
+                State::Key => return self.key().map(Some),
+                State::Value => return self.value().map(Some),
+                State::AfterValue => {
+                    self.state = if self.opens.is_empty() {
+                        State::End
+                    } else {
+                        State::Key
+                    };
+                    if let Some(event) = self.conditional() {
+                        return Ok(Some(event));
+                    }
+                }
+                State::End => {
+                    self.end()?;
+                    self.state = State::Done;
+                }
+                State::Done => return Ok(None),
+            }
+        }
+    }
+
+    /// Reads a `#base` or `#include` directive if there is one
+    fn directive(&mut self) -> Option<Event<'text>> {
+        let directive = self.scanner.directive()?;
+        let path = Cow::from(&self.text()[directive.path.contents()]);
+        let span = self.span(directive.path.range);
+        Some(if directive.is_base {
+            Event::Base { path, span }
+        } else {
+            Event::Include { path, span }
+        })
+    }
+
+    /// Reads either a key or the `}` closing the current object
+    fn key(&mut self) -> Result<Event<'text>, ParseError> {
+        let token = self.scanner.next_token();
+        match token.as_ref().map(|token| token.kind) {
+            None => match self.opens.last() {
+                Some(&open) => {
+                    let end = self.text().len();
+                    let lines = LineIndex::new(self.text());
+                    let open = lines.location(open);
+                    let kind = ParseErrorKind::UnclosedObject { open };
+                    Err(ParseError::with_lines(&lines, kind, end..end))
+                }
+                None => Err(self.unexpected(None, vec![Expected::Key])),
+            },
+            Some(TokenKind::ObjClose) if !self.opens.is_empty() => {
+                self.opens.pop();
+                self.state = State::AfterValue;
+                let span = self.span(token.unwrap().range);
+                Ok(Event::ObjEnd { span })
+            }
+            Some(TokenKind::ObjOpen) if !self.opens.is_empty() => {
+                Err(self.unexpected(token.as_ref(), vec![Expected::Key, Expected::ObjClose]))
+            }
+            Some(TokenKind::ObjOpen | TokenKind::ObjClose) => {
+                Err(self.unexpected(token.as_ref(), vec![Expected::Key]))
+            }
+            Some(_) => {
+                let token = token.unwrap();
+                self.num_pairs += 1;
+                if let Some(limit) = self.parser.max_pairs.filter(|&l| self.num_pairs > l) {
+                    return Err(
+                        self.error(ParseErrorKind::PairLimitExceeded { limit }, token.range)
+                    );
+                }
+
+                let (key, range) = self.string(token)?;
+                self.state = State::Value;
+                let span = self.span(range);
+                Ok(Event::Key { key, span })
+            }
+        }
+    }
+
+    /// Reads either a string or the `{` opening an object
+    fn value(&mut self) -> Result<Event<'text>, ParseError> {
+        let token = self.scanner.next_token();
+        match token.as_ref().map(|token| token.kind) {
+            None | Some(TokenKind::ObjClose) => {
+                Err(self.unexpected(token.as_ref(), vec![Expected::Value]))
+            }
+            Some(TokenKind::ObjOpen) => {
+                let range = token.unwrap().range;
+                let depth = self.opens.len() + 1;
+                if let Some(limit) = self.parser.max_depth.filter(|&limit| depth > limit) {
+                    return Err(self.error(ParseErrorKind::DepthLimitExceeded { limit }, range));
+                }
+
+                self.opens.push(range.start);
+                self.state = State::Key;
+                let span = self.span(range);
+                Ok(Event::ObjBegin { span })
+            }
+            Some(_) => {
+                let (value, range) = self.string(token.unwrap())?;
+                self.state = State::AfterValue;
+                let span = self.span(range);
+                Ok(Event::Str { value, span })
+            }
+        }
+    }
+
+    /// Returns the contents of a quoted or unquoted string along with its range
+    fn string(&self, token: Lexeme) -> Result<(Cow<'text, str>, Range<usize>), ParseError> {
+        let text = self.text();
+        let contents = match token.kind {
+            TokenKind::UnquotedStr => token.range.clone(),
+            TokenKind::QuotedStr => token.range.start + 1..token.range.end - 1,
+            TokenKind::UnterminatedStr => {
+                let start = token.range.start;
+                let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+                return Err(self.error(ParseErrorKind::UnclosedString, start..end));
+            }
+            _ => unreachable!("Only called with strings"),
+        };
+
+        if let Some(limit) = self.parser.max_str_len.filter(|&l| contents.len() > l) {
+            let kind = ParseErrorKind::StrLenLimitExceeded { limit };
+            return Err(self.error(kind, token.range));
+        }
+
+        let s = &text[contents.clone()];
+        if token.kind == TokenKind::UnquotedStr || self.parser.literal_special_chars {
+            return Ok((Cow::from(s), token.range));
+        }
+        // Unknown escape sequences are kept as is when they're lenient
+        let invalid = (!self.parser.lenient_escapes)
+            .then(|| invalid_escapes(s).next())
+            .flatten();
+        if let Some(invalid) = invalid {
+            let range = contents.start + invalid.start..contents.start + invalid.end;
+            let sequence = text[range.clone()].to_owned();
+            return Err(self.error(ParseErrorKind::InvalidEscape { sequence }, range));
+        }
+        Ok((unescape(s), token.range))
+    }
+
+    /// Reads a conditional tag if there is one following a value
+    fn conditional(&mut self) -> Option<Event<'text>> {
+        let (conditional, range) = self.scanner.conditional()?;
+        let span = self.span(range);
+        Some(Event::Conditional { conditional, span })
+    }
+
+    /// Only trivia and an optional trailing null byte can come after the top-level pair
+    fn end(&mut self) -> Result<(), ParseError> {
+        match self.scanner.end() {
+            Some(token) => Err(self.unexpected(Some(&token), vec![Expected::EndOfInput])),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'text> Iterator for EventReader<'text> {
+    type Item = Result<Event<'text>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_event() {
+            Ok(event) => event.map(Ok),
+            Err(err) => {
+                self.state = State::Done;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl FusedIterator for EventReader<'_> {}
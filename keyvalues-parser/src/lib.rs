@@ -10,26 +10,57 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
+use encoding::{Detected, Encoding};
+use error::{Error, ParseError};
+
+pub mod canonical;
+pub mod conditional;
+pub mod edit;
+pub mod encoding;
 pub mod error;
+pub mod events;
+pub mod ordered;
+pub mod resolve;
 #[cfg(feature = "serde")]
 #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
 mod serde;
+pub mod span;
+pub mod tape;
 pub mod text;
 
 /// `pest` re-exported for your convenience :)
+#[cfg(feature = "pest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pest")))]
 pub use pest;
 
 /// Parse a KeyValues document to a loosely typed representation
 ///
 /// This is shorthand for parsing a document with default settings aka `Parser::new().parse(text)`
-pub fn parse<'text>(text: &'text str) -> error::Result<PartialVdf<'text>> {
+pub fn parse<'text>(text: &'text str) -> Result<PartialVdf<'text>, ParseError> {
     Parser::new().parse(text)
 }
 
 /// A configurable KeyValues parser allowing for adjusting settings before parsing
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct Parser {
     literal_special_chars: bool,
+    lenient_escapes: bool,
+    valve_compat: bool,
+    strict: bool,
+    duplicate_keys: DuplicateKeys,
+    fold_keys: bool,
+    spans: bool,
+    max_depth: Option<usize>,
+    max_str_len: Option<usize>,
+    max_pairs: Option<usize>,
+    max_input_len: Option<usize>,
+    fallback_encoding: Encoding,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Parser {
@@ -40,10 +71,35 @@ impl Parser {
     /// | Toggle | Description |
     /// | :---: | :--- |
     /// | [`Parser::literal_special_chars()`] | Whether to interpret `\` in strings as the start of an escaped special character, or a literal `\` |
+    /// | [`Parser::lenient_escapes()`] | Whether to keep unknown escape sequences like `\G` as is instead of failing |
+    /// | [`Parser::valve_compat()`] | Whether to accept the same malformed text that Source games do |
+    /// | [`Parser::strict()`] | Whether to reject syntax that's technically valid, but ambiguous |
+    /// | [`Parser::duplicate_keys()`] | What to do with keys that show up more than once in an object |
+    /// | [`Parser::fold_keys()`] | Whether keys within an object that only differ in case are the same key |
+    /// | [`Parser::with_spans()`] | Whether to record the source location of every key and value |
+    /// | [`Parser::max_depth()`] | How deeply objects can be nested |
+    /// | [`Parser::max_str_len()`] | How long a single key or string value can be |
+    /// | [`Parser::max_pairs()`] | How many key-value pairs there can be in total |
+    /// | [`Parser::max_input_len()`] | How large the text can be |
+    /// | [`Parser::fallback_encoding()`] | What [`Parser::parse_bytes()`] decodes text as when it's not UTF-8 |
+    ///
+    /// None of the limits are set by default, duplicate keys are all kept, and the fallback
+    /// encoding is Windows-1252
     pub const fn new() -> Self {
         // same as Default, but const 😏
         Self {
             literal_special_chars: false,
+            lenient_escapes: false,
+            valve_compat: false,
+            strict: false,
+            duplicate_keys: DuplicateKeys::KeepAll,
+            fold_keys: false,
+            spans: false,
+            max_depth: None,
+            max_str_len: None,
+            max_pairs: None,
+            max_input_len: None,
+            fallback_encoding: Encoding::Windows1252,
         }
     }
 
@@ -58,6 +114,212 @@ impl Parser {
         self
     }
 
+    /// Toggle keeping unknown escape sequences instead of failing on them
+    ///
+    /// By default (`false`) a `\` followed by anything other than `n`, `r`, `t`, `\`, or `"` is a
+    /// [`ParseErrorKind::InvalidEscape`][error::ParseErrorKind::InvalidEscape] error. When `true`
+    /// the known sequences still get replaced while unknown ones are kept verbatim like Valve's
+    /// own tokenizer does. Use [`Parser::parse_with_warnings()`] to find out which ones were kept.
+    /// This has no effect with [`Parser::literal_special_chars()`] and isn't supported by
+    /// `Parser::parse_pest()`
+    ///
+    /// ```
+    /// use keyvalues_parser::Parser;
+    /// let vdf_text = r#"InstallDir "C:\Games\Half-Life 2\n""#;
+    /// assert!(Parser::new().parse(vdf_text).is_err());
+    /// let vdf = Parser::new().lenient_escapes(true).parse(vdf_text).unwrap();
+    /// assert_eq!(vdf.value.unwrap_str(), "C:\\Games\\Half-Life 2\n");
+    /// ```
+    pub const fn lenient_escapes(mut self, yes: bool) -> Self {
+        self.lenient_escapes = yes;
+        self
+    }
+
+    /// Toggle emulating the quirks of the engine's `KeyValues::LoadFromBuffer()`
+    ///
+    /// By default (`false`) the parser sticks to the grammar and errors on anything that doesn't
+    /// follow it. Source games are a lot more forgiving, so files that load fine in-game can be
+    /// rejected here. When `true` the parser instead handles text the same way the engine does
+    ///
+    /// - Objects left unclosed at the end of the text are closed
+    /// - Keys and string values are cut off at 4,095 bytes (rounded down to a `char` boundary)
+    /// - A key that's missing its value within an object is dropped
+    /// - Anything following the top-level pair is ignored
+    ///
+    /// See `tests/assets/valve_compat` for examples of each. Combine this with
+    /// [`Parser::lenient_escapes()`] to also keep unknown escape sequences like the engine. This
+    /// has no effect on [`Parser::events()`] or `Parser::parse_pest()`
+    ///
+    /// ```
+    /// use keyvalues_parser::Parser;
+    /// let vdf_text = "\"AppState\"\n{\n\t\"appid\" \"620\"\n\t\"name\"";
+    /// assert!(Parser::new().parse(vdf_text).is_err());
+    /// let vdf = Parser::new().valve_compat(true).parse(vdf_text).unwrap();
+    /// let obj = vdf.value.get_obj().unwrap();
+    /// assert_eq!(obj.keys().collect::<Vec<_>>(), ["appid"]);
+    /// ```
+    pub const fn valve_compat(mut self, yes: bool) -> Self {
+        self.valve_compat = yes;
+        self
+    }
+
+    /// Toggle rejecting ambiguous syntax
+    ///
+    /// By default (`false`) the parser accepts anything that follows the grammar. When `true` the
+    /// following forms are also rejected, which is handy for validating files before shipping
+    /// them
+    ///
+    /// | Form | Error |
+    /// | :--- | :--- |
+    /// | `#base a.vdf #base b.vdf` | [`ParseErrorKind::DirectiveSharesLine`][error::ParseErrorKind::DirectiveSharesLine] |
+    /// | `url http://example.com` | [`ParseErrorKind::CommentInUnquotedStr`][error::ParseErrorKind::CommentInUnquotedStr] |
+    /// | `"key"value` | [`ParseErrorKind::MissingSeparator`][error::ParseErrorKind::MissingSeparator] |
+    /// | A control character other than a tab or newline in a string | [`ParseErrorKind::NonPrintableChar`][error::ParseErrorKind::NonPrintableChar] |
+    ///
+    /// This has no effect on [`Parser::events()`] or `Parser::parse_pest()`
+    ///
+    /// ```
+    /// use keyvalues_parser::{error::ParseErrorKind, Parser};
+    /// let vdf_text = "#base\"foo.vdf\"#base\"bar.vdf\"\nkey val";
+    /// assert!(Parser::new().parse(vdf_text).is_ok());
+    /// let err = Parser::new().strict(true).parse(vdf_text).unwrap_err();
+    /// assert_eq!(err.kind(), &ParseErrorKind::DirectiveSharesLine);
+    /// assert_eq!(err.span().as_str(vdf_text), "#base");
+    /// ```
+    pub const fn strict(mut self, yes: bool) -> Self {
+        self.strict = yes;
+        self
+    }
+
+    /// Set how to handle keys that show up more than once within the same object
+    ///
+    /// See [`DuplicateKeys`] for the different policies. Keys are compared exactly, and the
+    /// top-level pairs of [`Parser::parse_document()`] are never treated as duplicates. This has
+    /// no effect on [`Parser::events()`] or `Parser::parse_pest()`
+    ///
+    /// ```
+    /// use keyvalues_parser::{DuplicateKeys, Parser};
+    /// let vdf_text = "config { volume 0.5 mute 1 volume 0.8 }";
+    /// let volumes = |policy| {
+    ///     let vdf = Parser::new().duplicate_keys(policy).parse(vdf_text)?;
+    ///     let values: Vec<_> = vdf.value.get_obj().unwrap()["volume"]
+    ///         .iter()
+    ///         .map(|value| value.get_str().unwrap().to_owned())
+    ///         .collect();
+    ///     Ok::<_, keyvalues_parser::error::ParseError>(values)
+    /// };
+    /// assert_eq!(volumes(DuplicateKeys::KeepAll)?, ["0.5", "0.8"]);
+    /// assert_eq!(volumes(DuplicateKeys::FirstWins)?, ["0.5"]);
+    /// assert_eq!(volumes(DuplicateKeys::LastWins)?, ["0.8"]);
+    /// assert!(volumes(DuplicateKeys::Error).is_err());
+    /// # Ok::<(), keyvalues_parser::error::ParseError>(())
+    /// ```
+    pub const fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Toggle treating keys within an object as the same when they only differ in ASCII case
+    ///
+    /// By default (`false`) keys are compared exactly, so `"AppState"` and `"appstate"` end up as
+    /// separate entries. When `true` every key in an object gets spelled the same way as the
+    /// first one that matches it ignoring case, which merges the entries together while keeping
+    /// the original spelling for rendering. This matches how the engine compares keys and also
+    /// applies to [`Parser::duplicate_keys()`]. The top-level pairs of
+    /// [`Parser::parse_document()`] are never folded. This has no effect on [`Parser::events()`]
+    /// or `Parser::parse_pest()`
+    ///
+    /// ```
+    /// use keyvalues_parser::Parser;
+    /// let vdf_text = r#"AppState { InstallDir Portal installdir "Portal 2" }"#;
+    /// let vdf = Parser::new().fold_keys(true).parse(vdf_text)?;
+    /// let obj = vdf.value.get_obj().unwrap();
+    /// assert_eq!(obj.keys().collect::<Vec<_>>(), ["InstallDir"]);
+    /// assert_eq!(obj["InstallDir"].len(), 2);
+    /// # Ok::<(), keyvalues_parser::error::ParseError>(())
+    /// ```
+    pub const fn fold_keys(mut self, yes: bool) -> Self {
+        self.fold_keys = yes;
+        self
+    }
+
+    /// Toggle recording source locations while parsing
+    ///
+    /// By default (`false`) no location information is kept. When `true` the parser will record
+    /// the byte range along with the line and column for every key, value, and object brace in
+    /// [`PartialVdf::spans`]. See the [`span`] module for more details
+    ///
+    /// ```
+    /// use keyvalues_parser::Parser;
+    /// let vdf = Parser::new().with_spans(true).parse("key value").unwrap();
+    /// let spans = vdf.spans.unwrap();
+    /// assert_eq!(spans.key.range(), 0..3);
+    /// assert_eq!(spans.value.span().range(), 4..9);
+    /// ```
+    pub const fn with_spans(mut self, yes: bool) -> Self {
+        self.spans = yes;
+        self
+    }
+
+    /// Limit how deeply objects can be nested
+    ///
+    /// The top-level object is at a depth of 1. Exceeding the limit results in a
+    /// [`ParseErrorKind::DepthLimitExceeded`][error::ParseErrorKind::DepthLimitExceeded] error.
+    /// Set this when parsing untrusted text since deeply nested objects can otherwise overflow
+    /// the stack
+    ///
+    /// ```
+    /// use keyvalues_parser::{error::ParseErrorKind, Parser};
+    /// let parser = Parser::new().max_depth(2);
+    /// parser.parse("a { b { c d } }").unwrap();
+    /// let err = parser.parse("a { b { c { d e } } }").unwrap_err();
+    /// assert_eq!(err.kind(), &ParseErrorKind::DepthLimitExceeded { limit: 2 });
+    /// ```
+    pub const fn max_depth(mut self, limit: usize) -> Self {
+        self.max_depth = Some(limit);
+        self
+    }
+
+    /// Limit the length in bytes of any single key or string value
+    ///
+    /// The length excludes any surrounding quotes and is measured before escape sequences get
+    /// replaced. Exceeding the limit results in a
+    /// [`ParseErrorKind::StrLenLimitExceeded`][error::ParseErrorKind::StrLenLimitExceeded] error
+    pub const fn max_str_len(mut self, limit: usize) -> Self {
+        self.max_str_len = Some(limit);
+        self
+    }
+
+    /// Limit the total number of key-value pairs including the top-level pair
+    ///
+    /// Exceeding the limit results in a
+    /// [`ParseErrorKind::PairLimitExceeded`][error::ParseErrorKind::PairLimitExceeded] error
+    pub const fn max_pairs(mut self, limit: usize) -> Self {
+        self.max_pairs = Some(limit);
+        self
+    }
+
+    /// Limit the size of the text in bytes
+    ///
+    /// This is checked before doing any parsing. Exceeding the limit results in a
+    /// [`ParseErrorKind::InputLenLimitExceeded`][error::ParseErrorKind::InputLenLimitExceeded]
+    /// error
+    pub const fn max_input_len(mut self, limit: usize) -> Self {
+        self.max_input_len = Some(limit);
+        self
+    }
+
+    /// Set the encoding that [`Parser::parse_bytes()`] falls back to
+    ///
+    /// Text that starts with a UTF-8 or UTF-16 byte order mark is always decoded accordingly and
+    /// text without one that's valid UTF-8 is decoded as UTF-8. Anything else gets decoded with
+    /// the fallback which is Windows-1252 by default. Falling back to [`Encoding::Utf8`] makes
+    /// invalid UTF-8 an error instead
+    pub const fn fallback_encoding(mut self, encoding: Encoding) -> Self {
+        self.fallback_encoding = encoding;
+        self
+    }
+
     /// Parse a KeyValues document to a loosely typed representation
     ///
     /// # Example
@@ -70,15 +332,450 @@ impl Parser {
     ///     .unwrap();
     /// assert_eq!(vdf.value.unwrap_str(), r"C:\You\Later");
     /// ```
-    pub fn parse<'text>(&self, vdf: &'text str) -> error::Result<PartialVdf<'text>> {
-        if self.literal_special_chars {
-            #[expect(deprecated)] // deprecated for thee, but not for me!
-            text::parse::raw_parse(vdf)
-        } else {
-            #[expect(deprecated)] // deprecated for thee, but not for me!
-            text::parse::escaped_parse(vdf)
+    pub fn parse<'text>(&self, vdf: &'text str) -> Result<PartialVdf<'text>, ParseError> {
+        text::parse::parse_with(self, vdf)
+    }
+
+    /// Parse a KeyValues document while also returning any warnings
+    ///
+    /// Errors are handled the same as [`Parser::parse()`]. Warnings are for things that parsed
+    /// fine, but may not mean what was intended. Currently that's the unknown escape sequences
+    /// kept by [`Parser::lenient_escapes()`] and any conditional tags that got
+    /// [dropped][error::ParseErrorKind::DroppedConditional]
+    ///
+    /// ```
+    /// use keyvalues_parser::{error::ParseErrorKind, Parser};
+    /// let parser = Parser::new().lenient_escapes(true);
+    /// let warned = parser.parse_with_warnings(r#"path "C:\Games\Portal""#)?;
+    /// let sequences: Vec<_> = warned
+    ///     .warnings
+    ///     .iter()
+    ///     .map(|warning| match warning.kind() {
+    ///         ParseErrorKind::InvalidEscape { sequence } => sequence.as_str(),
+    ///         _ => unreachable!(),
+    ///     })
+    ///     .collect();
+    /// assert_eq!(sequences, [r"\G", r"\P"]);
+    /// # Ok::<(), keyvalues_parser::error::Error>(())
+    /// ```
+    pub fn parse_with_warnings<'text>(&self, vdf: &'text str) -> Result<Warned<'text>, ParseError> {
+        text::parse::parse_warned_with(self, vdf)
+    }
+
+    /// Parse a KeyValues document with the original `pest`-based parser
+    ///
+    /// This is mostly kept around for comparing against [`Parser::parse()`]. The two produce the
+    /// same results as long as only these options are used
+    ///
+    /// - [`Parser::literal_special_chars()`]
+    /// - [`Parser::with_spans()`]
+    /// - [`Parser::max_depth()`], [`Parser::max_str_len()`], [`Parser::max_pairs()`], and
+    ///   [`Parser::max_input_len()`]
+    ///
+    /// Everything else ([`Parser::lenient_escapes()`], [`Parser::valve_compat()`],
+    /// [`Parser::strict()`], [`Parser::duplicate_keys()`], and [`Parser::fold_keys()`]) is ignored
+    /// and parses as if it was left at its default
+    ///
+    /// The limits are only checked once the text parses successfully (except for
+    /// [`Parser::max_depth()`] which is checked upfront since `pest` parses recursively), so text
+    /// that both breaks a limit and has a syntax error can report either one
+    ///
+    /// ```
+    /// use keyvalues_parser::Parser;
+    /// let parser = Parser::new().strict(true);
+    /// assert!(parser.parse(r#""key""value""#).is_err());
+    /// assert!(parser.parse_pest(r#""key""value""#).is_ok());
+    /// ```
+    #[cfg(feature = "pest")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "pest")))]
+    pub fn parse_pest<'text>(&self, vdf: &'text str) -> Result<PartialVdf<'text>, ParseError> {
+        text::parse::parse_pest_with(self, vdf)
+    }
+
+    /// Parse a KeyValues document from bytes in any of the supported [`Encoding`]s
+    ///
+    /// The encoding is detected as described in [`Parser::fallback_encoding()`] and any byte
+    /// order mark is stripped before parsing. Spans and error locations are relative to the
+    /// decoded text. The detected encoding is returned along with the parsed document so that
+    /// it can be written back out the same way with [`Decoded::render()`]
+    ///
+    /// ```
+    /// use keyvalues_parser::{encoding::Encoding, Parser};
+    ///
+    /// // Windows-1252 encoded text
+    /// let bytes = b"\"lang\" { \"Tokens\" { \"greeting\" \"Hall\xe5\" } }";
+    /// let decoded = Parser::new().parse_bytes(bytes)?;
+    /// assert_eq!(decoded.detected.encoding, Encoding::Windows1252);
+    /// let tokens = decoded.vdf.value.get_obj().unwrap()["Tokens"][0].get_obj().unwrap();
+    /// assert_eq!(tokens["greeting"][0].get_str(), Some("Hallå"));
+    /// # Ok::<(), keyvalues_parser::error::Error>(())
+    /// ```
+    pub fn parse_bytes<'bytes>(&self, bytes: &'bytes [u8]) -> Result<Decoded<'bytes>, Error> {
+        let detected = Detected::detect(bytes, self.fallback_encoding);
+        let vdf = match detected.decode(bytes)? {
+            Cow::Borrowed(text) => self.parse(text)?,
+            Cow::Owned(text) => self.parse(&text)?.into_owned(),
+        };
+        Ok(Decoded { vdf, detected })
+    }
+
+    /// Parse a KeyValues document while recovering from any errors along the way
+    ///
+    /// Instead of stopping at the first error this records every error that it runs into while
+    /// still producing a best-effort [`PartialVdf`]. This is handy for things like editors and
+    /// linters that need to keep working on broken input. Text that parses successfully with
+    /// [`Parser::parse()`] produces the same [`PartialVdf`] here without any errors
+    ///
+    /// - A key that's missing its value gets dropped
+    /// - A `{` without a key gets parsed to stay in sync with the braces, but is otherwise dropped
+    /// - A quoted string that's missing its closing `"` ends at the end of its line
+    /// - Any objects left unclosed are closed at the end of the text
+    ///
+    /// ```
+    /// use keyvalues_parser::Parser;
+    ///
+    /// let vdf_text = r#"
+    /// "Outer Key"
+    /// {
+    ///     "Bad Escape" "\q"
+    ///     { "Missing" "Key" }
+    ///     "Fine" "Pair"
+    ///     "Missing Value"
+    /// }
+    /// "Trailing Pair" {}
+    /// "#;
+    /// let recovered = Parser::new().parse_recovering(vdf_text);
+    /// let lines: Vec<_> = recovered.errors.iter().map(|err| err.line()).collect();
+    /// assert_eq!(lines, [4, 5, 8, 9]);
+    ///
+    /// let obj = recovered.vdf.value.get_obj().unwrap();
+    /// assert_eq!(obj.keys().collect::<Vec<_>>(), ["Bad Escape", "Fine"]);
+    /// ```
+    pub fn parse_recovering<'text>(&self, vdf: &'text str) -> Recovered<'text> {
+        text::parse::parse_recovering_with(self, vdf)
+    }
+
+    /// Parse a KeyValues document to a representation that keeps pairs in their original order
+    ///
+    /// Unlike [`Parser::parse()`], duplicate keys aren't grouped together and keys aren't sorted,
+    /// so rendering the result keeps everything in document order. See the [`ordered`] module
+    /// for more details. [`Parser::with_spans()`] has no effect here
+    ///
+    /// ```
+    /// use keyvalues_parser::Parser;
+    /// let vdf = Parser::new().parse_ordered("key { b 1 a 2 b 3 }").unwrap();
+    /// let obj = vdf.value.get_obj().unwrap();
+    /// assert_eq!(obj.keys().collect::<Vec<_>>(), ["b", "a", "b"]);
+    /// ```
+    pub fn parse_ordered<'text>(
+        &self,
+        vdf: &'text str,
+    ) -> Result<ordered::PartialVdf<'text>, ParseError> {
+        text::parse::parse_ordered_with(self, vdf)
+    }
+
+    /// Parse a KeyValues document that can have any number of top-level pairs
+    ///
+    /// [`Parser::parse()`] requires exactly one top-level pair while this accepts zero or more,
+    /// which is what formats like VMF maps and entity lumps use. `#base` and `#include`
+    /// directives can still only come at the start
+    ///
+    /// ```
+    /// use keyvalues_parser::Parser;
+    /// let doc = Parser::new().parse_document("#base base.vdf\na { b c }\nd e\na {}").unwrap();
+    /// assert_eq!(doc.bases, ["base.vdf"]);
+    /// let keys: Vec<_> = doc.roots.iter().map(|vdf| &vdf.key).collect();
+    /// assert_eq!(keys, ["a", "d", "a"]);
+    /// ```
+    pub fn parse_document<'text>(&self, vdf: &'text str) -> Result<MultiVdf<'text>, ParseError> {
+        text::parse::parse_multi_with(self, vdf)
+    }
+
+    /// Read a KeyValues document one [`Event`][events::Event] at a time
+    ///
+    /// This doesn't build up any tree, so it's handy for pulling a few values out of large
+    /// documents. Every event comes with its span regardless of [`Parser::with_spans()`]. See
+    /// the [`events`] module for more details
+    ///
+    /// ```
+    /// use keyvalues_parser::{events::Event, Parser};
+    ///
+    /// let keys: Vec<_> = Parser::new()
+    ///     .events("outer { a 1 b { c 2 } }")
+    ///     .filter_map(|event| match event {
+    ///         Ok(Event::Key { key, .. }) => Some(key),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(keys, ["outer", "a", "b", "c"]);
+    /// ```
+    pub fn events<'text>(&self, vdf: &'text str) -> events::EventReader<'text> {
+        events::EventReader::new(self, vdf)
+    }
+
+    /// Build a [`tape::Tape`] index over a KeyValues document for accessing values on demand
+    ///
+    /// This follows all of the same options and checks as [`Parser::parse()`] in a single pass,
+    /// but strings aren't decoded until they're accessed. See the [`tape`] module for more details
+    ///
+    /// ```
+    /// use keyvalues_parser::Parser;
+    /// let vdf_text = r#"outer { "escaped" "\"quoted\"" inner { key value } }"#;
+    /// let tape = Parser::new().tape(vdf_text)?;
+    /// let outer = tape.root().value().get_obj().unwrap();
+    /// let escaped = outer.get("escaped").unwrap().get_str().unwrap();
+    /// assert_eq!(escaped.raw(), r#"\"quoted\""#);
+    /// assert_eq!(escaped.decode(), r#""quoted""#);
+    /// assert_eq!(tape.to_vdf(), Parser::new().parse(vdf_text)?);
+    /// # Ok::<(), keyvalues_parser::error::ParseError>(())
+    /// ```
+    pub fn tape<'text>(&self, vdf: &'text str) -> Result<tape::Tape<'text>, ParseError> {
+        tape::Tape::new(self, vdf)
+    }
+}
+
+/// Options for laying out rendered text with [`Vdf::render_with()`], [`PartialVdf::render_with()`],
+/// and [`Value::render_with()`]
+///
+/// The defaults match the regular `render()` methods
+///
+/// ```
+/// use keyvalues_parser::{Indent, RenderOptions, Vdf};
+///
+/// let vdf = Vdf::parse(r#"AppState { appid 620 name "Portal 2" UserConfig { language english } }"#)?;
+/// let options = RenderOptions::new()
+///     .indent(Indent::Spaces(2))
+///     .align_values(true)
+///     .brace_on_same_line(true)
+///     .quote_only_when_needed(true);
+/// let mut rendered = String::new();
+/// vdf.render_with(&mut rendered, &options)?;
+/// assert_eq!(
+///     rendered,
+///     "AppState {\n  \
+///        UserConfig {\n    \
+///          language english\n  \
+///        }\n  \
+///        appid 620\n  \
+///        name  \"Portal 2\"\n\
+///      }\n",
+/// );
+/// # Ok::<(), keyvalues_parser::error::Error>(())
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderOptions {
+    indent: Indent,
+    tab_width: usize,
+    align_values: bool,
+    crlf: bool,
+    brace_on_same_line: bool,
+    quote_only_when_needed: bool,
+    blank_lines_between_blocks: bool,
+    compact: bool,
+    escaping: Escaping,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderOptions {
+    /// Constructs the default options
+    ///
+    /// Currently this consists of:
+    ///
+    /// | Option | Description | Default |
+    /// | :---: | :--- | :---: |
+    /// | [`RenderOptions::indent()`] | What each level of nesting is indented with | [`Indent::Tabs`] |
+    /// | [`RenderOptions::tab_width()`] | How many columns a tab takes up when aligning values | `4` |
+    /// | [`RenderOptions::align_values()`] | Whether string values within an object line up | `false` |
+    /// | [`RenderOptions::crlf()`] | Whether lines end with `\r\n` instead of `\n` | `false` |
+    /// | [`RenderOptions::brace_on_same_line()`] | Whether an object's `{` follows its key | `false` |
+    /// | [`RenderOptions::quote_only_when_needed()`] | Whether strings are left unquoted when possible | `false` |
+    /// | [`RenderOptions::blank_lines_between_blocks()`] | Whether top-level objects are set apart by blank lines | `false` |
+    /// | [`RenderOptions::compact()`] | Whether everything is squeezed onto a single line | `false` |
+    /// | [`RenderOptions::escaping()`] | How special characters in strings get written | [`Escaping::Escaped`] |
+    pub const fn new() -> Self {
+        Self {
+            indent: Indent::Tabs,
+            tab_width: 4,
+            align_values: false,
+            crlf: false,
+            brace_on_same_line: false,
+            quote_only_when_needed: false,
+            blank_lines_between_blocks: false,
+            compact: false,
+            escaping: Escaping::Escaped,
         }
     }
+
+    /// Set what each level of nesting is indented with
+    ///
+    /// This also picks what separates a key from its string value when values aren't aligned.
+    /// That's a tab for [`Indent::Tabs`] and a single space for [`Indent::Spaces`]
+    pub const fn indent(mut self, indent: Indent) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Set how many columns a tab takes up
+    ///
+    /// This is only used to figure out how many tabs it takes to line up values with
+    /// [`RenderOptions::align_values()`] while indenting with [`Indent::Tabs`]
+    pub const fn tab_width(mut self, width: usize) -> Self {
+        self.tab_width = width;
+        self
+    }
+
+    /// Toggle lining up the string values within each object
+    ///
+    /// By default (`false`) a string value directly follows its key. When `true` the string
+    /// values within an object all start at the same column like in Valve's own files. With
+    /// [`Indent::Tabs`] that's the first tab stop past the longest key
+    ///
+    /// ```
+    /// use keyvalues_parser::{RenderOptions, Vdf};
+    /// let vdf = Vdf::parse("user { AccountName gaben Timestamp 1 }")?;
+    /// let mut rendered = String::new();
+    /// vdf.render_with(&mut rendered, &RenderOptions::new().align_values(true))?;
+    /// assert_eq!(
+    ///     rendered,
+    ///     "\"user\"\n{\n\t\"AccountName\"\t\"gaben\"\n\t\"Timestamp\"\t\t\"1\"\n}\n",
+    /// );
+    /// # Ok::<(), keyvalues_parser::error::Error>(())
+    /// ```
+    pub const fn align_values(mut self, yes: bool) -> Self {
+        self.align_values = yes;
+        self
+    }
+
+    /// Toggle ending lines with `\r\n` instead of `\n`
+    pub const fn crlf(mut self, yes: bool) -> Self {
+        self.crlf = yes;
+        self
+    }
+
+    /// Toggle writing an object's opening `{` on the same line as its key
+    pub const fn brace_on_same_line(mut self, yes: bool) -> Self {
+        self.brace_on_same_line = yes;
+        self
+    }
+
+    /// Toggle leaving strings unquoted when they would parse back the same without quotes
+    ///
+    /// Strings that are empty or contain whitespace, quotes, or braces always get quoted along
+    /// with ones that would be mistaken for a comment, directive, or conditional
+    pub const fn quote_only_when_needed(mut self, yes: bool) -> Self {
+        self.quote_only_when_needed = yes;
+        self
+    }
+
+    /// Toggle separating the objects in the outermost object with blank lines
+    ///
+    /// A blank line goes between any two pairs in the outermost object when either of them
+    /// holds an object, so runs of string values stay grouped together
+    ///
+    /// ```
+    /// use keyvalues_parser::{RenderOptions, Vdf};
+    /// let vdf = Vdf::parse("root { a { } b 1 c 2 d { } }")?;
+    /// let mut rendered = String::new();
+    /// let options = RenderOptions::new()
+    ///     .brace_on_same_line(true)
+    ///     .blank_lines_between_blocks(true);
+    /// vdf.render_with(&mut rendered, &options)?;
+    /// assert_eq!(
+    ///     rendered,
+    ///     "\"root\" {\n\t\"a\" {\n\t}\n\n\t\"b\"\t\"1\"\n\t\"c\"\t\"2\"\n\n\t\"d\" {\n\t}\n}\n",
+    /// );
+    /// # Ok::<(), keyvalues_parser::error::Error>(())
+    /// ```
+    pub const fn blank_lines_between_blocks(mut self, yes: bool) -> Self {
+        self.blank_lines_between_blocks = yes;
+        self
+    }
+
+    /// Toggle rendering everything on a single line with as little text as possible
+    ///
+    /// This is handy for embedding VDF text in log lines, command-line arguments, or network
+    /// messages. Strings are left unquoted where possible and whitespace is only written between
+    /// two strings. The text parses back to the exact same tree, even with [`Parser::strict()`] as
+    /// long as no strings contain control characters, so the only exception to staying on one
+    /// line is that `#base` and `#include` directives each get their own. When `true` all of the
+    /// other options besides [`RenderOptions::crlf()`] are ignored
+    ///
+    /// ```
+    /// use keyvalues_parser::{Parser, RenderOptions, Vdf};
+    /// let vdf = Vdf::parse(r#"a { b c "d e" f g { } }"#)?;
+    /// let mut rendered = String::new();
+    /// vdf.render_with(&mut rendered, &RenderOptions::new().compact(true))?;
+    /// assert_eq!(rendered, r#"a{b c "d e" f g{}}"#);
+    /// assert_eq!(Vdf::from(Parser::new().strict(true).parse(&rendered)?), vdf);
+    /// # Ok::<(), keyvalues_parser::error::Error>(())
+    /// ```
+    pub const fn compact(mut self, yes: bool) -> Self {
+        self.compact = yes;
+        self
+    }
+
+    /// Set how special characters in strings get written
+    ///
+    /// See [`Escaping`] for the different modes
+    ///
+    /// ```
+    /// use keyvalues_parser::{Escaping, RenderOptions, Vdf};
+    /// let options = RenderOptions::new().escaping(Escaping::Auto);
+    /// let render = |vdf_text: &str| {
+    ///     let vdf = Vdf::parse(vdf_text)?;
+    ///     let mut rendered = String::new();
+    ///     vdf.render_with(&mut rendered, &options)?;
+    ///     Ok::<_, keyvalues_parser::error::Error>(rendered)
+    /// };
+    /// // Nothing needs escaping, so it's written raw
+    /// assert_eq!(render(r#"path "C:\\Games""#)?, "\"path\"\t\"C:\\Games\"\n");
+    /// // but a `"` can't be
+    /// assert_eq!(
+    ///     render(r#"path { 1 "C:\\Games" 2 "\"quoted\"" }"#)?,
+    ///     "\"path\"\n{\n\t\"1\"\t\"C:\\\\Games\"\n\t\"2\"\t\"\\\"quoted\\\"\"\n}\n",
+    /// );
+    /// # Ok::<(), keyvalues_parser::error::Error>(())
+    /// ```
+    pub const fn escaping(mut self, escaping: Escaping) -> Self {
+        self.escaping = escaping;
+        self
+    }
+}
+
+/// What [`RenderOptions::indent()`] indents with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Indent {
+    /// A single tab per level
+    #[default]
+    Tabs,
+    /// The given number of spaces per level
+    Spaces(usize),
+}
+
+/// How [`RenderOptions::escaping()`] writes special characters in strings
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Escaping {
+    /// Escape `"`, `\`, tabs, and line breaks like `render()` does. The text parses back with the
+    /// default [`Parser`]
+    #[default]
+    Escaped,
+    /// Write everything literally like `render_raw()` does. The text parses back with
+    /// [`Parser::literal_special_chars()`] and is what the engine expects for files like
+    /// `gameinfo.txt`. Rendering fails with
+    /// [`RenderError::InvalidRawChar`][error::RenderError::InvalidRawChar] when a string contains
+    /// a `"`. Use `validate_raw()` to find all of them
+    Raw,
+    /// Write everything literally when possible and fall back to escaping otherwise
+    ///
+    /// Raw is picked when none of the strings contain a `"` which is exactly when `validate_raw()`
+    /// comes back empty, so that can be used to tell which one was picked
+    Auto,
 }
 
 /// A Key is simply an alias for `Cow<str>`
@@ -102,6 +799,13 @@ pub type Key<'text> = Cow<'text, str>;
 ///
 /// The `Vdf` can also be rendered back to its text form through its `Display` implementation
 ///
+/// ## Conditionals
+///
+/// There's nowhere to store [conditional tags][crate::conditional] (e.g. `[$WIN32]`) here, so
+/// they're dropped while parsing and rendering won't bring them back.
+/// [`Parser::parse_with_warnings()`] reports each one that was dropped. Parse to the [`ordered`]
+/// representation with [`Parser::parse_ordered()`] to keep them
+///
 /// ## Example
 ///
 /// ```
@@ -155,6 +859,11 @@ impl<'text> Vdf<'text> {
     pub fn new(key: Key<'text>, value: Value<'text>) -> Self {
         Self { key, value }
     }
+
+    /// Converts all of the borrowed text into owned text
+    pub fn into_owned(self) -> Vdf<'static> {
+        Vdf::new(Cow::Owned(self.key.into_owned()), self.value.into_owned())
+    }
 }
 
 impl<'text> From<PartialVdf<'text>> for Vdf<'text> {
@@ -166,12 +875,120 @@ impl<'text> From<PartialVdf<'text>> for Vdf<'text> {
     }
 }
 
+/// How [`Parser`] handles a key that shows up more than once within the same object
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DuplicateKeys {
+    /// Keep every value in document order
+    #[default]
+    KeepAll,
+    /// Keep the first value and ignore any later ones
+    FirstWins,
+    /// Keep only the last value which matches how Steam reads its configs
+    LastWins,
+    /// Fail with a [`ParseErrorKind::DuplicateKey`][error::ParseErrorKind::DuplicateKey] error
+    Error,
+}
+
 // TODO: Just store a `Vdf` internally?
+/// A [`Vdf`] along with the paths from any `#base` and `#include` directives
+///
+/// Like [`Vdf`] this drops any [conditional tags][crate::conditional] while parsing which
+/// [`Parser::parse_with_warnings()`] reports. Use [`Parser::parse_ordered()`] to keep them
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct PartialVdf<'text> {
     pub key: Key<'text>,
     pub value: Value<'text>,
+    /// Paths from `#base` directives whose pairs get merged in as defaults
+    pub bases: Vec<Cow<'text, str>>,
+    /// Paths from `#include` directives whose pairs get appended
+    pub includes: Vec<Cow<'text, str>>,
+    /// Source locations for the top-level pair when parsed with [`Parser::with_spans()`]
+    pub spans: Option<span::PairSpans<'text>>,
+}
+
+impl PartialVdf<'_> {
+    /// Converts all of the borrowed text into owned text
+    pub fn into_owned(self) -> PartialVdf<'static> {
+        let owned = |paths: Vec<Cow<'_, str>>| {
+            paths
+                .into_iter()
+                .map(|path| Cow::Owned(path.into_owned()))
+                .collect()
+        };
+        PartialVdf {
+            key: Cow::Owned(self.key.into_owned()),
+            value: self.value.into_owned(),
+            bases: owned(self.bases),
+            includes: owned(self.includes),
+            spans: self.spans.map(span::PairSpans::into_owned),
+        }
+    }
+}
+
+/// A document with any number of top-level pairs
+///
+/// Returned by [`Parser::parse_document()`] for files like VMF maps that hold many top-level
+/// blocks instead of a single one. Rendering writes the pairs back out in order
+///
+/// ```
+/// use keyvalues_parser::MultiVdf;
+///
+/// let vdf_text = r#"
+/// "world" { "id" "1" }
+/// "entity" { "id" "2" }
+/// "entity" { "id" "3" }
+/// "#;
+/// let doc = MultiVdf::parse(vdf_text)?;
+/// let keys: Vec<_> = doc.roots.iter().map(|vdf| &vdf.key).collect();
+/// assert_eq!(keys, ["world", "entity", "entity"]);
+/// assert_eq!(MultiVdf::parse(&doc.to_string())?, doc);
+/// # Ok::<(), keyvalues_parser::error::Error>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MultiVdf<'text> {
+    /// The top-level pairs in the order they appear
+    pub roots: Vec<Vdf<'text>>,
+    /// Paths from `#base` directives whose pairs get merged in as defaults
     pub bases: Vec<Cow<'text, str>>,
+    /// Paths from `#include` directives whose pairs get appended
+    pub includes: Vec<Cow<'text, str>>,
+    /// Source locations for each of the top-level pairs when parsed with
+    /// [`Parser::with_spans()`]
+    pub spans: Option<Vec<span::PairSpans<'text>>>,
+}
+
+/// The result of [`Parser::parse_recovering()`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recovered<'text> {
+    /// Everything that could be parsed. The key is empty and the value is an empty object when
+    /// the text didn't contain a top-level pair at all
+    pub vdf: PartialVdf<'text>,
+    /// Every error encountered in the order they were found
+    pub errors: Vec<ParseError>,
+}
+
+impl Recovered<'_> {
+    /// Returns if the text parsed without any errors
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// The result of [`Parser::parse_with_warnings()`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warned<'text> {
+    pub vdf: PartialVdf<'text>,
+    /// Every warning in the order they were found
+    pub warnings: Vec<ParseError>,
+}
+
+/// The result of [`Parser::parse_bytes()`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decoded<'bytes> {
+    pub vdf: PartialVdf<'bytes>,
+    /// How the bytes were encoded
+    pub detected: Detected,
 }
 
 // TODO: why is this type alias a thing if it's not private but the usage of it inside `Obj` is?
@@ -228,6 +1045,81 @@ impl<'text> Obj<'text> {
         self.0
     }
 
+    /// Converts all of the borrowed text into owned text
+    pub fn into_owned(self) -> Obj<'static> {
+        let inner = self
+            .0
+            .into_iter()
+            .map(|(key, values)| {
+                let values = values.into_iter().map(Value::into_owned).collect();
+                (Cow::Owned(key.into_owned()), values)
+            })
+            .collect();
+        Obj(inner)
+    }
+
+    /// Returns the values for the first key that matches `key` ignoring ASCII case
+    ///
+    /// This matches how the engine looks up keys. Keys are checked in sorted order, so use
+    /// [`Parser::fold_keys()`] to merge keys that only differ in case while parsing. Unlike
+    /// [`BTreeMap::get()`] this has to check every key
+    ///
+    /// ```
+    /// use keyvalues_parser::Parser;
+    /// let vdf = Parser::new().parse(r#"AppState { appid 620 StateFlags 4 }"#)?;
+    /// let obj = vdf.value.get_obj().unwrap();
+    /// assert!(obj.get("stateflags").is_none());
+    /// let values = obj.get_ignore_case("stateflags").unwrap();
+    /// assert_eq!(values[0].get_str(), Some("4"));
+    /// # Ok::<(), keyvalues_parser::error::ParseError>(())
+    /// ```
+    pub fn get_ignore_case(&self, key: &str) -> Option<&Vec<Value<'text>>> {
+        self.get_key_value_ignore_case(key)
+            .map(|(_, values)| values)
+    }
+
+    /// The same as [`Obj::get_ignore_case()`], but returns the key as it's spelled in the object
+    pub fn get_key_value_ignore_case(
+        &self,
+        key: &str,
+    ) -> Option<(&Key<'text>, &Vec<Value<'text>>)> {
+        self.0
+            .iter()
+            .find(|(other, _)| other.eq_ignore_ascii_case(key))
+    }
+
+    /// The same as [`Obj::get_ignore_case()`], but returns the values mutably
+    ///
+    /// When several keys only differ by ASCII case this only returns the values of the first one
+    /// in sorted order (uppercase letters sort before lowercase ones). The others are left as is
+    ///
+    /// ```
+    /// use keyvalues_parser::{Parser, Value};
+    /// let mut vdf = Parser::new().parse(r#"AppState { stateflags 2 StateFlags 4 }"#)?;
+    /// let obj = vdf.value.get_mut_obj().unwrap();
+    /// obj.get_mut_ignore_case("STATEFLAGS").unwrap()[0] = Value::Str("6".into());
+    /// assert_eq!(obj["StateFlags"][0].get_str(), Some("6"));
+    /// assert_eq!(obj["stateflags"][0].get_str(), Some("2"));
+    /// # Ok::<(), keyvalues_parser::error::ParseError>(())
+    /// ```
+    pub fn get_mut_ignore_case(&mut self, key: &str) -> Option<&mut Vec<Value<'text>>> {
+        self.0
+            .iter_mut()
+            .find(|(other, _)| other.eq_ignore_ascii_case(key))
+            .map(|(_, values)| values)
+    }
+
+    /// Returns the values for every key that matches `key` ignoring ASCII case
+    pub fn get_all_ignore_case<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> impl Iterator<Item = &'a Value<'text>> + 'a {
+        self.0
+            .iter()
+            .filter(move |(other, _)| other.eq_ignore_ascii_case(key))
+            .flat_map(|(_, values)| values)
+    }
+
     /// Creates an iterator that returns the [`Vdf`]s that compose the object
     ///
     /// This is notably different compared to just iterating over the `BTreeMap`s items because it
@@ -355,6 +1247,14 @@ pub enum Value<'text> {
 }
 
 impl<'text> Value<'text> {
+    /// Converts all of the borrowed text into owned text
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Self::Str(s) => Value::Str(Cow::Owned(s.into_owned())),
+            Self::Obj(obj) => Value::Obj(obj.into_owned()),
+        }
+    }
+
     /// Returns if the current value is the `Str` variant
     ///
     /// ```
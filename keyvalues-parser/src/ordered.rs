@@ -0,0 +1,406 @@
+//! A loosely typed representation of VDF text that keeps pairs in their original order
+//!
+//! The types here mirror the ones at the root of the crate, but an [`Obj`] stores its pairs as a
+//! list instead of a [`BTreeMap`]. That means that parsing and then rendering some text keeps
+//! the pairs in the same order, including the relative order of duplicate keys that were
+//! interleaved with other keys
+//!
+//! Pairs here also keep their [conditional tags][crate::conditional] which can be applied with
+//! [`PartialVdf::evaluate_conditionals()`] and friends
+//!
+//! ```
+//! use keyvalues_parser::Parser;
+//!
+//! let vdf_text = "\"Key\"\n{\n\t\"b\"\t\"1\"\n\t\"a\"\t\"2\"\n\t\"b\"\t\"3\"\n}\n";
+//! let vdf = Parser::new().parse_ordered(vdf_text)?;
+//! assert_eq!(vdf.to_string(), vdf_text);
+//!
+//! // while the regular representation sorts and groups the keys
+//! let sorted = keyvalues_parser::parse(vdf_text)?;
+//! assert_ne!(sorted.to_string(), vdf_text);
+//! # Ok::<(), keyvalues_parser::error::Error>(())
+//! ```
+
+use std::{borrow::Cow, collections::BTreeMap};
+
+use crate::{conditional::Conditional, error::ParseError, Key, Parser};
+
+/// The top-level of an order-preserving document along with any `#base` and `#include` paths
+///
+/// See [`crate::PartialVdf`] for the sorted equivalent
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PartialVdf<'text> {
+    pub key: Key<'text>,
+    pub value: Value<'text>,
+    pub conditional: Option<Conditional<'text>>,
+    pub bases: Vec<Cow<'text, str>>,
+    pub includes: Vec<Cow<'text, str>>,
+}
+
+impl<'text> PartialVdf<'text> {
+    /// Attempts to parse VDF text to an order-preserving [`PartialVdf`]
+    pub fn parse(s: &'text str) -> Result<Self, ParseError> {
+        Parser::new().parse_ordered(s)
+    }
+
+    pub fn parse_raw(s: &'text str) -> Result<Self, ParseError> {
+        Parser::new().literal_special_chars(true).parse_ordered(s)
+    }
+
+    /// Removes all of the pairs whose conditionals don't hold for the `defined` symbols
+    ///
+    /// The conditionals of the remaining pairs are cleared. Returns `None` when the top-level
+    /// pair's conditional doesn't hold. See [`Conditional::evaluate()`] for how symbols are matched
+    pub fn evaluate_conditionals<S: AsRef<str>>(self, defined: &[S]) -> Option<Self> {
+        let Self {
+            key,
+            value,
+            conditional,
+            bases,
+            includes,
+        } = self;
+        Vdf {
+            key,
+            value,
+            conditional,
+        }
+        .evaluate_conditionals(defined)
+        .map(|Vdf { key, value, .. }| Self {
+            key,
+            value,
+            conditional: None,
+            bases,
+            includes,
+        })
+    }
+}
+
+impl<'text> From<PartialVdf<'text>> for crate::PartialVdf<'text> {
+    fn from(partial: PartialVdf<'text>) -> Self {
+        Self {
+            key: partial.key,
+            value: partial.value.into(),
+            bases: partial.bases,
+            includes: partial.includes,
+            spans: None,
+        }
+    }
+}
+
+/// A single key-value pair
+///
+/// See [`crate::Vdf`] for the sorted equivalent
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Vdf<'text> {
+    pub key: Key<'text>,
+    pub value: Value<'text>,
+    pub conditional: Option<Conditional<'text>>,
+}
+
+impl<'text> Vdf<'text> {
+    pub fn new(key: Key<'text>, value: Value<'text>) -> Self {
+        Self {
+            key,
+            value,
+            conditional: None,
+        }
+    }
+
+    /// Attempts to parse VDF text to an order-preserving [`Vdf`]
+    pub fn parse(s: &'text str) -> Result<Self, ParseError> {
+        Ok(Self::from(PartialVdf::parse(s)?))
+    }
+
+    pub fn parse_raw(s: &'text str) -> Result<Self, ParseError> {
+        Ok(Self::from(PartialVdf::parse_raw(s)?))
+    }
+
+    /// Returns the pair with its conditional applied or `None` if the conditional doesn't hold
+    ///
+    /// See [`PartialVdf::evaluate_conditionals()`]
+    pub fn evaluate_conditionals<S: AsRef<str>>(self, defined: &[S]) -> Option<Self> {
+        if self
+            .conditional
+            .as_ref()
+            .map_or(true, |cond| cond.evaluate(defined))
+        {
+            Some(Self::new(
+                self.key,
+                self.value.evaluate_conditionals(defined),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+impl<'text> From<PartialVdf<'text>> for Vdf<'text> {
+    fn from(partial: PartialVdf<'text>) -> Self {
+        Self {
+            key: partial.key,
+            value: partial.value,
+            conditional: partial.conditional,
+        }
+    }
+}
+
+impl<'text> From<Vdf<'text>> for crate::Vdf<'text> {
+    fn from(vdf: Vdf<'text>) -> Self {
+        Self::new(vdf.key, vdf.value.into())
+    }
+}
+
+impl<'text> From<crate::Vdf<'text>> for Vdf<'text> {
+    fn from(vdf: crate::Vdf<'text>) -> Self {
+        Self::new(vdf.key, vdf.value.into())
+    }
+}
+
+/// Either a string or an order-preserving object
+///
+/// See [`crate::Value`] for the sorted equivalent
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Value<'text> {
+    Str(Cow<'text, str>),
+    Obj(Obj<'text>),
+}
+
+impl<'text> Value<'text> {
+    pub fn is_str(&self) -> bool {
+        self.get_str().is_some()
+    }
+
+    pub fn is_obj(&self) -> bool {
+        self.get_obj().is_some()
+    }
+
+    pub fn get_str(&self) -> Option<&str> {
+        if let Self::Str(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_obj(&self) -> Option<&Obj<'text>> {
+        if let Self::Obj(obj) = self {
+            Some(obj)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut_str(&mut self) -> Option<&mut Cow<'text, str>> {
+        if let Self::Str(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut_obj(&mut self) -> Option<&mut Obj<'text>> {
+        if let Self::Obj(obj) = self {
+            Some(obj)
+        } else {
+            None
+        }
+    }
+
+    /// Applies the conditionals of all of the pairs within an object
+    ///
+    /// See [`PartialVdf::evaluate_conditionals()`]
+    pub fn evaluate_conditionals<S: AsRef<str>>(self, defined: &[S]) -> Self {
+        match self {
+            Self::Str(s) => Self::Str(s),
+            Self::Obj(obj) => Self::Obj(obj.evaluate_conditionals(defined)),
+        }
+    }
+}
+
+impl<'text> From<Value<'text>> for crate::Value<'text> {
+    fn from(value: Value<'text>) -> Self {
+        match value {
+            Value::Str(s) => Self::Str(s),
+            Value::Obj(obj) => Self::Obj(obj.into()),
+        }
+    }
+}
+
+impl<'text> From<crate::Value<'text>> for Value<'text> {
+    fn from(value: crate::Value<'text>) -> Self {
+        match value {
+            crate::Value::Str(s) => Self::Str(s),
+            crate::Value::Obj(obj) => Self::Obj(obj.into()),
+        }
+    }
+}
+
+/// An object that stores its pairs in order
+///
+/// Keys can be repeated within an object. Methods that take a key operate on the first matching
+/// pair unless stated otherwise
+///
+/// ```
+/// # use keyvalues_parser::ordered::{Obj, Value};
+/// # use std::borrow::Cow;
+/// let mut obj = Obj::new();
+/// obj.push(Cow::from("key"), Value::Str(Cow::from("first")));
+/// obj.push(Cow::from("earlier key"), Value::Obj(Obj::new()));
+/// obj.push(Cow::from("key"), Value::Str(Cow::from("second")));
+///
+/// // Pairs stay in the order they were added
+/// assert_eq!(
+///     obj.keys().collect::<Vec<_>>(),
+///     ["key", "earlier key", "key"],
+/// );
+/// assert_eq!(
+///     obj.get_all("key").filter_map(Value::get_str).collect::<Vec<_>>(),
+///     ["first", "second"],
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Obj<'text>(pub Vec<Vdf<'text>>);
+
+impl<'text> Obj<'text> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_inner(self) -> Vec<Vdf<'text>> {
+        self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over all of the pairs in order
+    pub fn iter(&self) -> std::slice::Iter<'_, Vdf<'text>> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Vdf<'text>> {
+        self.0.iter_mut()
+    }
+
+    /// Iterates over the keys of all of the pairs in order (including duplicates)
+    pub fn keys(&self) -> impl Iterator<Item = &Key<'text>> + '_ {
+        self.0.iter().map(|vdf| &vdf.key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    /// Returns the value of the first pair with a matching key
+    pub fn get(&self, key: &str) -> Option<&Value<'text>> {
+        self.0
+            .iter()
+            .find(|vdf| vdf.key == key)
+            .map(|vdf| &vdf.value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value<'text>> {
+        self.0
+            .iter_mut()
+            .find(|vdf| vdf.key == key)
+            .map(|vdf| &mut vdf.value)
+    }
+
+    /// Returns the value of the first pair with a key that matches ignoring ASCII case
+    pub fn get_ignore_case(&self, key: &str) -> Option<&Value<'text>> {
+        self.0
+            .iter()
+            .find(|vdf| vdf.key.eq_ignore_ascii_case(key))
+            .map(|vdf| &vdf.value)
+    }
+
+    /// Returns the values of all of the pairs with a key that matches ignoring ASCII case in order
+    pub fn get_all_ignore_case<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> impl Iterator<Item = &'a Value<'text>> + 'a {
+        self.0
+            .iter()
+            .filter(move |vdf| vdf.key.eq_ignore_ascii_case(key))
+            .map(|vdf| &vdf.value)
+    }
+
+    /// Returns the values of all of the pairs with a matching key in order
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a Value<'text>> + 'a {
+        self.0
+            .iter()
+            .filter(move |vdf| vdf.key == key)
+            .map(|vdf| &vdf.value)
+    }
+
+    /// Appends a pair to the end of the object even if the key is already present
+    pub fn push(&mut self, key: Key<'text>, value: Value<'text>) {
+        self.0.push(Vdf::new(key, value));
+    }
+
+    /// Removes the first pair with a matching key
+    pub fn remove(&mut self, key: &str) -> Option<Vdf<'text>> {
+        self.position(key).map(|idx| self.0.remove(idx))
+    }
+
+    /// Removes the pairs whose conditionals don't hold recursively
+    ///
+    /// See [`PartialVdf::evaluate_conditionals()`]
+    pub fn evaluate_conditionals<S: AsRef<str>>(self, defined: &[S]) -> Self {
+        self.into_iter()
+            .filter_map(|vdf| vdf.evaluate_conditionals(defined))
+            .collect()
+    }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        self.0.iter().position(|vdf| vdf.key == key)
+    }
+}
+
+impl<'text> FromIterator<Vdf<'text>> for Obj<'text> {
+    fn from_iter<T: IntoIterator<Item = Vdf<'text>>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<'text> IntoIterator for Obj<'text> {
+    type Item = Vdf<'text>;
+    type IntoIter = std::vec::IntoIter<Vdf<'text>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, 'text> IntoIterator for &'a Obj<'text> {
+    type Item = &'a Vdf<'text>;
+    type IntoIter = std::slice::Iter<'a, Vdf<'text>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Groups duplicate keys together, so the relative order of pairs is lost along with any
+/// conditionals
+impl<'text> From<Obj<'text>> for crate::Obj<'text> {
+    fn from(obj: Obj<'text>) -> Self {
+        let mut inner: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for Vdf { key, value, .. } in obj {
+            inner.entry(key).or_default().push(value.into());
+        }
+        Self(inner)
+    }
+}
+
+/// The pairs end up sorted by key
+impl<'text> From<crate::Obj<'text>> for Obj<'text> {
+    fn from(obj: crate::Obj<'text>) -> Self {
+        obj.into_vdfs().map(Vdf::from).collect()
+    }
+}
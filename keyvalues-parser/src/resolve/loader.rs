@@ -0,0 +1,83 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::normalize;
+
+/// Loads the text of files for a [`Resolver`][super::Resolver]
+///
+/// Paths passed to the loader have already been joined onto the directory of the file that
+/// referenced them and had any `.` and `..` components resolved
+pub trait FileLoader {
+    fn load(&self, path: &Path) -> io::Result<String>;
+}
+
+impl<L: FileLoader + ?Sized> FileLoader for &L {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        (**self).load(path)
+    }
+}
+
+/// Loads files from the filesystem
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdFileLoader;
+
+impl FileLoader for StdFileLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+/// Loads files from a set of in-memory files
+///
+/// ```
+/// use keyvalues_parser::resolve::{FileLoader, MemoryFileLoader};
+/// use std::path::Path;
+///
+/// let mut loader = MemoryFileLoader::new();
+/// loader.insert("resource/ui/hud.res", "hud {}");
+/// assert_eq!(loader.load(Path::new("resource/ui/./hud.res")).unwrap(), "hud {}");
+/// assert!(loader.load(Path::new("resource/hud.res")).is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryFileLoader {
+    files: HashMap<PathBuf, String>,
+}
+
+impl MemoryFileLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file returning the previous contents if there were any
+    pub fn insert(
+        &mut self,
+        path: impl AsRef<Path>,
+        contents: impl Into<String>,
+    ) -> Option<String> {
+        self.files.insert(normalize(path.as_ref()), contents.into())
+    }
+}
+
+impl FileLoader for MemoryFileLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        self.files.get(&normalize(path)).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No in-memory file at {}", path.display()),
+            )
+        })
+    }
+}
+
+impl<P: AsRef<Path>, S: Into<String>> FromIterator<(P, S)> for MemoryFileLoader {
+    fn from_iter<T: IntoIterator<Item = (P, S)>>(iter: T) -> Self {
+        let mut loader = Self::new();
+        for (path, contents) in iter {
+            loader.insert(path, contents);
+        }
+        loader
+    }
+}
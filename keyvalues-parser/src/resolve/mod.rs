@@ -0,0 +1,384 @@
+//! Resolves `#base` and `#include` directives by loading and merging the referenced files
+//!
+//! Paths from directives are looked up relative to the directory of the file that contains them
+//! (with `\` treated as a path separator) and are merged following Valve's semantics
+//!
+//! - `#include`d files have the pairs from their top-level object appended to the including
+//!   file's top-level object
+//! - `#base` files act as defaults. Pairs whose keys are missing get added, and objects that are
+//!   present in both get merged recursively while the including file's values win otherwise
+//!
+//! Includes are applied before bases, and each file is fully resolved before it gets merged in
+//!
+//! ```
+//! use keyvalues_parser::resolve::{MemoryFileLoader, Resolver};
+//! use std::path::Path;
+//!
+//! let loader: MemoryFileLoader = [
+//!     (
+//!         "ui/hud.res",
+//!         r#"#base "shared/defaults.res"
+//!         hud { health { xpos 10 } }"#,
+//!     ),
+//!     ("ui/shared/defaults.res", "hud { health { xpos 0 ypos 0 } ammo { xpos 0 } }"),
+//! ]
+//! .into_iter()
+//! .collect();
+//!
+//! let resolved = Resolver::new(loader).resolve("ui/hud.res")?;
+//! let hud = resolved.vdf.value.get_obj().unwrap();
+//! let health = hud["health"][0].get_obj().unwrap();
+//! assert_eq!(health["xpos"][0].get_str(), Some("10"));
+//! assert_eq!(health["ypos"][0].get_str(), Some("0"));
+//!
+//! let provenance = &resolved.provenance;
+//! assert_eq!(provenance.source(&["health", "xpos"]), Some(Path::new("ui/hud.res")));
+//! assert_eq!(
+//!     provenance.source(&["ammo"]),
+//!     Some(Path::new("ui/shared/defaults.res")),
+//! );
+//! # Ok::<(), keyvalues_parser::resolve::ResolveError>(())
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt, io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{error::ParseError, Obj, Parser, Value, Vdf};
+
+mod loader;
+
+pub use loader::{FileLoader, MemoryFileLoader, StdFileLoader};
+
+/// Loads a file along with everything that it references through `#base` and `#include`
+#[derive(Clone, Debug, Default)]
+pub struct Resolver<L> {
+    loader: L,
+    parser: Parser,
+    sandbox: Option<PathBuf>,
+}
+
+impl<L: FileLoader> Resolver<L> {
+    /// Creates a resolver that loads files with `loader` and parses them with default settings
+    pub fn new(loader: L) -> Self {
+        Self {
+            loader,
+            parser: Parser::new(),
+            sandbox: None,
+        }
+    }
+
+    /// Sets the parser used for each file e.g. to parse files with literal special characters
+    pub fn parser(mut self, parser: Parser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Restricts all loaded files to be within `root`
+    ///
+    /// Relative paths passed to [`Resolver::resolve()`] are looked up relative to `root`, and any
+    /// file that would be loaded from outside of `root` results in a
+    /// [`ResolveError::OutsideSandbox`] instead
+    pub fn sandbox(mut self, root: impl AsRef<Path>) -> Self {
+        self.sandbox = Some(normalize(root.as_ref()));
+        self
+    }
+
+    /// Loads and fully resolves the file at `path`
+    pub fn resolve(&self, path: impl AsRef<Path>) -> Result<Resolved, ResolveError> {
+        let path = match &self.sandbox {
+            Some(root) => root.join(path),
+            None => path.as_ref().to_owned(),
+        };
+
+        let mut state = State::default();
+        let (key, node) = self.resolve_file(normalize(&path), &mut state)?;
+
+        let mut provenance = Provenance {
+            files: state.files,
+            pairs: BTreeMap::new(),
+        };
+        let value = node.into_value(&mut Vec::new(), &mut provenance.pairs);
+        Ok(Resolved {
+            vdf: Vdf::new(Cow::Owned(key), value),
+            provenance,
+        })
+    }
+
+    fn resolve_file(
+        &self,
+        path: PathBuf,
+        state: &mut State,
+    ) -> Result<(String, Node), ResolveError> {
+        if let Some(root) = &self.sandbox {
+            if !path.starts_with(root) {
+                return Err(ResolveError::OutsideSandbox { path });
+            }
+        }
+        if state.stack.contains(&path) {
+            let mut chain = state.stack.clone();
+            chain.push(path);
+            return Err(ResolveError::Cycle { chain });
+        }
+
+        let text = self
+            .loader
+            .load(&path)
+            .map_err(|source| ResolveError::Load {
+                path: path.clone(),
+                source,
+            })?;
+        let partial = self
+            .parser
+            .parse(&text)
+            .map_err(|source| ResolveError::Parse {
+                path: path.clone(),
+                source,
+            })?;
+
+        let file = match state.files.iter().position(|file| file == &path) {
+            Some(file) => file,
+            None => {
+                state.files.push(path.clone());
+                state.files.len() - 1
+            }
+        };
+        let mut node = Node::new(partial.value, file);
+
+        let dir = path.parent().unwrap_or(Path::new("")).to_owned();
+        state.stack.push(path);
+        for include in &partial.includes {
+            let (_, included) = self.resolve_file(join(&dir, include), state)?;
+            node.append(included);
+        }
+        for base in &partial.bases {
+            let (_, base) = self.resolve_file(join(&dir, base), state)?;
+            node.merge_defaults(base);
+        }
+        state.stack.pop();
+
+        Ok((partial.key.into_owned(), node))
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// The chain of files currently being resolved used for detecting cycles
+    stack: Vec<PathBuf>,
+    /// Every file that has been loaded in the order they were first seen
+    files: Vec<PathBuf>,
+}
+
+/// The result of resolving a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolved {
+    /// The fully merged top-level pair which uses the key from the resolved file
+    pub vdf: Vdf<'static>,
+    pub provenance: Provenance,
+}
+
+/// Tracks which file each pair in a [`Resolved`] tree came from
+///
+/// Pairs are identified by their path from the top-level object where each step is a key along
+/// with the index of the value for that key (to tell apart duplicate keys). The empty path refers
+/// to the top-level pair
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Provenance {
+    files: Vec<PathBuf>,
+    pairs: BTreeMap<Vec<(String, usize)>, usize>,
+}
+
+impl Provenance {
+    /// Every file that was loaded in the order they were first seen starting with the resolved
+    /// file
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Returns the file the pair came from following the first value for each key in `keys`
+    pub fn source(&self, keys: &[&str]) -> Option<&Path> {
+        let path: Vec<_> = keys.iter().map(|&key| (key, 0)).collect();
+        self.source_at(&path)
+    }
+
+    /// Returns the file the pair came from where each step selects a key and the index of its value
+    pub fn source_at(&self, path: &[(&str, usize)]) -> Option<&Path> {
+        let path: Vec<_> = path
+            .iter()
+            .map(|&(key, idx)| (key.to_owned(), idx))
+            .collect();
+        self.pairs
+            .get(&path)
+            .map(|&file| self.files[file].as_path())
+    }
+
+    /// Iterates over the path of every pair along with the file it came from
+    pub fn iter(&self) -> impl Iterator<Item = (&[(String, usize)], &Path)> + '_ {
+        self.pairs
+            .iter()
+            .map(|(path, &file)| (path.as_slice(), self.files[file].as_path()))
+    }
+}
+
+/// Errors encountered while resolving files
+#[derive(Debug)]
+pub enum ResolveError {
+    /// The [`FileLoader`] failed to load a file
+    Load { path: PathBuf, source: io::Error },
+    /// A loaded file failed to parse
+    Parse { path: PathBuf, source: ParseError },
+    /// Files reference each other in a loop. The chain starts with the resolved file and ends
+    /// with the file that was referenced again
+    Cycle { chain: Vec<PathBuf> },
+    /// A path led outside of the [sandbox][Resolver::sandbox]
+    OutsideSandbox { path: PathBuf },
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load { path, source } => {
+                write!(f, "Failed loading {} Error: {source}", path.display())
+            }
+            Self::Parse { path, source } => {
+                write!(f, "Failed parsing {} Error: {source}", path.display())
+            }
+            Self::Cycle { chain } => {
+                f.write_str("Encountered a cycle of files: ")?;
+                for (i, path) in chain.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(" -> ")?;
+                    }
+                    write!(f, "{}", path.display())?;
+                }
+                Ok(())
+            }
+            Self::OutsideSandbox { path } => {
+                write!(
+                    f,
+                    "Refusing to load file outside of sandbox: {}",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Load { source, .. } => Some(source),
+            Self::Parse { source, .. } => Some(source),
+            Self::Cycle { .. } | Self::OutsideSandbox { .. } => None,
+        }
+    }
+}
+
+/// A value tagged with the file it came from
+struct Node {
+    file: usize,
+    value: NodeValue,
+}
+
+enum NodeValue {
+    Str(String),
+    Obj(BTreeMap<String, Vec<Node>>),
+}
+
+impl Node {
+    fn new(value: Value<'_>, file: usize) -> Self {
+        let value = match value {
+            Value::Str(s) => NodeValue::Str(s.into_owned()),
+            Value::Obj(obj) => NodeValue::Obj(
+                obj.into_inner()
+                    .into_iter()
+                    .map(|(key, values)| {
+                        let nodes = values.into_iter().map(|v| Self::new(v, file)).collect();
+                        (key.into_owned(), nodes)
+                    })
+                    .collect(),
+            ),
+        };
+        Self { file, value }
+    }
+
+    fn append(&mut self, other: Self) {
+        if let (NodeValue::Obj(obj), NodeValue::Obj(other)) = (&mut self.value, other.value) {
+            for (key, nodes) in other {
+                obj.entry(key).or_default().extend(nodes);
+            }
+        }
+    }
+
+    fn merge_defaults(&mut self, base: Self) {
+        if let (NodeValue::Obj(obj), NodeValue::Obj(base)) = (&mut self.value, base.value) {
+            for (key, base_nodes) in base {
+                match obj.get_mut(&key) {
+                    // Only the first value with a matching key gets merged into
+                    Some(nodes) => {
+                        if let Some(first) = nodes.first_mut() {
+                            for base_node in base_nodes {
+                                first.merge_defaults(base_node);
+                            }
+                        }
+                    }
+                    None => {
+                        obj.insert(key, base_nodes);
+                    }
+                }
+            }
+        }
+    }
+
+    fn into_value(
+        self,
+        path: &mut Vec<(String, usize)>,
+        pairs: &mut BTreeMap<Vec<(String, usize)>, usize>,
+    ) -> Value<'static> {
+        pairs.insert(path.clone(), self.file);
+        match self.value {
+            NodeValue::Str(s) => Value::Str(Cow::Owned(s)),
+            NodeValue::Obj(obj) => {
+                let mut inner = BTreeMap::new();
+                for (key, nodes) in obj {
+                    let mut values = Vec::with_capacity(nodes.len());
+                    for (idx, node) in nodes.into_iter().enumerate() {
+                        path.push((key.clone(), idx));
+                        values.push(node.into_value(path, pairs));
+                        path.pop();
+                    }
+                    inner.insert(Cow::Owned(key), values);
+                }
+                Value::Obj(Obj(inner))
+            }
+        }
+    }
+}
+
+/// Joins a path from a directive onto `dir` treating `\`s as separators
+fn join(dir: &Path, path: &str) -> PathBuf {
+    normalize(&dir.join(path.replace('\\', "/")))
+}
+
+/// Lexically resolves `.` and `..` components without touching the filesystem
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // Can't go above the root
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
@@ -0,0 +1,278 @@
+//! Source location information for parsed VDF text
+//!
+//! Spans are opt-in through [`Parser::with_spans()`][crate::Parser::with_spans] and get stored
+//! in a tree that mirrors the structure of the parsed [`Value`][crate::Value]s
+//!
+//! ```
+//! use keyvalues_parser::Parser;
+//!
+//! let vdf_text = r#"
+//! "Outer Key"
+//! {
+//!     "Inner Key" "Inner Value"
+//! }
+//! "#;
+//! let vdf = Parser::new().with_spans(true).parse(vdf_text)?;
+//! let spans = vdf.spans.unwrap();
+//!
+//! let inner = &spans.value.get_obj().unwrap()["Inner Key"][0];
+//! let value_span = inner.value.get_str().unwrap();
+//! assert_eq!(value_span.as_str(vdf_text), r#""Inner Value""#);
+//! assert_eq!((value_span.start.line, value_span.start.column), (4, 17));
+//! # Ok::<(), keyvalues_parser::error::Error>(())
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    ops::{Deref, DerefMut, Range},
+};
+
+use crate::Key;
+
+/// A single position within some source text
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Location {
+    /// The byte offset from the start of the text
+    pub offset: usize,
+    /// The 1-based line number
+    pub line: usize,
+    /// The 1-based column number counted in `char`s
+    pub column: usize,
+}
+
+/// A region of source text spanning from `start` up to (but not including) `end`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    /// Returns the range of bytes covered by the span
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+
+    /// Returns the length of the span in bytes
+    pub fn len(&self) -> usize {
+        self.end.offset - self.start.offset
+    }
+
+    /// Returns if the span doesn't cover any bytes
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Slices out the spanned portion of the original text
+    ///
+    /// # Panics
+    ///
+    /// If `text` isn't the text that the span was created from
+    pub fn as_str<'text>(&self, text: &'text str) -> &'text str {
+        &text[self.range()]
+    }
+}
+
+/// Maps byte offsets to line and column numbers
+///
+/// Line starts are computed once upfront so that each lookup is just a binary search followed by
+/// counting the `char`s on a single line
+#[derive(Clone, Debug)]
+pub(crate) struct LineIndex<'text> {
+    text: &'text str,
+    line_starts: Vec<usize>,
+}
+
+impl<'text> LineIndex<'text> {
+    pub(crate) fn new(text: &'text str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { text, line_starts }
+    }
+
+    pub(crate) fn text(&self) -> &'text str {
+        self.text
+    }
+
+    pub(crate) fn location(&self, offset: usize) -> Location {
+        let line_idx = match self.line_starts.binary_search(&offset) {
+            Ok(idx) => idx,
+            Err(idx) => idx - 1,
+        };
+        let line_start = self.line_starts[line_idx];
+        let column = self.text[line_start..offset].chars().count() + 1;
+
+        Location {
+            offset,
+            line: line_idx + 1,
+            column,
+        }
+    }
+
+    pub(crate) fn span(&self, range: Range<usize>) -> Span {
+        Span {
+            start: self.location(range.start),
+            end: self.location(range.end),
+        }
+    }
+}
+
+/// Tracks line and column numbers while moving forward through some text
+///
+/// Unlike [`LineIndex`] nothing gets stored per line, so offsets have to be visited in order
+#[derive(Clone, Debug)]
+pub(crate) struct LineCursor<'text> {
+    text: &'text str,
+    current: Location,
+}
+
+impl<'text> LineCursor<'text> {
+    pub(crate) fn new(text: &'text str) -> Self {
+        Self {
+            text,
+            current: Location {
+                offset: 0,
+                line: 1,
+                column: 1,
+            },
+        }
+    }
+
+    /// # Panics
+    ///
+    /// If `offset` comes before an offset that was already visited
+    pub(crate) fn location(&mut self, offset: usize) -> Location {
+        let skipped = &self.text[self.current.offset..offset];
+        match skipped.rfind('\n') {
+            Some(last_newline) => {
+                self.current.line += skipped.bytes().filter(|&b| b == b'\n').count();
+                self.current.column = skipped[last_newline + 1..].chars().count() + 1;
+            }
+            None => self.current.column += skipped.chars().count(),
+        }
+        self.current.offset = offset;
+        self.current
+    }
+
+    pub(crate) fn span(&mut self, range: Range<usize>) -> Span {
+        Span {
+            start: self.location(range.start),
+            end: self.location(range.end),
+        }
+    }
+}
+
+/// The spans for a single key-value pair
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PairSpans<'text> {
+    /// The span of the key including any surrounding quotes
+    pub key: Span,
+    pub value: ValueSpans<'text>,
+    /// The span of the trailing conditional tag if there is one e.g. `[$WIN32]`
+    pub conditional: Option<Span>,
+}
+
+impl PairSpans<'_> {
+    pub(crate) fn into_owned(self) -> PairSpans<'static> {
+        PairSpans {
+            key: self.key,
+            value: self.value.into_owned(),
+            conditional: self.conditional,
+        }
+    }
+}
+
+/// The spans for a [`Value`][crate::Value] mirroring its structure
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ValueSpans<'text> {
+    /// The span of a string including any surrounding quotes
+    Str(Span),
+    Obj(ObjSpans<'text>),
+}
+
+impl<'text> ValueSpans<'text> {
+    pub(crate) fn into_owned(self) -> ValueSpans<'static> {
+        match self {
+            Self::Str(span) => ValueSpans::Str(span),
+            Self::Obj(obj) => ValueSpans::Obj(obj.into_owned()),
+        }
+    }
+
+    /// Returns the span covering the whole value
+    ///
+    /// For objects this spans from the opening brace through the closing brace
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Str(span) => *span,
+            Self::Obj(obj) => Span {
+                start: obj.open.start,
+                end: obj.close.end,
+            },
+        }
+    }
+
+    /// Gets the inner [`Span`] if this is a `ValueSpans::Str`
+    pub fn get_str(&self) -> Option<&Span> {
+        if let Self::Str(span) = self {
+            Some(span)
+        } else {
+            None
+        }
+    }
+
+    /// Gets the inner [`ObjSpans`] if this is a `ValueSpans::Obj`
+    pub fn get_obj(&self) -> Option<&ObjSpans<'text>> {
+        if let Self::Obj(obj) = self {
+            Some(obj)
+        } else {
+            None
+        }
+    }
+}
+
+/// The spans for an [`Obj`][crate::Obj]
+///
+/// The pairs mirror the layout of the original [`Obj`][crate::Obj], so the spans for the value at
+/// `obj[key][i]` can be found at `obj_spans[key][i]`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjSpans<'text> {
+    /// The span of the opening `{`
+    pub open: Span,
+    /// The span of the closing `}`
+    pub close: Span,
+    pub pairs: BTreeMap<Key<'text>, Vec<PairSpans<'text>>>,
+}
+
+impl ObjSpans<'_> {
+    pub(crate) fn into_owned(self) -> ObjSpans<'static> {
+        let pairs = self
+            .pairs
+            .into_iter()
+            .map(|(key, spans)| {
+                let spans = spans.into_iter().map(PairSpans::into_owned).collect();
+                (Cow::Owned(key.into_owned()), spans)
+            })
+            .collect();
+        ObjSpans {
+            open: self.open,
+            close: self.close,
+            pairs,
+        }
+    }
+}
+
+impl<'text> Deref for ObjSpans<'text> {
+    type Target = BTreeMap<Key<'text>, Vec<PairSpans<'text>>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pairs
+    }
+}
+
+impl DerefMut for ObjSpans<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.pairs
+    }
+}
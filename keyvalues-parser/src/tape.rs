@@ -0,0 +1,688 @@
+//! A flat index over VDF text for pulling a few values out of large documents
+//!
+//! Building a [`Tape`] makes a single pass over the text that only records where each string and
+//! object is. Nothing gets decoded or allocated per string, so it's much cheaper than building a
+//! full [`Vdf`][crate::Vdf] tree. Strings are only decoded (escapes and all) once they're
+//! accessed through a [`TapeStr`], and any part of the tape can be turned into the regular
+//! representation with [`TapeValue::to_value()`] or [`Tape::to_vdf()`]
+//!
+//! ```
+//! use keyvalues_parser::Parser;
+//!
+//! let vdf_text = r#"
+//! "AppState"
+//! {
+//!     "appid" "620"
+//!     "UserConfig" { "language" "english" }
+//!     "InstalledDepots" { "621" { "manifest" "1234" } }
+//! }
+//! "#;
+//! let tape = Parser::new().tape(vdf_text)?;
+//! let root = tape.root();
+//! assert_eq!(root.key(), "AppState");
+//!
+//! let language = root
+//!     .value()
+//!     .get_path(&["UserConfig", "language"])
+//!     .and_then(|value| value.get_str())
+//!     .unwrap();
+//! assert_eq!(language.decode(), "english");
+//! # Ok::<(), keyvalues_parser::error::ParseError>(())
+//! ```
+//!
+//! The layout is similar to [simdjson's tape](https://github.com/simdjson/simdjson/blob/master/doc/tape.md).
+//! Every pair is stored as its key followed by its value, and each object stores the index just
+//! past its last pair, so whole objects can be stepped over without looking at their contents
+
+use std::{borrow::Cow, collections::BTreeMap, fmt, ops::Range};
+
+use crate::{
+    error::{Expected, ParseError, ParseErrorKind},
+    span::LineIndex,
+    text::{
+        lex::{Lexeme, Scanner, TokenKind},
+        parse::{
+            check_input_len, found, invalid_escapes, shares_line, strict_errors, truncate,
+            unescape, VALVE_MAX_TOKEN_LEN,
+        },
+    },
+    DuplicateKeys, Obj, Parser, PartialVdf, Value,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Entry {
+    /// A string with the byte range of its contents (excluding any quotes)
+    Str {
+        start: usize,
+        end: usize,
+        quoted: bool,
+    },
+    /// An object holding `len` pairs where `end` is the index right after its last entry
+    Obj { len: usize, end: usize },
+}
+
+/// The index built by [`Parser::tape()`]
+///
+/// Conditional tags are skipped over, and keys are folded and deduplicated according to the
+/// [`Parser`], the same as with [`Parser::parse()`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tape<'text> {
+    text: &'text str,
+    escaped: bool,
+    /// Whether strings get cut off like [`Parser::valve_compat()`]
+    truncate: bool,
+    bases: Vec<Range<usize>>,
+    includes: Vec<Range<usize>>,
+    /// The top-level key and value followed by the rest of the document in order
+    entries: Vec<Entry>,
+}
+
+impl<'text> Tape<'text> {
+    pub(crate) fn new(parser: &Parser, text: &'text str) -> Result<Self, ParseError> {
+        check_input_len(parser, text)?;
+        let mut builder = Builder {
+            scanner: Scanner::new(text, !parser.literal_special_chars),
+            parser,
+            tape: Self {
+                text,
+                escaped: !parser.literal_special_chars,
+                truncate: parser.valve_compat,
+                bases: Vec::new(),
+                includes: Vec::new(),
+                entries: Vec::new(),
+            },
+            opens: Vec::new(),
+            num_pairs: 0,
+        };
+        builder.document()?;
+        Ok(builder.tape)
+    }
+
+    /// Returns the top-level pair
+    pub fn root(&self) -> TapePair<'_, 'text> {
+        TapePair { tape: self, idx: 0 }
+    }
+
+    /// Returns the paths from any `#base` directives
+    pub fn bases(&self) -> impl Iterator<Item = &'text str> + '_ {
+        self.bases.iter().map(|range| &self.text[range.clone()])
+    }
+
+    /// Returns the paths from any `#include` directives
+    pub fn includes(&self) -> impl Iterator<Item = &'text str> + '_ {
+        self.includes.iter().map(|range| &self.text[range.clone()])
+    }
+
+    /// Decodes the entire tape to the same [`PartialVdf`] that [`Parser::parse()`] would return
+    /// (without any spans)
+    pub fn to_vdf(&self) -> PartialVdf<'text> {
+        let root = self.root();
+        PartialVdf {
+            key: root.key().decode(),
+            value: root.value().to_value(),
+            bases: self.bases().map(Cow::from).collect(),
+            includes: self.includes().map(Cow::from).collect(),
+            spans: None,
+        }
+    }
+
+    fn str_at(&self, idx: usize) -> TapeStr<'text> {
+        match self.entries[idx] {
+            Entry::Str { start, end, quoted } => TapeStr {
+                raw: &self.text[start..end],
+                escaped: quoted && self.escaped,
+                truncate: self.truncate,
+            },
+            Entry::Obj { .. } => unreachable!("Keys are always strings"),
+        }
+    }
+
+    /// Returns the index of the entry after the value at `idx`
+    fn skip(&self, idx: usize) -> usize {
+        match self.entries[idx] {
+            Entry::Str { .. } => idx + 1,
+            Entry::Obj { end, .. } => end,
+        }
+    }
+}
+
+/// A string within a [`Tape`] that's only decoded when asked
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TapeStr<'text> {
+    raw: &'text str,
+    escaped: bool,
+    truncate: bool,
+}
+
+impl<'text> TapeStr<'text> {
+    /// Returns the string as it appears in the text without the surrounding quotes
+    pub fn raw(&self) -> &'text str {
+        self.raw
+    }
+
+    /// Returns the string with any escape sequences replaced
+    ///
+    /// This only allocates when there are escape sequences to replace. Long strings are also cut
+    /// off here when building the tape with [`Parser::valve_compat()`]
+    pub fn decode(&self) -> Cow<'text, str> {
+        let s = if self.escaped {
+            unescape(self.raw)
+        } else {
+            Cow::from(self.raw)
+        };
+        if self.truncate && s.len() > VALVE_MAX_TOKEN_LEN {
+            truncate(s, VALVE_MAX_TOKEN_LEN)
+        } else {
+            s
+        }
+    }
+
+    /// Returns if [`TapeStr::decode()`] would differ from [`TapeStr::raw()`]
+    fn needs_decoding(&self) -> bool {
+        // Replacing escapes only ever makes strings shorter
+        (self.escaped && self.raw.contains('\\'))
+            || (self.truncate && self.raw.len() > VALVE_MAX_TOKEN_LEN)
+    }
+}
+
+impl PartialEq<str> for TapeStr<'_> {
+    fn eq(&self, other: &str) -> bool {
+        if self.needs_decoding() {
+            self.decode() == other
+        } else {
+            self.raw == other
+        }
+    }
+}
+
+impl PartialEq<&str> for TapeStr<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl fmt::Display for TapeStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.decode())
+    }
+}
+
+/// A key-value pair within a [`Tape`]
+#[derive(Clone, Copy, Debug)]
+pub struct TapePair<'tape, 'text> {
+    tape: &'tape Tape<'text>,
+    /// The index of the key
+    idx: usize,
+}
+
+impl<'tape, 'text> TapePair<'tape, 'text> {
+    pub fn key(&self) -> TapeStr<'text> {
+        self.tape.str_at(self.idx)
+    }
+
+    pub fn value(&self) -> TapeValue<'tape, 'text> {
+        TapeValue {
+            tape: self.tape,
+            idx: self.idx + 1,
+        }
+    }
+}
+
+/// A value within a [`Tape`] which is either a string or an object
+#[derive(Clone, Copy, Debug)]
+pub struct TapeValue<'tape, 'text> {
+    tape: &'tape Tape<'text>,
+    idx: usize,
+}
+
+impl<'tape, 'text> TapeValue<'tape, 'text> {
+    pub fn is_str(&self) -> bool {
+        self.get_str().is_some()
+    }
+
+    pub fn is_obj(&self) -> bool {
+        self.get_obj().is_some()
+    }
+
+    pub fn get_str(&self) -> Option<TapeStr<'text>> {
+        match self.tape.entries[self.idx] {
+            Entry::Str { .. } => Some(self.tape.str_at(self.idx)),
+            Entry::Obj { .. } => None,
+        }
+    }
+
+    pub fn get_obj(&self) -> Option<TapeObj<'tape, 'text>> {
+        match self.tape.entries[self.idx] {
+            Entry::Str { .. } => None,
+            Entry::Obj { .. } => Some(TapeObj {
+                tape: self.tape,
+                idx: self.idx,
+            }),
+        }
+    }
+
+    /// Follows `path` through nested objects taking the first value for each key
+    ///
+    /// An empty path returns the value itself
+    pub fn get_path(&self, path: &[&str]) -> Option<Self> {
+        path.iter()
+            .try_fold(*self, |value, key| value.get_obj()?.get(key))
+    }
+
+    /// Decodes the value to the regular representation
+    pub fn to_value(&self) -> Value<'text> {
+        match self.get_obj() {
+            Some(obj) => Value::Obj(obj.to_obj()),
+            None => Value::Str(self.tape.str_at(self.idx).decode()),
+        }
+    }
+}
+
+/// An object within a [`Tape`]
+#[derive(Clone, Copy, Debug)]
+pub struct TapeObj<'tape, 'text> {
+    tape: &'tape Tape<'text>,
+    idx: usize,
+}
+
+impl<'tape, 'text> TapeObj<'tape, 'text> {
+    /// Returns the number of pairs in the object including any duplicate keys that were kept
+    pub fn len(&self) -> usize {
+        match self.tape.entries[self.idx] {
+            Entry::Obj { len, .. } => len,
+            Entry::Str { .. } => unreachable!("Always points at an object"),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over the pairs in document order
+    pub fn iter(&self) -> TapePairs<'tape, 'text> {
+        TapePairs {
+            tape: self.tape,
+            next: self.idx + 1,
+            end: self.tape.skip(self.idx),
+        }
+    }
+
+    /// Returns the value of the first pair with a matching key
+    pub fn get(&self, key: &str) -> Option<TapeValue<'tape, 'text>> {
+        self.get_all(key).next()
+    }
+
+    /// Returns the values of all of the pairs with a matching key in document order
+    pub fn get_all<'a>(&self, key: &'a str) -> impl Iterator<Item = TapeValue<'tape, 'text>> + 'a
+    where
+        'tape: 'a,
+        'text: 'a,
+    {
+        self.iter()
+            .filter(move |pair| pair.key() == key)
+            .map(|pair| pair.value())
+    }
+
+    /// Decodes the object to the regular representation
+    pub fn to_obj(&self) -> Obj<'text> {
+        let mut obj = Obj::new();
+        for pair in self.iter() {
+            obj.entry(pair.key().decode())
+                .or_default()
+                .push(pair.value().to_value());
+        }
+        obj
+    }
+}
+
+impl<'tape, 'text> IntoIterator for TapeObj<'tape, 'text> {
+    type Item = TapePair<'tape, 'text>;
+    type IntoIter = TapePairs<'tape, 'text>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the pairs of a [`TapeObj`]
+#[derive(Clone, Debug)]
+pub struct TapePairs<'tape, 'text> {
+    tape: &'tape Tape<'text>,
+    next: usize,
+    end: usize,
+}
+
+impl<'tape, 'text> Iterator for TapePairs<'tape, 'text> {
+    type Item = TapePair<'tape, 'text>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+
+        let idx = self.next;
+        self.next = self.tape.skip(idx + 1);
+        Some(TapePair {
+            tape: self.tape,
+            idx,
+        })
+    }
+}
+
+/// An object that's still being filled in by the [`Builder`]
+struct Open<'text> {
+    /// The index of the object's entry
+    idx: usize,
+    /// Where the object's `{` is
+    brace: usize,
+    /// The key of the pair that the object is the value of
+    key: PendingKey,
+    /// The keys within the object (lowercased when folding) when they have to be tracked
+    keys: BTreeMap<Cow<'text, str>, SeenKey>,
+}
+
+/// The key of a pair that hasn't been added to its object yet
+#[derive(Clone, Debug)]
+struct PendingKey {
+    /// The index of the key's entry
+    idx: usize,
+    range: Range<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SeenKey {
+    /// The entry for the first spelling of the key
+    spelling: Entry,
+    /// Where the key was first seen
+    first: usize,
+    /// The index of the key of the pair that's currently kept
+    idx: usize,
+}
+
+/// Fills in a [`Tape`] while checking the text the same way as [`Parser::parse()`]
+///
+/// Objects are filled in without recursing. A pair with an object value gets added to its own
+/// object once the `}` is reached, which is when keys get folded and the duplicate key policy
+/// gets applied
+struct Builder<'parser, 'text> {
+    scanner: Scanner<'text>,
+    parser: &'parser Parser,
+    tape: Tape<'text>,
+    opens: Vec<Open<'text>>,
+    num_pairs: usize,
+}
+
+impl<'text> Builder<'_, 'text> {
+    fn text(&self) -> &'text str {
+        self.scanner.text()
+    }
+
+    fn error(&self, kind: ParseErrorKind, range: Range<usize>) -> ParseError {
+        ParseError::new(self.text(), kind, range)
+    }
+
+    fn unexpected(&self, token: Option<&Lexeme>, expected: Vec<Expected>) -> ParseError {
+        let end = self.text().len();
+        let range = token.map_or(end..end, |token| token.range.clone());
+        let kind = ParseErrorKind::Unexpected {
+            found: found(token),
+            expected,
+        };
+        self.error(kind, range)
+    }
+
+    fn document(&mut self) -> Result<(), ParseError> {
+        self.directives()?;
+
+        let token = self.scanner.next_token();
+        match token.as_ref().map(|token| token.kind) {
+            None | Some(TokenKind::ObjOpen | TokenKind::ObjClose) => {
+                return Err(self.unexpected(token.as_ref(), vec![Expected::Key]));
+            }
+            Some(_) => self.pair(token.unwrap())?,
+        }
+
+        while let Some(open) = self.opens.last() {
+            let token = self.scanner.next_token();
+            match token.as_ref().map(|token| token.kind) {
+                None => {
+                    // Valve closes any objects left open at the end of the text
+                    if !self.parser.valve_compat {
+                        let end = self.text().len();
+                        let lines = LineIndex::new(self.text());
+                        let open = lines.location(open.brace);
+                        let kind = ParseErrorKind::UnclosedObject { open };
+                        return Err(ParseError::with_lines(&lines, kind, end..end));
+                    }
+                    self.close()?;
+                }
+                Some(TokenKind::ObjClose) => self.close()?,
+                Some(TokenKind::ObjOpen) => {
+                    let expected = vec![Expected::Key, Expected::ObjClose];
+                    return Err(self.unexpected(token.as_ref(), expected));
+                }
+                Some(_) => self.pair(token.unwrap())?,
+            }
+        }
+
+        // Valve ignores anything following the top-level pair
+        match self.scanner.end().filter(|_| !self.parser.valve_compat) {
+            Some(token) => Err(self.unexpected(Some(&token), vec![Expected::EndOfInput])),
+            None => Ok(()),
+        }
+    }
+
+    /// Consumes as many `#base` and `#include` directives as possible
+    fn directives(&mut self) -> Result<(), ParseError> {
+        while let Some(directive) = self.scanner.directive() {
+            let path = directive.path.contents();
+            if directive.is_base {
+                self.tape.bases.push(path);
+            } else {
+                self.tape.includes.push(path);
+            }
+
+            if self.parser.strict {
+                if let Some(token) = shares_line(&self.scanner) {
+                    return Err(self.error(ParseErrorKind::DirectiveSharesLine, token.range));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records a pair up through its value or the `{` that opens its value
+    fn pair(&mut self, key_token: Lexeme) -> Result<(), ParseError> {
+        self.num_pairs += 1;
+        if let Some(limit) = self.parser.max_pairs.filter(|&l| self.num_pairs > l) {
+            let kind = ParseErrorKind::PairLimitExceeded { limit };
+            return Err(self.error(kind, key_token.range));
+        }
+
+        let key = PendingKey {
+            idx: self.tape.entries.len(),
+            range: key_token.range.clone(),
+        };
+        self.string(key_token)?;
+
+        let token = self.scanner.next_token();
+        match token.as_ref().map(|token| token.kind) {
+            Some(TokenKind::ObjOpen) => self.open(token.unwrap().range, key),
+            Some(TokenKind::ObjClose) | None => {
+                // Valve silently drops keys that are missing their values within objects
+                if !self.parser.valve_compat || self.opens.is_empty() {
+                    return Err(self.unexpected(token.as_ref(), vec![Expected::Value]));
+                }
+                // Leave the `}` to close out the current object
+                if let Some(token) = token {
+                    self.scanner.reset(token.range.start);
+                }
+                self.tape.entries.truncate(key.idx);
+                Ok(())
+            }
+            Some(_) => {
+                self.string(token.unwrap())?;
+                self.scanner.conditional();
+                self.finish_pair(key)
+            }
+        }
+    }
+
+    fn open(&mut self, brace: Range<usize>, key: PendingKey) -> Result<(), ParseError> {
+        if let Some(limit) = self.parser.max_depth.filter(|&l| self.opens.len() + 1 > l) {
+            return Err(self.error(ParseErrorKind::DepthLimitExceeded { limit }, brace));
+        }
+
+        self.opens.push(Open {
+            idx: self.tape.entries.len(),
+            brace: brace.start,
+            key,
+            keys: BTreeMap::new(),
+        });
+        self.tape.entries.push(Entry::Obj { len: 0, end: 0 });
+        Ok(())
+    }
+
+    /// Closes the innermost object and finishes the pair that it's the value of
+    fn close(&mut self) -> Result<(), ParseError> {
+        let open = self.opens.pop().expect("Only called with an open object");
+        let end = self.tape.entries.len();
+        if let Entry::Obj { end: obj_end, .. } = &mut self.tape.entries[open.idx] {
+            *obj_end = end;
+        }
+        self.scanner.conditional();
+        self.finish_pair(open.key)
+    }
+
+    /// Adds the pair that was just finished to the object it's in
+    fn finish_pair(&mut self, key: PendingKey) -> Result<(), ParseError> {
+        let Some(obj_idx) = self.opens.last().map(|open| open.idx) else {
+            // The top-level pair
+            return Ok(());
+        };
+
+        let tracks_keys =
+            self.parser.fold_keys || self.parser.duplicate_keys != DuplicateKeys::KeepAll;
+        if tracks_keys && !self.dedupe(&key)? {
+            self.tape.entries.truncate(key.idx);
+            return Ok(());
+        }
+
+        if let Entry::Obj { len, .. } = &mut self.tape.entries[obj_idx] {
+            *len += 1;
+        }
+        Ok(())
+    }
+
+    /// Folds the pair's key and applies the duplicate key policy returning whether the pair
+    /// should be kept
+    fn dedupe(&mut self, key: &PendingKey) -> Result<bool, ParseError> {
+        let decoded = self.tape.str_at(key.idx).decode();
+        let lookup = if self.parser.fold_keys {
+            Cow::Owned(decoded.to_ascii_lowercase())
+        } else {
+            decoded
+        };
+        let keys = &mut self.opens.last_mut().expect("Within an object").keys;
+        let Some(&seen) = keys.get(&lookup) else {
+            let seen = SeenKey {
+                spelling: self.tape.entries[key.idx],
+                first: key.range.start,
+                idx: key.idx,
+            };
+            keys.insert(lookup, seen);
+            return Ok(true);
+        };
+
+        if self.parser.fold_keys {
+            self.tape.entries[key.idx] = seen.spelling;
+        }
+        match self.parser.duplicate_keys {
+            DuplicateKeys::KeepAll => Ok(true),
+            DuplicateKeys::FirstWins => Ok(false),
+            DuplicateKeys::LastWins => {
+                let removed = self.remove_pair(seen.idx);
+                let keys = &mut self.opens.last_mut().expect("Within an object").keys;
+                if let Some(seen) = keys.get_mut(&lookup) {
+                    seen.idx = key.idx - removed;
+                }
+                Ok(true)
+            }
+            DuplicateKeys::Error => {
+                let lines = LineIndex::new(self.text());
+                let kind = ParseErrorKind::DuplicateKey {
+                    key: self.tape.str_at(key.idx).decode().into_owned(),
+                    first: lines.location(seen.first),
+                };
+                Err(ParseError::with_lines(&lines, kind, key.range.clone()))
+            }
+        }
+    }
+
+    /// Removes the finished pair with its key at `idx` from the innermost open object returning
+    /// how many entries were removed
+    fn remove_pair(&mut self, idx: usize) -> usize {
+        let end = self.tape.skip(idx + 1);
+        let removed = end - idx;
+        self.tape.entries.drain(idx..end);
+
+        // Everything that came after the pair got shifted back
+        for entry in &mut self.tape.entries[idx..] {
+            if let Entry::Obj { end, .. } = entry {
+                *end -= removed;
+            }
+        }
+        let open = self.opens.last_mut().expect("Within an object");
+        for seen in open.keys.values_mut().filter(|seen| seen.idx > idx) {
+            seen.idx -= removed;
+        }
+        if let Entry::Obj { len, .. } = &mut self.tape.entries[open.idx] {
+            *len -= 1;
+        }
+
+        removed
+    }
+
+    fn string(&mut self, token: Lexeme) -> Result<(), ParseError> {
+        let text = self.text();
+        let quoted = match token.kind {
+            TokenKind::UnquotedStr => false,
+            TokenKind::QuotedStr => true,
+            TokenKind::UnterminatedStr => {
+                let start = token.range.start;
+                let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+                return Err(self.error(ParseErrorKind::UnclosedString, start..end));
+            }
+            _ => unreachable!("Only called with strings"),
+        };
+        let contents = token.contents();
+
+        if self.parser.strict {
+            let mut errors = strict_errors(text, token.kind, token.range.start, contents.clone());
+            if let Some((kind, range)) = errors.next() {
+                return Err(self.error(kind, range));
+            }
+        }
+        if let Some(limit) = self.parser.max_str_len.filter(|&l| contents.len() > l) {
+            let kind = ParseErrorKind::StrLenLimitExceeded { limit };
+            return Err(self.error(kind, token.range));
+        }
+
+        // Unknown escape sequences are kept as is when they're lenient
+        if quoted && self.tape.escaped && !self.parser.lenient_escapes {
+            if let Some(invalid) = invalid_escapes(&text[contents.clone()]).next() {
+                let range = contents.start + invalid.start..contents.start + invalid.end;
+                let sequence = text[range.clone()].to_owned();
+                return Err(self.error(ParseErrorKind::InvalidEscape { sequence }, range));
+            }
+        }
+
+        self.tape.entries.push(Entry::Str {
+            start: contents.start,
+            end: contents.end,
+            quoted,
+        });
+        Ok(())
+    }
+}
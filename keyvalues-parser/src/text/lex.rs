@@ -0,0 +1,437 @@
+//! A lossless lexer for VDF text meant for syntax tooling like highlighters and formatters
+//!
+//! Unlike the parsers, the [`Lexer`] keeps all of the trivia (whitespace and comments) and never
+//! fails. Text that can't form a valid token is still emitted as a token with an error kind (see
+//! [`TokenKind::is_error()`]), so the spans of all of the tokens always cover the entire text
+//!
+//! ```
+//! use keyvalues_parser::text::lex::{Lexer, TokenKind};
+//!
+//! let vdf_text = "#base base.vdf\nkey // comment\n{ inner \"value\" [$WIN32] }";
+//! let kinds: Vec<_> = Lexer::new(vdf_text)
+//!     .map(|token| token.kind)
+//!     .filter(|kind| *kind != TokenKind::Whitespace)
+//!     .collect();
+//! assert_eq!(
+//!     kinds,
+//!     [
+//!         TokenKind::Directive,
+//!         TokenKind::UnquotedStr,
+//!         TokenKind::UnquotedStr,
+//!         TokenKind::Comment,
+//!         TokenKind::ObjOpen,
+//!         TokenKind::UnquotedStr,
+//!         TokenKind::QuotedStr,
+//!         TokenKind::Conditional,
+//!         TokenKind::ObjClose,
+//!     ]
+//! );
+//!
+//! // every byte belongs to a token
+//! let relexed: String = Lexer::new(vdf_text)
+//!     .map(|token| token.span.as_str(vdf_text))
+//!     .collect();
+//! assert_eq!(relexed, vdf_text);
+//! ```
+
+use std::ops::Range;
+
+use crate::{
+    conditional::Conditional,
+    span::{LineCursor, Span},
+};
+
+const DIRECTIVES: [&str; 2] = ["#base", "#include"];
+
+/// The different kinds of [`Token`]s
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum TokenKind {
+    /// A run of ` `, `\t`, `\r`, and `\n`
+    Whitespace,
+    /// `//` up to, but not including, the end of the line
+    Comment,
+    /// A string wrapped in `"`s (the quotes are included in the token)
+    QuotedStr,
+    /// A quoted string that hit the end of the text before its closing `"`
+    UnterminatedStr,
+    UnquotedStr,
+    ObjOpen,
+    ObjClose,
+    /// The `#base` or `#include` keyword at the start of a directive
+    ///
+    /// The path that follows is a regular string token
+    Directive,
+    /// A conditional tag following a value e.g. `[$WIN32 && !$X360]`
+    Conditional,
+}
+
+impl TokenKind {
+    /// Returns if the token is whitespace or a comment
+    pub fn is_trivia(self) -> bool {
+        matches!(self, Self::Whitespace | Self::Comment)
+    }
+
+    /// Returns if the token is a string of any kind
+    pub fn is_str(self) -> bool {
+        matches!(
+            self,
+            Self::QuotedStr | Self::UnterminatedStr | Self::UnquotedStr
+        )
+    }
+
+    /// Returns if the token can never be part of valid VDF text
+    pub fn is_error(self) -> bool {
+        matches!(self, Self::UnterminatedStr)
+    }
+}
+
+/// A single token emitted by the [`Lexer`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// What the lexer expects next which is used to pick out directives and conditionals
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Context {
+    Directives,
+    DirectivePath,
+    Key,
+    Value,
+    AfterValue,
+}
+
+/// An iterator over all of the [`Token`]s in some VDF text
+///
+/// Lexing follows the same rules as [`Parser`][crate::Parser], so `#base` and `#include` are
+/// only treated as directives before the top-level pair and conditionals are only picked out
+/// after values
+#[derive(Clone, Debug)]
+pub struct Lexer<'text> {
+    scanner: Scanner<'text>,
+    cursor: LineCursor<'text>,
+    escaped: bool,
+    context: Context,
+}
+
+impl<'text> Lexer<'text> {
+    pub fn new(text: &'text str) -> Self {
+        Self {
+            scanner: Scanner::new(text, true),
+            cursor: LineCursor::new(text),
+            escaped: true,
+            context: Context::Directives,
+        }
+    }
+
+    /// Lex special characters in quoted strings literally instead of as escape sequences
+    ///
+    /// This matches [`Parser::literal_special_chars()`][crate::Parser::literal_special_chars]
+    ///
+    /// ```
+    /// use keyvalues_parser::text::lex::{Lexer, TokenKind};
+    ///
+    /// let vdf_text = r#"key "C:\Dir\""#;
+    /// let last = |lexer: Lexer| lexer.last().unwrap().kind;
+    /// assert_eq!(last(Lexer::new(vdf_text)), TokenKind::UnterminatedStr);
+    /// assert_eq!(
+    ///     last(Lexer::new(vdf_text).literal_special_chars(true)),
+    ///     TokenKind::QuotedStr,
+    /// );
+    /// ```
+    pub fn literal_special_chars(mut self, literal: bool) -> Self {
+        self.escaped = !literal;
+        self.scanner.escaped = !literal;
+        self
+    }
+
+    /// Splits off the `#base` or `#include` keyword if `lexeme` starts a directive
+    fn directive(&mut self, lexeme: &Lexeme) -> Option<Range<usize>> {
+        let mut lookahead = self.scanner.clone();
+        lookahead.reset(lexeme.range.start);
+        let Directive { keyword, .. } = lookahead.directive()?;
+
+        // The path is a regular string token, but directive paths never contain escapes
+        self.scanner.reset(keyword.end);
+        self.scanner.escaped = false;
+        Some(keyword)
+    }
+
+    /// Picks out directives and conditionals while keeping track of what comes next
+    fn significant(&mut self, lexeme: Lexeme) -> (TokenKind, Range<usize>) {
+        match self.context {
+            Context::Directives => {
+                if let Some(keyword) = self.directive(&lexeme) {
+                    self.context = Context::DirectivePath;
+                    return (TokenKind::Directive, keyword);
+                }
+            }
+            Context::DirectivePath => {
+                self.scanner.escaped = self.escaped;
+                self.context = Context::Directives;
+                return (lexeme.kind, lexeme.range);
+            }
+            Context::AfterValue => {
+                if let Some(conditional) = self.conditional(&lexeme) {
+                    self.context = Context::Key;
+                    return (TokenKind::Conditional, conditional);
+                }
+            }
+            Context::Key | Context::Value => {}
+        }
+
+        // Anything else is a string since the scanner doesn't pick out directives or conditionals
+        self.context = match lexeme.kind {
+            TokenKind::ObjOpen => Context::Key,
+            TokenKind::ObjClose => Context::AfterValue,
+            _ if self.context == Context::Value => Context::AfterValue,
+            _ => Context::Value,
+        };
+        (lexeme.kind, lexeme.range)
+    }
+
+    fn conditional(&mut self, lexeme: &Lexeme) -> Option<Range<usize>> {
+        self.scanner.reset(lexeme.range.start);
+        match self.scanner.conditional() {
+            Some((_, range)) => Some(range),
+            None => {
+                self.scanner.reset(lexeme.range.end);
+                None
+            }
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let lexeme = self.scanner.next()?;
+        let (kind, range) = if lexeme.kind.is_trivia() {
+            (lexeme.kind, lexeme.range)
+        } else {
+            self.significant(lexeme)
+        };
+
+        let span = self.cursor.span(range);
+        Some(Token { kind, span })
+    }
+}
+
+/// A token from the [`Scanner`] which only tracks its byte range
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Lexeme {
+    pub(crate) kind: TokenKind,
+    pub(crate) range: Range<usize>,
+}
+
+impl Lexeme {
+    /// Returns the range of a string without the quotes around quoted strings
+    pub(crate) fn contents(&self) -> Range<usize> {
+        match self.kind {
+            TokenKind::QuotedStr => self.range.start + 1..self.range.end - 1,
+            _ => self.range.clone(),
+        }
+    }
+}
+
+/// A `#base` or `#include` directive picked out by [`Scanner::directive()`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Directive {
+    pub(crate) is_base: bool,
+    pub(crate) keyword: Range<usize>,
+    /// Either a quoted or unquoted string. A path glued onto the keyword e.g. `#basefile.vdf` is
+    /// split off into its own unquoted string
+    pub(crate) path: Lexeme,
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+fn is_unquoted(b: u8) -> bool {
+    !is_whitespace(b) && !matches!(b, b'"' | b'{' | b'}')
+}
+
+/// The context-free lexer underlying the parsers and the [`Lexer`]
+///
+/// Iterating never emits [`TokenKind::Directive`] or [`TokenKind::Conditional`] since those depend
+/// on the surrounding tokens. Instead whatever drives the scanner picks them out with
+/// [`Scanner::directive()`] and [`Scanner::conditional()`], so that every parser shares the same
+/// rules. Every byte that we care about is ASCII, so we can scan over bytes while only ever slicing
+/// on `char` boundaries
+#[derive(Clone, Debug)]
+pub(crate) struct Scanner<'text> {
+    text: &'text str,
+    pos: usize,
+    /// Whether a `\` in a quoted string escapes the following character
+    pub(crate) escaped: bool,
+}
+
+impl<'text> Scanner<'text> {
+    pub(crate) fn new(text: &'text str, escaped: bool) -> Self {
+        Self {
+            text,
+            pos: 0,
+            escaped,
+        }
+    }
+
+    pub(crate) fn text(&self) -> &'text str {
+        self.text
+    }
+
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Moves the scanner back (or forward) to an earlier token boundary
+    pub(crate) fn reset(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    /// Skips over any trivia returning the next significant token
+    pub(crate) fn next_token(&mut self) -> Option<Lexeme> {
+        self.find(|token| !token.kind.is_trivia())
+    }
+
+    /// Consumes a `#base` or `#include` directive if one comes next
+    ///
+    /// Nothing is consumed otherwise. That includes a keyword that isn't followed by a path since
+    /// then it has to be the top-level key instead
+    pub(crate) fn directive(&mut self) -> Option<Directive> {
+        let start = self.pos;
+        let directive = self.directive_inner();
+        if directive.is_none() {
+            self.pos = start;
+        }
+        directive
+    }
+
+    fn directive_inner(&mut self) -> Option<Directive> {
+        let token = self
+            .next_token()
+            .filter(|token| token.kind == TokenKind::UnquotedStr)?;
+        let s = &self.text[token.range.clone()];
+        let keyword = DIRECTIVES
+            .into_iter()
+            .find(|keyword| s.starts_with(keyword))?;
+        let is_base = keyword == "#base";
+        let keyword = token.range.start..token.range.start + keyword.len();
+
+        // A comment right after the keyword isn't part of a glued path e.g. `#base// note`
+        let glued = keyword.end < token.range.end && !s[keyword.len()..].starts_with("//");
+        let path = if glued {
+            Lexeme {
+                kind: TokenKind::UnquotedStr,
+                range: keyword.end..token.range.end,
+            }
+        } else {
+            self.pos = keyword.end;
+            // Directive paths never contain escapes
+            let escaped = std::mem::replace(&mut self.escaped, false);
+            let path = self.next_token();
+            self.escaped = escaped;
+            path.filter(|path| matches!(path.kind, TokenKind::QuotedStr | TokenKind::UnquotedStr))?
+        };
+
+        Some(Directive {
+            is_base,
+            keyword,
+            path,
+        })
+    }
+
+    /// Consumes a conditional tag e.g. `[$WIN32]` if one comes next
+    ///
+    /// Conditionals can contain characters that end unquoted strings, so the tag is parsed
+    /// directly from the text instead of from tokens
+    pub(crate) fn conditional(&mut self) -> Option<(Conditional<'text>, Range<usize>)> {
+        let start = self.pos;
+        let tagged = self.next_token().and_then(|token| {
+            let tag_start = token.range.start;
+            let (conditional, len) = Conditional::parse_prefix(&self.text[tag_start..])?;
+            Some((conditional, tag_start..tag_start + len))
+        });
+        self.pos = tagged.as_ref().map_or(start, |(_, range)| range.end);
+        tagged
+    }
+
+    /// Consumes what follows the top-level pair returning the first token that isn't allowed
+    ///
+    /// Only trivia and an optional trailing null byte can come after the top-level pair
+    pub(crate) fn end(&mut self) -> Option<Lexeme> {
+        let token = self.next_token()?;
+        if self.text[token.range.clone()].starts_with('\0') {
+            self.pos = token.range.start + 1;
+            self.next_token()
+        } else {
+            Some(token)
+        }
+    }
+
+    fn bytes(&self) -> &'text [u8] {
+        self.text.as_bytes()
+    }
+
+    fn eat_while(&mut self, f: impl Fn(u8) -> bool) {
+        while self.bytes().get(self.pos).map_or(false, |&b| f(b)) {
+            self.pos += 1;
+        }
+    }
+
+    fn quoted(&mut self) -> TokenKind {
+        // Skip the opening quote
+        self.pos += 1;
+        while let Some(&b) = self.bytes().get(self.pos) {
+            match b {
+                b'"' => {
+                    self.pos += 1;
+                    return TokenKind::QuotedStr;
+                }
+                b'\\' if self.escaped => self.pos = (self.pos + 2).min(self.text.len()),
+                _ => self.pos += 1,
+            }
+        }
+
+        TokenKind::UnterminatedStr
+    }
+}
+
+impl Iterator for Scanner<'_> {
+    type Item = Lexeme;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.pos;
+        let kind = match *self.bytes().get(start)? {
+            b if is_whitespace(b) => {
+                self.eat_while(is_whitespace);
+                TokenKind::Whitespace
+            }
+            b'/' if self.bytes().get(start + 1) == Some(&b'/') => {
+                self.eat_while(|b| b != b'\n');
+                TokenKind::Comment
+            }
+            b'"' => self.quoted(),
+            b'{' => {
+                self.pos += 1;
+                TokenKind::ObjOpen
+            }
+            b'}' => {
+                self.pos += 1;
+                TokenKind::ObjClose
+            }
+            _ => {
+                self.eat_while(is_unquoted);
+                TokenKind::UnquotedStr
+            }
+        };
+
+        Some(Lexeme {
+            kind,
+            range: start..self.pos,
+        })
+    }
+}
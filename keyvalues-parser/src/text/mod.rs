@@ -1,6 +1,9 @@
+pub mod lex;
 pub mod parse;
 #[path = "render.rs"]
 mod render_;
 
+pub(crate) use render_::{find_invalid_raw_char, is_unquoted_safe, write_str, RenderType};
+
 #[deprecated(since = "0.2.3", note = "Empty and unintentionally exposed :)")]
 pub mod render {}
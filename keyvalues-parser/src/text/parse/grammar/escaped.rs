@@ -6,12 +6,6 @@ type ParseResult<'a> = pest::ParseResult<BoxedState<'a>>;
 
 common_parsing!(pest_parse, Rule, true);
 
-/// Attempts to parse VDF text to a [`Vdf`]
-#[deprecated(since = "0.2.3", note = "Moved to `keyvalues_parser::parse()`")]
-pub fn parse(s: &str) -> Result<PartialVdf<'_>> {
-    parse_(s)
-}
-
 #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Rule {
@@ -20,7 +14,9 @@ pub enum Rule {
     WHITESPACE,
     COMMENT,
     vdf,
+    directive,
     base_macro,
+    include_macro,
     quoted_raw_string,
     quoted_raw_inner,
     pairs,
@@ -28,6 +24,7 @@ pub enum Rule {
     key,
     value,
     obj,
+    conditional,
     quoted_string,
     quoted_inner,
     char,
@@ -38,7 +35,7 @@ pub enum Rule {
 mod rules {
     #![allow(non_snake_case)]
 
-    use super::{any, skip, soi, whitespace, BoxedState, ParseResult, Rule};
+    use super::{any, conditional_body, skip, soi, whitespace, BoxedState, ParseResult, Rule};
 
     use pest::Atomicity;
 
@@ -50,8 +47,8 @@ mod rules {
                 .and_then(|s| {
                     s.sequence(|s| {
                         s.optional(|s| {
-                            base_macro(s).and_then(|s| {
-                                s.repeat(|s| s.sequence(|s| skip(s).and_then(base_macro)))
+                            directive(s).and_then(|s| {
+                                s.repeat(|s| s.sequence(|s| skip(s).and_then(directive)))
                             })
                         })
                     })
@@ -64,6 +61,10 @@ mod rules {
                 .and_then(EOI)
         })
     }
+    #[inline]
+    pub fn directive(s: BoxedState<'_>) -> ParseResult<'_> {
+        base_macro(s).or_else(include_macro)
+    }
     pub fn base_macro(s: BoxedState<'_>) -> ParseResult<'_> {
         s.rule(Rule::base_macro, |s| {
             s.sequence(|s| {
@@ -73,6 +74,15 @@ mod rules {
             })
         })
     }
+    pub fn include_macro(s: BoxedState<'_>) -> ParseResult<'_> {
+        s.rule(Rule::include_macro, |s| {
+            s.sequence(|s| {
+                s.match_string("#include")
+                    .and_then(skip)
+                    .and_then(|s| quoted_raw_string(s).or_else(unquoted_string))
+            })
+        })
+    }
     #[inline]
     pub fn quoted_raw_string(s: BoxedState<'_>) -> ParseResult<'_> {
         s.atomic(Atomicity::CompoundAtomic, |s| {
@@ -102,7 +112,18 @@ mod rules {
     #[inline]
     pub fn pair(s: BoxedState<'_>) -> ParseResult<'_> {
         s.rule(Rule::pair, |s| {
-            s.sequence(|s| key(s).and_then(skip).and_then(value))
+            s.sequence(|s| {
+                key(s)
+                    .and_then(skip)
+                    .and_then(value)
+                    .and_then(|s| s.optional(|s| s.sequence(|s| skip(s).and_then(conditional))))
+            })
+        })
+    }
+    #[inline]
+    pub fn conditional(s: BoxedState<'_>) -> ParseResult<'_> {
+        s.rule(Rule::conditional, |s| {
+            s.atomic(Atomicity::Atomic, conditional_body)
         })
     }
     #[inline]
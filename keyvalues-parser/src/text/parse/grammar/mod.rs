@@ -0,0 +1,488 @@
+//! The original `pest`-based parsers
+//!
+//! These are kept around behind the `pest` feature to compare against the hand-written parser
+
+use std::{borrow::Cow, ops::Range};
+
+use pest::{error::InputLocation, iterators::Pair as PestPair, Atomicity, RuleType};
+
+use super::{check_input_len, found, invalid_escapes, unescape, Parsed, Tree};
+use crate::{
+    conditional::Conditional,
+    error::{Expected, ParseError, ParseErrorKind, Result},
+    span::{LineIndex, ObjSpans, PairSpans, Span, ValueSpans},
+    text::lex::{Scanner, TokenKind},
+    Parser, Value,
+};
+
+mod escaped;
+mod raw;
+
+pub use escaped::PestError as EscapedPestError;
+pub use raw::PestError as RawPestError;
+
+type BoxedState<'a, R> = Box<pest::ParserState<'a, R>>;
+type ParseResult<'a, R> = pest::ParseResult<BoxedState<'a, R>>;
+
+#[inline]
+fn whitespace<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
+    s.atomic(Atomicity::Atomic, |s| {
+        s.match_string(" ")
+            .or_else(|s| s.match_string("\t"))
+            .or_else(|s| s.match_string("\r"))
+            .or_else(|s| s.match_string("\n"))
+    })
+}
+
+#[inline]
+fn any<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
+    s.skip(1)
+}
+
+fn soi<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
+    s.start_of_input()
+}
+
+#[inline]
+fn comment<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
+    s.atomic(Atomicity::Atomic, |s| {
+        s.sequence(|s| {
+            s.match_string("//").and_then(|s| {
+                s.repeat(|s| {
+                    s.sequence(|s| s.lookahead(false, |s| s.match_string("\n")).and_then(any))
+                })
+            })
+        })
+    })
+}
+
+/// Matches everything within a conditional tag e.g. `[$WIN32 && !$X360]`
+///
+/// Keep in sync with [`crate::conditional::Conditional::parse()`]
+fn conditional_body<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
+    fn spaces<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
+        s.repeat(|s| s.match_string(" ").or_else(|s| s.match_string("\t")))
+    }
+
+    fn symbol<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
+        // `match_range()` is inclusive on both ends
+        #[allow(clippy::almost_complete_range)]
+        fn symbol_char<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
+            s.match_range('a'..'z')
+                .or_else(|s| s.match_range('A'..'Z'))
+                .or_else(|s| s.match_range('0'..'9'))
+                .or_else(|s| s.match_string("_"))
+        }
+
+        s.sequence(|s| {
+            s.optional(|s| s.match_string("!"))
+                .and_then(|s| s.match_string("$"))
+                .and_then(symbol_char)
+                .and_then(|s| s.repeat(symbol_char))
+        })
+    }
+
+    s.sequence(|s| {
+        s.match_string("[")
+            .and_then(spaces)
+            .and_then(symbol)
+            .and_then(|s| {
+                s.repeat(|s| {
+                    s.sequence(|s| {
+                        spaces(s)
+                            .and_then(|s| s.match_string("&&").or_else(|s| s.match_string("||")))
+                            .and_then(spaces)
+                            .and_then(symbol)
+                    })
+                })
+            })
+            .and_then(spaces)
+            .and_then(|s| s.match_string("]"))
+    })
+}
+
+#[inline]
+fn skip<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
+    if s.atomicity() == Atomicity::NonAtomic {
+        s.sequence(|s| {
+            s.repeat(whitespace).and_then(|s| {
+                s.repeat(|s| s.sequence(|s| comment(s).and_then(|s| s.repeat(whitespace))))
+            })
+        })
+    } else {
+        Ok(s)
+    }
+}
+
+// unfortunate hack to re-use most of the code that consumes the pest parser produced by our two
+// separate grammars :/
+macro_rules! common_parsing {
+    ($parse_fn:ident, $rule:ty, $parse_escaped:expr) => {
+        pub(crate) fn parse_<'a, V: Tree<'a>>(
+            s: &'a str,
+            parser: &Parser,
+        ) -> Result<Parsed<'a, V>, ParseError> {
+            let mut full_grammar = $parse_fn(s).map_err(|err| {
+                let expected = match &err.variant {
+                    pest::error::ErrorVariant::ParsingError { positives, .. } => positives
+                        .iter()
+                        .filter_map(|&rule| expected(rule))
+                        .collect(),
+                    pest::error::ErrorVariant::CustomError { .. } => Vec::new(),
+                };
+                parse_error(s, parser, err.location, expected)
+            })?;
+            let lines = parser.spans.then(|| LineIndex::new(s));
+            let lines = lines.as_ref();
+
+            // There can be multiple base and include macros before the initial pair
+            let mut bases = Vec::new();
+            let mut includes = Vec::new();
+            loop {
+                let pair = full_grammar.next().unwrap();
+                let paths = match pair.as_rule() {
+                    <$rule>::base_macro => &mut bases,
+                    <$rule>::include_macro => &mut includes,
+                    _ => {
+                        check_limits(s, parser, &pair)?;
+                        let (key, value, conditional, spans) = parse_pair(pair, lines);
+                        return Ok(Parsed {
+                            key,
+                            value,
+                            conditional,
+                            bases,
+                            includes,
+                            spans,
+                        });
+                    }
+                };
+
+                let path_string = pair.into_inner().next().unwrap();
+                let path = match path_string.as_rule() {
+                    <$rule>::quoted_raw_string => path_string.into_inner().next().unwrap(),
+                    <$rule>::unquoted_string => path_string,
+                    _ => unreachable!("Prevented by grammar"),
+                }
+                .as_str();
+                paths.push(Cow::from(path));
+            }
+        }
+
+        /// Describes a rule that `pest` tried to match in terms of VDF
+        fn expected(rule: $rule) -> Option<Expected> {
+            match rule {
+                <$rule>::base_macro | <$rule>::include_macro | <$rule>::pair => Some(Expected::Key),
+                <$rule>::obj | <$rule>::quoted_string | <$rule>::unquoted_string => {
+                    Some(Expected::Value)
+                }
+                <$rule>::EOI => Some(Expected::EndOfInput),
+                // Directive paths and conditionals can always be left out
+                _ => None,
+            }
+        }
+
+        /// Checks [`Parser::max_pairs()`] and [`Parser::max_str_len()`] against the tree in the
+        /// same order that the hand-written parser does
+        fn check_limits(
+            text: &str,
+            parser: &Parser,
+            root: &PestPair<'_, $rule>,
+        ) -> Result<(), ParseError> {
+            let mut num_pairs = 0;
+            let nodes = std::iter::once(root.clone()).chain(root.clone().into_inner().flatten());
+            for node in nodes {
+                let span = node.as_span();
+                let (kind, span) = match node.as_rule() {
+                    <$rule>::pair => {
+                        num_pairs += 1;
+                        match parser.max_pairs.filter(|&limit| num_pairs > limit) {
+                            // The error points at the key
+                            Some(limit) => (
+                                ParseErrorKind::PairLimitExceeded { limit },
+                                node.into_inner().next().unwrap().as_span(),
+                            ),
+                            None => continue,
+                        }
+                    }
+                    <$rule>::quoted_string | <$rule>::unquoted_string => {
+                        let len = match node.as_rule() {
+                            <$rule>::quoted_string => span.as_str().len() - 2,
+                            _ => span.as_str().len(),
+                        };
+                        match parser.max_str_len.filter(|&limit| len > limit) {
+                            Some(limit) => (ParseErrorKind::StrLenLimitExceeded { limit }, span),
+                            None => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                return Err(ParseError::new(text, kind, span.start()..span.end()));
+            }
+
+            Ok(())
+        }
+
+        fn parse_pair<'a, V: Tree<'a>>(
+            grammar_pair: PestPair<'a, $rule>,
+            lines: Option<&LineIndex<'_>>,
+        ) -> (
+            Cow<'a, str>,
+            V,
+            Option<Conditional<'a>>,
+            Option<PairSpans<'a>>,
+        ) {
+            // Structure: pair
+            //            \ key          <- Desired
+            //            \ value        <- Desired
+            //            \ conditional? <- Desired
+            if let <$rule>::pair = grammar_pair.as_rule() {
+                // Parse out the key and value
+                let mut grammar_pair_innards = grammar_pair.into_inner();
+                let grammar_string = grammar_pair_innards.next().unwrap();
+                let key_span = lines.map(|lines| span_of(lines, &grammar_string));
+                let key = parse_string(grammar_string);
+
+                let grammar_value = grammar_pair_innards.next().unwrap();
+                let (value, value_spans) = parse_value(grammar_value, lines);
+
+                let grammar_conditional = grammar_pair_innards.next();
+                let conditional_span = grammar_conditional
+                    .as_ref()
+                    .zip(lines)
+                    .map(|(cond, lines)| span_of(lines, cond));
+                let conditional = grammar_conditional
+                    .map(|cond| Conditional::parse(cond.as_str()).expect("Prevented by grammar"));
+
+                let spans = key_span.zip(value_spans).map(|(key, value)| PairSpans {
+                    key,
+                    value,
+                    conditional: conditional_span,
+                });
+                (key, value, conditional, spans)
+            } else {
+                unreachable!("Prevented by grammar");
+            }
+        }
+
+        fn parse_string(grammar_string: PestPair<'_, $rule>) -> Cow<'_, str> {
+            match grammar_string.as_rule() {
+                // Structure: quoted_string
+                //            \ "
+                //            \ quoted_inner <- Desired
+                //            \ "
+                <$rule>::quoted_string => {
+                    let quoted_inner = grammar_string.into_inner().next().unwrap();
+                    if $parse_escaped {
+                        parse_escaped_string(quoted_inner)
+                    } else {
+                        Cow::from(quoted_inner.as_str())
+                    }
+                }
+                // Structure: unquoted_string <- Desired
+                <$rule>::unquoted_string => {
+                    let s = grammar_string.as_str();
+                    Cow::from(s)
+                }
+                _ => unreachable!("Prevented by grammar"),
+            }
+        }
+
+        // Note: there can be a slight performance win here by having the grammar skip capturing
+        // quoted_inner and instead just slice off the starting and ending '"', but I'm going to pass since
+        // it seems like a hack for a ~4% improvement
+        fn parse_escaped_string(inner: PestPair<'_, $rule>) -> Cow<'_, str> {
+            unescape(inner.as_str())
+        }
+
+        fn parse_value<'a, V: Tree<'a>>(
+            grammar_value: PestPair<'a, $rule>,
+            lines: Option<&LineIndex<'_>>,
+        ) -> (V, Option<ValueSpans<'a>>) {
+            // Structure: value is ( obj | quoted_string | unquoted_string )
+            match grammar_value.as_rule() {
+                // Structure: ( quoted_string | unquoted_string )
+                <$rule>::quoted_string | <$rule>::unquoted_string => {
+                    let spans = lines.map(|lines| ValueSpans::Str(span_of(lines, &grammar_value)));
+                    (V::from_str(parse_string(grammar_value)), spans)
+                }
+                // Structure: obj
+                //            \ pair* <- Desired
+                <$rule>::obj => {
+                    let mut obj_spans = lines.map(|lines| obj_spans_of(lines, &grammar_value));
+                    let mut obj = V::Obj::default();
+                    for grammar_pair in grammar_value.into_inner() {
+                        let (key, value, conditional, pair_spans) = parse_pair(grammar_pair, lines);
+                        if let Some((obj_spans, pair_spans)) = obj_spans.as_mut().zip(pair_spans) {
+                            obj_spans.entry(key.clone()).or_default().push(pair_spans);
+                        }
+                        V::push(&mut obj, key, value, conditional);
+                    }
+
+                    (V::from_obj(obj), obj_spans.map(ValueSpans::Obj))
+                }
+                _ => unreachable!("Prevented by grammar"),
+            }
+        }
+
+        impl<'a> From<PestPair<'a, $rule>> for Value<'a> {
+            fn from(grammar_value: PestPair<'a, $rule>) -> Self {
+                parse_value::<Value<'a>>(grammar_value, None).0
+            }
+        }
+    };
+}
+
+// expose ^^ macro to the rest of the crate
+pub(crate) use common_parsing;
+
+fn span_of<R: RuleType>(lines: &LineIndex<'_>, pair: &PestPair<'_, R>) -> Span {
+    let span = pair.as_span();
+    lines.span(span.start()..span.end())
+}
+
+fn obj_spans_of<'a, R: RuleType>(lines: &LineIndex<'_>, obj: &PestPair<'_, R>) -> ObjSpans<'a> {
+    // Structure: obj is `{` pair* `}` so the braces are the first and last bytes
+    let span = obj.as_span();
+    ObjSpans {
+        open: lines.span(span.start()..span.start() + 1),
+        close: lines.span(span.end() - 1..span.end()),
+        pairs: Default::default(),
+    }
+}
+
+pub(crate) fn parse_tree<'a, V: Tree<'a>>(
+    parser: &Parser,
+    s: &'a str,
+) -> Result<Parsed<'a, V>, ParseError> {
+    check_input_len(parser, s)?;
+    // `pest` parses recursively, so text that's nested too deeply has to be turned away before
+    // it gets the chance to overflow the stack
+    if let Some(limit) = parser.max_depth {
+        if let Some(brace) = too_deep(s, parser, limit) {
+            return Err(ParseError::new(
+                s,
+                ParseErrorKind::DepthLimitExceeded { limit },
+                brace,
+            ));
+        }
+    }
+
+    if parser.literal_special_chars {
+        raw::parse_(s, parser)
+    } else {
+        escaped::parse_(s, parser)
+    }
+}
+
+/// Returns the `{` and `}` tokens in `text` up until `end`
+fn braces<'a>(
+    text: &'a str,
+    parser: &Parser,
+    end: usize,
+) -> impl Iterator<Item = (TokenKind, Range<usize>)> + 'a {
+    let mut scanner = Scanner::new(text, !parser.literal_special_chars);
+    while scanner.directive().is_some() {}
+    std::iter::from_fn(move || scanner.next_token())
+        .take_while(move |token| token.range.start < end)
+        .filter(|token| matches!(token.kind, TokenKind::ObjOpen | TokenKind::ObjClose))
+        .map(|token| (token.kind, token.range))
+}
+
+/// Returns the first `{` that's nested deeper than `limit`
+fn too_deep(text: &str, parser: &Parser, limit: usize) -> Option<Range<usize>> {
+    let mut depth = 0_usize;
+    braces(text, parser, text.len()).find_map(|(kind, range)| {
+        if kind == TokenKind::ObjOpen {
+            depth += 1;
+            (depth > limit).then_some(range)
+        } else {
+            depth = depth.saturating_sub(1);
+            None
+        }
+    })
+}
+
+/// Builds the error for text that `pest` failed to parse at `location` while looking for one of
+/// the `expected` items
+///
+/// `pest` only knows which rules it tried, so the token that it stopped at is used to fill in
+/// the details
+fn parse_error(
+    text: &str,
+    parser: &Parser,
+    location: InputLocation,
+    mut expected: Vec<Expected>,
+) -> ParseError {
+    let pos = match location {
+        InputLocation::Pos(pos) => pos,
+        InputLocation::Span((start, _)) => start,
+    };
+    let mut seen = Vec::new();
+    expected.retain(|&item| {
+        let is_new = !seen.contains(&item);
+        seen.push(item);
+        is_new
+    });
+
+    let escaped = !parser.literal_special_chars;
+    let mut scanner = Scanner::new(text, escaped);
+    scanner.reset(pos);
+    let token = scanner.next_token();
+
+    // A string that was expected, but couldn't be matched
+    let expects_str = expected
+        .iter()
+        .any(|item| matches!(item, Expected::Key | Expected::Value));
+    if let Some(token) = token.as_ref().filter(|_| expects_str) {
+        match token.kind {
+            TokenKind::UnterminatedStr => {
+                let start = token.range.start;
+                let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+                return ParseError::new(text, ParseErrorKind::UnclosedString, start..end);
+            }
+            TokenKind::QuotedStr if escaped => {
+                let contents = token.contents();
+                if let Some(invalid) = invalid_escapes(&text[contents.clone()]).next() {
+                    let range = contents.start + invalid.start..contents.start + invalid.end;
+                    let sequence = text[range.clone()].to_owned();
+                    return ParseError::new(
+                        text,
+                        ParseErrorKind::InvalidEscape { sequence },
+                        range,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut opens = Vec::new();
+    for (kind, range) in braces(text, parser, pos) {
+        if kind == TokenKind::ObjOpen {
+            opens.push(range.start);
+        } else {
+            opens.pop();
+        }
+    }
+
+    let end = text.len();
+    match (&token, opens.last()) {
+        (None, Some(&open)) if expected == [Expected::Key] => {
+            let lines = LineIndex::new(text);
+            let open = lines.location(open);
+            let kind = ParseErrorKind::UnclosedObject { open };
+            ParseError::with_lines(&lines, kind, end..end)
+        }
+        _ => {
+            if !opens.is_empty() && expected.contains(&Expected::Key) {
+                expected.push(Expected::ObjClose);
+            }
+            let range = token.as_ref().map_or(end..end, |token| token.range.clone());
+            let kind = ParseErrorKind::Unexpected {
+                found: found(token.as_ref()),
+                expected,
+            };
+            ParseError::new(text, kind, range)
+        }
+    }
+}
@@ -6,15 +6,6 @@ type ParseResult<'a> = pest::ParseResult<BoxedState<'a>>;
 
 common_parsing!(pest_parse, Rule, false);
 
-/// Attempts to parse VDF text to a [`Vdf`]
-#[deprecated(
-    since = "0.2.3",
-    note = "Please use `Parser::new().literal_special_chars(true).parse()` instead"
-)]
-pub fn parse(s: &str) -> Result<PartialVdf<'_>> {
-    parse_(s)
-}
-
 #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Rule {
@@ -23,13 +14,16 @@ pub enum Rule {
     WHITESPACE,
     COMMENT,
     vdf,
+    directive,
     base_macro,
+    include_macro,
     quoted_raw_string,
     pairs,
     pair,
     key,
     value,
     obj,
+    conditional,
     quoted_string,
     quoted_inner,
     unquoted_string,
@@ -39,7 +33,7 @@ pub enum Rule {
 mod rules {
     #![allow(non_snake_case)]
 
-    use super::{any, skip, soi, whitespace, BoxedState, ParseResult, Rule};
+    use super::{any, conditional_body, skip, soi, whitespace, BoxedState, ParseResult, Rule};
 
     use pest::Atomicity;
 
@@ -51,8 +45,8 @@ mod rules {
                 .and_then(|s| {
                     s.sequence(|s| {
                         s.optional(|s| {
-                            base_macro(s).and_then(|s| {
-                                s.repeat(|s| s.sequence(|s| skip(s).and_then(base_macro)))
+                            directive(s).and_then(|s| {
+                                s.repeat(|s| s.sequence(|s| skip(s).and_then(directive)))
                             })
                         })
                     })
@@ -65,6 +59,10 @@ mod rules {
                 .and_then(EOI)
         })
     }
+    #[inline]
+    pub fn directive(s: BoxedState<'_>) -> ParseResult<'_> {
+        base_macro(s).or_else(include_macro)
+    }
     pub fn base_macro(s: BoxedState<'_>) -> ParseResult<'_> {
         s.rule(Rule::base_macro, |s| {
             s.sequence(|s| {
@@ -74,6 +72,15 @@ mod rules {
             })
         })
     }
+    pub fn include_macro(s: BoxedState<'_>) -> ParseResult<'_> {
+        s.rule(Rule::include_macro, |s| {
+            s.sequence(|s| {
+                s.match_string("#include")
+                    .and_then(skip)
+                    .and_then(|s| quoted_raw_string(s).or_else(unquoted_string))
+            })
+        })
+    }
     #[inline]
     pub fn quoted_raw_string(s: BoxedState<'_>) -> ParseResult<'_> {
         s.atomic(Atomicity::CompoundAtomic, |s| {
@@ -97,7 +104,18 @@ mod rules {
     #[inline]
     pub fn pair(s: BoxedState<'_>) -> ParseResult<'_> {
         s.rule(Rule::pair, |s| {
-            s.sequence(|s| key(s).and_then(skip).and_then(value))
+            s.sequence(|s| {
+                key(s)
+                    .and_then(skip)
+                    .and_then(value)
+                    .and_then(|s| s.optional(|s| s.sequence(|s| skip(s).and_then(conditional))))
+            })
+        })
+    }
+    #[inline]
+    pub fn conditional(s: BoxedState<'_>) -> ParseResult<'_> {
+        s.rule(Rule::conditional, |s| {
+            s.atomic(Atomicity::Atomic, conditional_body)
         })
     }
     #[inline]
@@ -0,0 +1,665 @@
+//! A hand-written parser built on top of the [`Scanner`]
+//!
+//! By default parsing stops at the first error, but it can also record the error and
+//! resynchronize instead, so that every error gets reported. Braces are used to stay in sync with
+//! the nesting of objects and unterminated strings are cut off at the end of their line
+//!
+//! Objects are parsed recursively, but [`Parser::max_depth()`] is enforced before descending into
+//! an object, so limited parsers can't be driven to overflow the stack
+
+use std::{borrow::Cow, collections::BTreeMap, ops::Range};
+
+use super::{invalid_escapes, unescape, Parsed, ParsedMulti, Tree};
+use crate::{
+    conditional::Conditional,
+    error::{Expected, Found, ParseError, ParseErrorKind},
+    span::{LineIndex, ObjSpans, PairSpans, Span, ValueSpans},
+    text::lex::{Lexeme, Scanner, TokenKind},
+    DuplicateKeys, Key, Parser,
+};
+
+/// The longest token that Valve's tokenizer keeps, which is `KEYVALUES_TOKEN_SIZE` minus the null
+/// terminator
+pub(crate) const VALVE_MAX_TOKEN_LEN: usize = 4095;
+
+pub(crate) type PairParts<'a, V> = (Key<'a>, V, Option<Conditional<'a>>, Option<PairSpans<'a>>);
+
+/// Parses `text` returning the first error if there is one
+pub(crate) fn parse<'a, V: Tree<'a>>(
+    parser: &Parser,
+    text: &'a str,
+) -> Result<Parsed<'a, V>, ParseError> {
+    parse_checked(parser, text, false).map(|(parsed, _)| parsed)
+}
+
+/// The same as [`parse()`], but also returns any warnings from parsing successfully
+pub(crate) fn parse_warned<'a, V: Tree<'a>>(
+    parser: &Parser,
+    text: &'a str,
+) -> Result<(Parsed<'a, V>, Vec<ParseError>), ParseError> {
+    parse_checked(parser, text, V::DROPS_CONDITIONALS)
+}
+
+fn parse_checked<'a, V: Tree<'a>>(
+    parser: &Parser,
+    text: &'a str,
+    warn_dropped_conditionals: bool,
+) -> Result<(Parsed<'a, V>, Vec<ParseError>), ParseError> {
+    check_input_len(parser, text)?;
+    let mut hand = HandParser::new(text, parser, false);
+    hand.warn_dropped_conditionals = warn_dropped_conditionals;
+    let parsed = hand.document();
+    match hand.errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok((parsed, hand.warnings)),
+    }
+}
+
+/// Parses as much of `text` as possible returning every error encountered along the way
+///
+/// The returned tree matches what [`parse()`] produces when there are no errors
+pub(crate) fn parse_recovering<'a, V: Tree<'a>>(
+    parser: &Parser,
+    text: &'a str,
+) -> (Parsed<'a, V>, Vec<ParseError>) {
+    parse_inner(parser, text, true)
+}
+
+/// Parses `text` as a document that can have any number of top-level pairs
+pub(crate) fn parse_multi<'a, V: Tree<'a>>(
+    parser: &Parser,
+    text: &'a str,
+) -> Result<ParsedMulti<'a, V>, ParseError> {
+    check_input_len(parser, text)?;
+    let mut hand = HandParser::new(text, parser, false);
+    let parsed = hand.multi_document();
+    match hand.errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(parsed),
+    }
+}
+
+fn parse_inner<'a, V: Tree<'a>>(
+    parser: &Parser,
+    text: &'a str,
+    recover: bool,
+) -> (Parsed<'a, V>, Vec<ParseError>) {
+    if let Err(err) = check_input_len(parser, text) {
+        return (empty(), vec![err]);
+    }
+
+    let mut hand = HandParser::new(text, parser, recover);
+    let parsed = hand.document();
+    (parsed, hand.errors)
+}
+
+pub(crate) fn check_input_len(parser: &Parser, text: &str) -> Result<(), ParseError> {
+    match parser.max_input_len.filter(|&limit| text.len() > limit) {
+        Some(limit) => {
+            // Avoid indexing all of the text since it can be arbitrarily large
+            let mut end = limit;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            let kind = ParseErrorKind::InputLenLimitExceeded { limit };
+            Err(ParseError::new(&text[..end], kind, end..end))
+        }
+        None => Ok(()),
+    }
+}
+
+/// What gets returned when there's no top-level pair at all
+fn empty<'a, V: Tree<'a>>() -> Parsed<'a, V> {
+    Parsed {
+        bases: Vec::new(),
+        includes: Vec::new(),
+        key: Cow::from(""),
+        value: V::from_obj(Default::default()),
+        conditional: None,
+        spans: None,
+    }
+}
+
+/// Cuts `s` down to at most `len` bytes without splitting a `char`
+pub(crate) fn truncate(s: Cow<'_, str>, len: usize) -> Cow<'_, str> {
+    let mut end = len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    match s {
+        Cow::Borrowed(s) => Cow::Borrowed(&s[..end]),
+        Cow::Owned(mut s) => {
+            s.truncate(end);
+            Cow::Owned(s)
+        }
+    }
+}
+
+/// Returns the token following the scanner's position if it's on the same line
+pub(crate) fn shares_line(scanner: &Scanner<'_>) -> Option<Lexeme> {
+    let start = scanner.pos();
+    let token = scanner.clone().next_token()?;
+    (!scanner.text()[start..token.range.start].contains('\n')).then_some(token)
+}
+
+/// Returns the ambiguous forms of a string starting at `start` with the given `contents` that
+/// [`Parser::strict()`] rejects
+pub(crate) fn strict_errors(
+    text: &str,
+    kind: TokenKind,
+    start: usize,
+    contents: Range<usize>,
+) -> impl Iterator<Item = (ParseErrorKind, Range<usize>)> {
+    let separated = text[..start].bytes().next_back().map_or(true, |b| {
+        matches!(b, b' ' | b'\t' | b'\r' | b'\n' | b'{' | b'}')
+    });
+    let missing_separator = (!separated).then(|| {
+        let len = text[start..].chars().next().map_or(1, char::len_utf8);
+        (ParseErrorKind::MissingSeparator, start..start + len)
+    });
+
+    let s = &text[contents.clone()];
+    let comment = s
+        .find("//")
+        .filter(|_| kind == TokenKind::UnquotedStr)
+        .map(|i| {
+            let i = contents.start + i;
+            (ParseErrorKind::CommentInUnquotedStr, i..i + 2)
+        });
+
+    let non_printable = s
+        .char_indices()
+        .find(|&(_, c)| c.is_control() && !matches!(c, '\t' | '\r' | '\n'))
+        .map(|(i, invalid_char)| {
+            let i = contents.start + i;
+            let kind = ParseErrorKind::NonPrintableChar { invalid_char };
+            (kind, i..i + invalid_char.len_utf8())
+        });
+
+    [missing_separator, comment, non_printable]
+        .into_iter()
+        .flatten()
+}
+
+pub(crate) fn found(token: Option<&Lexeme>) -> Found {
+    match token.map(|token| token.kind) {
+        None => Found::EndOfInput,
+        Some(TokenKind::ObjOpen) => Found::ObjOpen,
+        Some(TokenKind::ObjClose) => Found::ObjClose,
+        Some(_) => Found::Str,
+    }
+}
+
+struct HandParser<'a> {
+    scanner: Scanner<'a>,
+    /// Only built when it's needed for spans or errors
+    lines: Option<LineIndex<'a>>,
+    escaped: bool,
+    /// Whether unknown escape sequences are warnings instead of errors
+    lenient_escapes: bool,
+    /// Whether to emulate the quirks of Valve's tokenizer
+    valve_compat: bool,
+    /// Whether to reject ambiguous syntax
+    strict: bool,
+    duplicate_keys: DuplicateKeys,
+    fold_keys: bool,
+    spans: bool,
+    /// Whether to keep going after an error
+    recover: bool,
+    /// Whether conditionals get warned about since the tree being built can't store them
+    warn_dropped_conditionals: bool,
+    max_depth: Option<usize>,
+    max_str_len: Option<usize>,
+    max_pairs: Option<usize>,
+    /// How many objects deep we currently are
+    depth: usize,
+    num_pairs: usize,
+    /// Set after hitting an error that parsing can't continue past
+    halted: bool,
+    errors: Vec<ParseError>,
+    warnings: Vec<ParseError>,
+}
+
+impl<'a> HandParser<'a> {
+    fn new(text: &'a str, parser: &Parser, recover: bool) -> Self {
+        let escaped = !parser.literal_special_chars;
+        Self {
+            scanner: Scanner::new(text, escaped),
+            lines: None,
+            escaped,
+            lenient_escapes: parser.lenient_escapes,
+            valve_compat: parser.valve_compat,
+            strict: parser.strict,
+            duplicate_keys: parser.duplicate_keys,
+            fold_keys: parser.fold_keys,
+            spans: parser.spans,
+            recover,
+            warn_dropped_conditionals: false,
+            max_depth: parser.max_depth,
+            max_str_len: parser.max_str_len,
+            max_pairs: parser.max_pairs,
+            depth: 0,
+            num_pairs: 0,
+            halted: false,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn text(&self) -> &'a str {
+        self.scanner.text()
+    }
+
+    fn lines(&mut self) -> &LineIndex<'a> {
+        let text = self.text();
+        self.lines.get_or_insert_with(|| LineIndex::new(text))
+    }
+
+    fn span(&mut self, range: Range<usize>) -> Span {
+        self.lines().span(range)
+    }
+
+    fn error(&mut self, kind: ParseErrorKind, range: Range<usize>) {
+        let err = ParseError::with_lines(self.lines(), kind, range);
+        self.errors.push(err);
+        if !self.recover {
+            self.halted = true;
+        }
+    }
+
+    fn unexpected(&mut self, token: Option<&Lexeme>, expected: Vec<Expected>) {
+        let end = self.text().len();
+        let range = token.map_or(end..end, |token| token.range.clone());
+        let kind = ParseErrorKind::Unexpected {
+            found: found(token),
+            expected,
+        };
+        self.error(kind, range);
+    }
+
+    fn document<V: Tree<'a>>(&mut self) -> Parsed<'a, V> {
+        let (bases, includes) = self.directives();
+
+        let root = loop {
+            let token = self.scanner.next_token();
+            match token.as_ref().map(|token| token.kind) {
+                None => {
+                    self.unexpected(None, vec![Expected::Key]);
+                    break None;
+                }
+                Some(TokenKind::ObjClose) => {
+                    self.unexpected(token.as_ref(), vec![Expected::Key]);
+                    if self.halted {
+                        break None;
+                    }
+                }
+                Some(TokenKind::ObjOpen) => {
+                    // Missing the key, so treat the object as the value for an empty one
+                    self.unexpected(token.as_ref(), vec![Expected::Key]);
+                    let open = token.unwrap().range;
+                    let key_range = open.start..open.start;
+                    let (obj, obj_spans) = self.obj::<V>(open);
+                    let (conditional, conditional_span) = self.conditional();
+                    let spans = obj_spans.map(|obj_spans| PairSpans {
+                        key: self.span(key_range),
+                        value: ValueSpans::Obj(obj_spans),
+                        conditional: conditional_span,
+                    });
+                    break Some((Cow::from(""), V::from_obj(obj), conditional, spans));
+                }
+                Some(_) => break self.pair(token.unwrap()),
+            }
+        };
+
+        match root {
+            Some((key, value, conditional, spans)) => {
+                // Valve ignores anything following the top-level pair
+                if !self.halted && !self.valve_compat {
+                    self.end();
+                }
+                Parsed {
+                    bases,
+                    includes,
+                    key,
+                    value,
+                    conditional,
+                    spans,
+                }
+            }
+            None => Parsed {
+                bases,
+                includes,
+                ..empty()
+            },
+        }
+    }
+
+    fn multi_document<V: Tree<'a>>(&mut self) -> ParsedMulti<'a, V> {
+        let (bases, includes) = self.directives();
+
+        let mut roots = Vec::new();
+        while !self.halted {
+            let token = self.scanner.next_token();
+            match token.as_ref().map(|token| token.kind) {
+                None => break,
+                Some(TokenKind::ObjOpen | TokenKind::ObjClose) => {
+                    self.unexpected(token.as_ref(), vec![Expected::Key, Expected::EndOfInput]);
+                }
+                Some(_) => {
+                    let token = token.unwrap();
+                    if self.text()[token.range.clone()].starts_with('\0') {
+                        self.scanner.reset(token.range.start);
+                        self.end();
+                        break;
+                    }
+                    roots.extend(self.pair(token));
+                }
+            }
+        }
+
+        ParsedMulti {
+            bases,
+            includes,
+            roots,
+        }
+    }
+
+    /// Consumes as many `#base` and `#include` directives as possible returning their paths
+    fn directives(&mut self) -> (Vec<Cow<'a, str>>, Vec<Cow<'a, str>>) {
+        let mut bases = Vec::new();
+        let mut includes = Vec::new();
+        while let Some(directive) = self.scanner.directive() {
+            let path = Cow::from(&self.text()[directive.path.contents()]);
+            if directive.is_base {
+                bases.push(path);
+            } else {
+                includes.push(path);
+            }
+            self.own_line();
+        }
+
+        (bases, includes)
+    }
+
+    /// Checks that only trivia follows a directive on its line when strict
+    fn own_line(&mut self) {
+        if !self.strict {
+            return;
+        }
+
+        if let Some(token) = shares_line(&self.scanner) {
+            self.error(ParseErrorKind::DirectiveSharesLine, token.range);
+        }
+    }
+
+    /// Parses the rest of a pair after its key returning `None` if the value is missing
+    fn pair<V: Tree<'a>>(&mut self, key_token: Lexeme) -> Option<PairParts<'a, V>> {
+        self.num_pairs += 1;
+        if let Some(limit) = self.max_pairs.filter(|&limit| self.num_pairs > limit) {
+            self.error(ParseErrorKind::PairLimitExceeded { limit }, key_token.range);
+            self.halted = true;
+            return None;
+        }
+
+        let (key, key_range) = self.string(key_token);
+
+        let token = self.scanner.next_token();
+        let (value, value_spans) = match token.as_ref().map(|token| token.kind) {
+            Some(TokenKind::ObjOpen) => {
+                let (obj, obj_spans) = self.obj::<V>(token.unwrap().range);
+                (V::from_obj(obj), obj_spans.map(ValueSpans::Obj))
+            }
+            Some(TokenKind::ObjClose) | None => {
+                // Valve silently drops keys that are missing their values within objects
+                if !self.valve_compat || self.depth == 0 {
+                    self.unexpected(token.as_ref(), vec![Expected::Value]);
+                }
+                // Leave the `}` to close out the current object
+                if let Some(token) = token {
+                    self.scanner.reset(token.range.start);
+                }
+                return None;
+            }
+            Some(_) => {
+                let (s, range) = self.string(token.unwrap());
+                let spans = self.spans.then(|| ValueSpans::Str(self.span(range)));
+                (V::from_str(s), spans)
+            }
+        };
+
+        let (conditional, conditional_span) = self.conditional();
+        let spans = value_spans.map(|value| PairSpans {
+            key: self.span(key_range),
+            value,
+            conditional: conditional_span,
+        });
+        Some((key, value, conditional, spans))
+    }
+
+    /// Parses the pairs of an object up through its closing `}`
+    fn obj<V: Tree<'a>>(&mut self, open: Range<usize>) -> (V::Obj, Option<ObjSpans<'a>>) {
+        let mut obj = V::Obj::default();
+        let mut obj_spans = self.spans.then(|| ObjSpans {
+            open: self.span(open.clone()),
+            ..Default::default()
+        });
+
+        self.depth += 1;
+        let close = if let Some(limit) = self.max_depth.filter(|&limit| self.depth > limit) {
+            self.error(ParseErrorKind::DepthLimitExceeded { limit }, open.clone());
+            self.skip_obj()
+        } else {
+            self.pairs::<V>(open.start, &mut obj, &mut obj_spans)
+        };
+        self.depth -= 1;
+
+        if let Some(obj_spans) = obj_spans.as_mut() {
+            obj_spans.close = self.span(close);
+        }
+        (obj, obj_spans)
+    }
+
+    /// Parses pairs into `obj` until hitting the `}` that closes it returning the range of the `}`
+    fn pairs<V: Tree<'a>>(
+        &mut self,
+        open: usize,
+        obj: &mut V::Obj,
+        obj_spans: &mut Option<ObjSpans<'a>>,
+    ) -> Range<usize> {
+        // Where each key was first seen when duplicates aren't all kept
+        let mut seen = BTreeMap::new();
+        // The first spelling of each lowercased key when folding
+        let mut spellings = BTreeMap::new();
+        loop {
+            let end = self.text().len();
+            if self.halted {
+                break end..end;
+            }
+
+            let token = self.scanner.next_token();
+            match token.as_ref().map(|token| token.kind) {
+                None => {
+                    // Valve closes any objects left open at the end of the text
+                    if !self.valve_compat {
+                        let open = self.lines().location(open);
+                        self.error(ParseErrorKind::UnclosedObject { open }, end..end);
+                    }
+                    break end..end;
+                }
+                Some(TokenKind::ObjClose) => break token.unwrap().range,
+                Some(TokenKind::ObjOpen) => {
+                    // An object without a key gets parsed to stay in sync with the braces, but
+                    // there's nowhere to put it
+                    self.unexpected(token.as_ref(), vec![Expected::Key, Expected::ObjClose]);
+                    self.obj::<V>(token.unwrap().range);
+                }
+                Some(_) => {
+                    let key_range = token.as_ref().unwrap().range.clone();
+                    let Some((key, value, conditional, pair_spans)) = self.pair(token.unwrap())
+                    else {
+                        continue;
+                    };
+                    let key = if self.fold_keys {
+                        spellings
+                            .entry(key.to_ascii_lowercase())
+                            .or_insert(key)
+                            .clone()
+                    } else {
+                        key
+                    };
+                    if !self.dedupe::<V>(&mut seen, &key, key_range, obj, obj_spans) {
+                        continue;
+                    }
+                    if let Some((obj_spans, pair_spans)) = obj_spans.as_mut().zip(pair_spans) {
+                        obj_spans.entry(key.clone()).or_default().push(pair_spans);
+                    }
+                    V::push(obj, key, value, conditional);
+                }
+            }
+        }
+    }
+
+    /// Applies the duplicate key policy returning whether the new pair should be kept
+    fn dedupe<V: Tree<'a>>(
+        &mut self,
+        seen: &mut BTreeMap<Key<'a>, usize>,
+        key: &Key<'a>,
+        key_range: Range<usize>,
+        obj: &mut V::Obj,
+        obj_spans: &mut Option<ObjSpans<'a>>,
+    ) -> bool {
+        if self.duplicate_keys == DuplicateKeys::KeepAll {
+            return true;
+        }
+        let Some(&first) = seen.get(key) else {
+            seen.insert(key.clone(), key_range.start);
+            return true;
+        };
+
+        match self.duplicate_keys {
+            DuplicateKeys::KeepAll => true,
+            DuplicateKeys::FirstWins => false,
+            DuplicateKeys::LastWins => {
+                V::remove(obj, key);
+                if let Some(obj_spans) = obj_spans.as_mut() {
+                    obj_spans.remove(key);
+                }
+                true
+            }
+            DuplicateKeys::Error => {
+                let first = self.lines().location(first);
+                let kind = ParseErrorKind::DuplicateKey {
+                    key: key.to_string(),
+                    first,
+                };
+                self.error(kind, key_range);
+                false
+            }
+        }
+    }
+
+    /// Skips to the end of an object without recursing returning the range of its closing `}`
+    fn skip_obj(&mut self) -> Range<usize> {
+        let mut depth = 1;
+        while let Some(token) = self.scanner.next_token() {
+            match token.kind {
+                TokenKind::ObjOpen => depth += 1,
+                TokenKind::ObjClose => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return token.range;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let end = self.text().len();
+        end..end
+    }
+
+    /// Returns the contents of a quoted or unquoted string along with its range
+    fn string(&mut self, token: Lexeme) -> (Cow<'a, str>, Range<usize>) {
+        let text = self.text();
+        let (contents, range) = match token.kind {
+            TokenKind::UnquotedStr => (token.range.clone(), token.range),
+            TokenKind::QuotedStr => (token.range.start + 1..token.range.end - 1, token.range),
+            TokenKind::UnterminatedStr => {
+                // Cut the string off at the end of the line instead of letting it swallow
+                // everything that follows
+                let start = token.range.start;
+                let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+                self.error(ParseErrorKind::UnclosedString, start..end);
+                self.scanner.reset(end);
+
+                let contents_end = start + text[start..end].trim_end_matches('\r').len();
+                (start + 1..contents_end, start..end)
+            }
+            _ => unreachable!("Only called with strings"),
+        };
+
+        if self.strict {
+            for (kind, range) in strict_errors(text, token.kind, range.start, contents.clone()) {
+                self.error(kind, range);
+            }
+        }
+        if let Some(limit) = self.max_str_len.filter(|&limit| contents.len() > limit) {
+            self.error(ParseErrorKind::StrLenLimitExceeded { limit }, range.clone());
+        }
+
+        let mut s = if token.kind == TokenKind::UnquotedStr {
+            Cow::from(&text[contents])
+        } else {
+            self.quoted_inner(contents)
+        };
+        if self.valve_compat && s.len() > VALVE_MAX_TOKEN_LEN {
+            s = truncate(s, VALVE_MAX_TOKEN_LEN);
+        }
+        (s, range)
+    }
+
+    fn quoted_inner(&mut self, inner: Range<usize>) -> Cow<'a, str> {
+        let s = &self.text()[inner.clone()];
+        if !self.escaped {
+            return Cow::from(s);
+        }
+
+        for range in invalid_escapes(s) {
+            let range = inner.start + range.start..inner.start + range.end;
+            let sequence = self.text()[range.clone()].to_owned();
+            let kind = ParseErrorKind::InvalidEscape { sequence };
+            if self.lenient_escapes {
+                let warning = ParseError::with_lines(self.lines(), kind, range);
+                self.warnings.push(warning);
+            } else {
+                self.error(kind, range);
+            }
+        }
+
+        // Unknown escape sequences are kept as is
+        unescape(s)
+    }
+
+    /// Consumes a conditional tag if there is one following a value
+    fn conditional(&mut self) -> (Option<Conditional<'a>>, Option<Span>) {
+        match self.scanner.conditional() {
+            Some((conditional, range)) => {
+                if self.warn_dropped_conditionals {
+                    let kind = ParseErrorKind::DroppedConditional;
+                    let warning = ParseError::with_lines(self.lines(), kind, range.clone());
+                    self.warnings.push(warning);
+                }
+                let span = self.spans.then(|| self.span(range));
+                (Some(conditional), span)
+            }
+            None => (None, None),
+        }
+    }
+
+    /// Only trivia and an optional trailing null byte can come after the top-level pair
+    fn end(&mut self) {
+        if let Some(token) = self.scanner.end() {
+            self.unexpected(Some(&token), vec![Expected::EndOfInput]);
+        }
+    }
+}
@@ -1,222 +1,341 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, ops::Range};
 
-use crate::{error::Result, Obj, PartialVdf, Value, Vdf};
-
-use pest::{iterators::Pair as PestPair, Atomicity, RuleType};
+use crate::{
+    conditional::Conditional,
+    error::{ParseError, Result},
+    ordered,
+    span::PairSpans,
+    Key, MultiVdf, Obj, Parser, PartialVdf, Recovered, Value, Vdf, Warned,
+};
 
 // TODO: rename `PartialVdf` to `TopLevelVdf` and have it hold a `Vdf` instead of flattening it out
 
-mod escaped;
-mod raw;
-
-#[expect(deprecated)]
-pub use escaped::parse as escaped_parse;
-pub use escaped::PestError as EscapedPestError;
-#[expect(deprecated)]
-pub use raw::parse as raw_parse;
-pub use raw::PestError as RawPestError;
-
-type BoxedState<'a, R> = Box<pest::ParserState<'a, R>>;
-type ParseResult<'a, R> = pest::ParseResult<BoxedState<'a, R>>;
-
-#[inline]
-fn whitespace<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
-    s.atomic(Atomicity::Atomic, |s| {
-        s.match_string(" ")
-            .or_else(|s| s.match_string("\t"))
-            .or_else(|s| s.match_string("\r"))
-            .or_else(|s| s.match_string("\n"))
-    })
+#[cfg(feature = "pest")]
+mod grammar;
+mod hand;
+
+#[cfg(feature = "pest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pest")))]
+pub use grammar::{EscapedPestError, RawPestError};
+pub(crate) use hand::{
+    check_input_len, found, shares_line, strict_errors, truncate, VALVE_MAX_TOKEN_LEN,
+};
+
+/// Attempts to parse VDF text to a [`Vdf`]
+#[deprecated(since = "0.2.3", note = "Moved to `keyvalues_parser::parse()`")]
+pub fn escaped_parse(s: &str) -> Result<PartialVdf<'_>, ParseError> {
+    parse_with(&Parser::new(), s)
 }
 
-#[inline]
-fn any<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
-    s.skip(1)
+#[deprecated(
+    since = "0.2.3",
+    note = "Please use `Parser::new().literal_special_chars(true).parse()` instead"
+)]
+pub fn raw_parse(s: &str) -> Result<PartialVdf<'_>, ParseError> {
+    parse_with(&Parser::new().literal_special_chars(true), s)
 }
 
-fn soi<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
-    s.start_of_input()
+/// Everything that gets pulled out of the top-level of a VDF document
+pub(crate) struct Parsed<'a, V> {
+    pub(crate) bases: Vec<Cow<'a, str>>,
+    pub(crate) includes: Vec<Cow<'a, str>>,
+    pub(crate) key: Key<'a>,
+    pub(crate) value: V,
+    pub(crate) conditional: Option<Conditional<'a>>,
+    pub(crate) spans: Option<PairSpans<'a>>,
 }
 
-#[inline]
-fn comment<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
-    s.atomic(Atomicity::Atomic, |s| {
-        s.sequence(|s| {
-            s.match_string("//").and_then(|s| {
-                s.repeat(|s| {
-                    s.sequence(|s| s.lookahead(false, |s| s.match_string("\n")).and_then(any))
-                })
-            })
-        })
-    })
+/// Everything that gets pulled out of a document with any number of top-level pairs
+pub(crate) struct ParsedMulti<'a, V> {
+    pub(crate) bases: Vec<Cow<'a, str>>,
+    pub(crate) includes: Vec<Cow<'a, str>>,
+    pub(crate) roots: Vec<hand::PairParts<'a, V>>,
 }
 
-#[inline]
-fn skip<R: RuleType>(s: BoxedState<'_, R>) -> ParseResult<'_, R> {
-    if s.atomicity() == Atomicity::NonAtomic {
-        s.sequence(|s| {
-            s.repeat(whitespace).and_then(|s| {
-                s.repeat(|s| s.sequence(|s| comment(s).and_then(|s| s.repeat(whitespace))))
-            })
-        })
-    } else {
-        Ok(s)
+/// Allows for building either of the loosely typed representations while parsing
+pub(crate) trait Tree<'a>: Sized {
+    type Obj: Default;
+    /// Whether [`Tree::push()`] throws away the conditional
+    const DROPS_CONDITIONALS: bool;
+
+    fn from_str(s: Cow<'a, str>) -> Self;
+    fn from_obj(obj: Self::Obj) -> Self;
+    fn push(obj: &mut Self::Obj, key: Key<'a>, value: Self, conditional: Option<Conditional<'a>>);
+    /// Removes all of the values for `key`
+    fn remove(obj: &mut Self::Obj, key: &str);
+}
+
+impl<'a> Tree<'a> for Value<'a> {
+    type Obj = Obj<'a>;
+    const DROPS_CONDITIONALS: bool = true;
+
+    fn from_str(s: Cow<'a, str>) -> Self {
+        Self::Str(s)
+    }
+
+    fn from_obj(obj: Obj<'a>) -> Self {
+        Self::Obj(obj)
+    }
+
+    // The sorted representation has nowhere to store conditionals, so they're dropped.
+    // `Parser::parse_with_warnings()` reports each one
+    fn push(obj: &mut Obj<'a>, key: Key<'a>, value: Self, _: Option<Conditional<'a>>) {
+        obj.entry(key).or_default().push(value);
+    }
+
+    fn remove(obj: &mut Obj<'a>, key: &str) {
+        obj.remove(key);
     }
 }
 
-// unfortunate hack to re-use most of the code that consumes the pest parser produced by our two
-// separate grammars :/
-macro_rules! common_parsing {
-    ($parse_fn:ident, $rule:ty, $parse_escaped:expr) => {
-        fn parse_(s: &str) -> Result<PartialVdf<'_>> {
-            let mut full_grammar = $parse_fn(s)?;
-
-            // There can be multiple base macros before the initial pair
-            let mut bases = Vec::new();
-            loop {
-                let pair = full_grammar.next().unwrap();
-                if let <$rule>::base_macro = pair.as_rule() {
-                    let base_path_string = pair.into_inner().next().unwrap();
-                    let base_path = match base_path_string.as_rule() {
-                        <$rule>::quoted_raw_string => base_path_string.into_inner().next().unwrap(),
-                        <$rule>::unquoted_string => base_path_string,
-                        _ => unreachable!("Prevented by grammar"),
-                    }
-                    .as_str();
-                    bases.push(Cow::from(base_path));
-                } else {
-                    let (key, value) = parse_pair(pair);
-                    return Ok(PartialVdf { key, value, bases });
-                }
-            }
-        }
+impl<'a> Tree<'a> for ordered::Value<'a> {
+    type Obj = ordered::Obj<'a>;
+    const DROPS_CONDITIONALS: bool = false;
 
-        fn parse_pair(grammar_pair: PestPair<'_, $rule>) -> (Cow<'_, str>, Value<'_>) {
-            // Structure: pair
-            //            \ key   <- Desired
-            //            \ value <- Desired
-            if let <$rule>::pair = grammar_pair.as_rule() {
-                // Parse out the key and value
-                let mut grammar_pair_innards = grammar_pair.into_inner();
-                let grammar_string = grammar_pair_innards.next().unwrap();
-                let key = parse_string(grammar_string);
+    fn from_str(s: Cow<'a, str>) -> Self {
+        Self::Str(s)
+    }
 
-                let grammar_value = grammar_pair_innards.next().unwrap();
-                let value = Value::from(grammar_value);
+    fn from_obj(obj: ordered::Obj<'a>) -> Self {
+        Self::Obj(obj)
+    }
 
-                (key, value)
-            } else {
-                unreachable!("Prevented by grammar");
-            }
-        }
+    fn push(
+        obj: &mut ordered::Obj<'a>,
+        key: Key<'a>,
+        value: Self,
+        conditional: Option<Conditional<'a>>,
+    ) {
+        obj.0.push(ordered::Vdf {
+            key,
+            value,
+            conditional,
+        });
+    }
 
-        fn parse_string(grammar_string: PestPair<'_, $rule>) -> Cow<'_, str> {
-            match grammar_string.as_rule() {
-                // Structure: quoted_string
-                //            \ "
-                //            \ quoted_inner <- Desired
-                //            \ "
-                <$rule>::quoted_string => {
-                    let quoted_inner = grammar_string.into_inner().next().unwrap();
-                    if $parse_escaped {
-                        parse_escaped_string(quoted_inner)
-                    } else {
-                        Cow::from(quoted_inner.as_str())
-                    }
-                }
-                // Structure: unquoted_string <- Desired
-                <$rule>::unquoted_string => {
-                    let s = grammar_string.as_str();
-                    Cow::from(s)
+    fn remove(obj: &mut ordered::Obj<'a>, key: &str) {
+        obj.0.retain(|vdf| vdf.key != key);
+    }
+}
+
+/// Returns the ranges of the escape sequences in `s` that aren't `\n`, `\r`, `\t`, `\\`, or `\"`
+pub(crate) fn invalid_escapes(s: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut chars = s.char_indices();
+    std::iter::from_fn(move || {
+        while let Some((i, c)) = chars.next() {
+            if c != '\\' {
+                continue;
+            }
+            if let Some((j, escaped)) = chars.next() {
+                if !matches!(escaped, 'n' | 'r' | 't' | '\\' | '"') {
+                    return Some(i..j + escaped.len_utf8());
                 }
-                _ => unreachable!("Prevented by grammar"),
             }
         }
+        None
+    })
+}
 
-        // Note: there can be a slight performance win here by having the grammar skip capturing
-        // quoted_inner and instead just slice off the starting and ending '"', but I'm going to pass since
-        // it seems like a hack for a ~4% improvement
-        fn parse_escaped_string(inner: PestPair<'_, $rule>) -> Cow<'_, str> {
-            let s = inner.as_str();
-
-            if s.contains('\\') {
-                // Escaped version won't be quite as long, but it will likely be close
-                let mut escaped = String::with_capacity(s.len());
-                let mut it = s.chars();
-
-                while let Some(ch) = it.next() {
-                    if ch == '\\' {
-                        // Character is escaped so check the next character to figure out the full
-                        // character
-                        match it.next() {
-                            Some('n') => escaped.push('\n'),
-                            Some('r') => escaped.push('\r'),
-                            Some('t') => escaped.push('\t'),
-                            Some('\\') => escaped.push('\\'),
-                            Some('\"') => escaped.push('\"'),
-                            _ => unreachable!("Prevented by grammar"),
-                        }
-                    } else {
-                        escaped.push(ch)
+/// Replaces the escape sequences in `s` with the characters they represent
+///
+/// The grammar only allows for `\n`, `\r`, `\t`, `\\`, and `\"`, so anything else is left as-is
+pub(crate) fn unescape(s: &str) -> Cow<'_, str> {
+    if s.contains('\\') {
+        // Escaped version won't be quite as long, but it will likely be close
+        let mut escaped = String::with_capacity(s.len());
+        let mut it = s.chars();
+
+        while let Some(ch) = it.next() {
+            if ch == '\\' {
+                // Character is escaped so check the next character to figure out the full
+                // character
+                match it.next() {
+                    Some('n') => escaped.push('\n'),
+                    Some('r') => escaped.push('\r'),
+                    Some('t') => escaped.push('\t'),
+                    Some('\\') => escaped.push('\\'),
+                    Some('\"') => escaped.push('\"'),
+                    Some(other) => {
+                        escaped.push('\\');
+                        escaped.push(other);
                     }
+                    None => escaped.push('\\'),
                 }
-
-                Cow::from(escaped)
             } else {
-                Cow::from(s)
+                escaped.push(ch)
             }
         }
 
-        impl<'a> From<PestPair<'a, $rule>> for Value<'a> {
-            fn from(grammar_value: PestPair<'a, $rule>) -> Self {
-                // Structure: value is ( obj | quoted_string | unquoted_string )
-                match grammar_value.as_rule() {
-                    // Structure: ( quoted_string | unquoted_string )
-                    <$rule>::quoted_string | <$rule>::unquoted_string => {
-                        Self::Str(parse_string(grammar_value))
-                    }
-                    // Structure: obj
-                    //            \ pair* <- Desired
-                    <$rule>::obj => {
-                        let mut obj = Obj::new();
-                        for grammar_pair in grammar_value.into_inner() {
-                            let (key, value) = parse_pair(grammar_pair);
-                            let entry = obj.entry(key).or_default();
-                            (*entry).push(value);
-                        }
-
-                        Self::Obj(obj)
-                    }
-                    _ => unreachable!("Prevented by grammar"),
-                }
-            }
-        }
+        Cow::from(escaped)
+    } else {
+        Cow::from(s)
+    }
+}
+
+pub(crate) fn parse_with<'a>(parser: &Parser, s: &'a str) -> Result<PartialVdf<'a>, ParseError> {
+    let Parsed {
+        bases,
+        includes,
+        key,
+        value,
+        spans,
+        ..
+    } = hand::parse(parser, s)?;
+    Ok(PartialVdf {
+        key,
+        value,
+        bases,
+        includes,
+        spans,
+    })
+}
+
+#[cfg(feature = "pest")]
+pub(crate) fn parse_pest_with<'a>(
+    parser: &Parser,
+    s: &'a str,
+) -> Result<PartialVdf<'a>, ParseError> {
+    let Parsed {
+        bases,
+        includes,
+        key,
+        value,
+        spans,
+        ..
+    } = grammar::parse_tree(parser, s)?;
+    Ok(PartialVdf {
+        key,
+        value,
+        bases,
+        includes,
+        spans,
+    })
+}
+
+pub(crate) fn parse_warned_with<'a>(parser: &Parser, s: &'a str) -> Result<Warned<'a>, ParseError> {
+    let (
+        Parsed {
+            bases,
+            includes,
+            key,
+            value,
+            spans,
+            ..
+        },
+        warnings,
+    ) = hand::parse_warned(parser, s)?;
+    let vdf = PartialVdf {
+        key,
+        value,
+        bases,
+        includes,
+        spans,
+    };
+    Ok(Warned { vdf, warnings })
+}
+
+pub(crate) fn parse_recovering_with<'a>(parser: &Parser, s: &'a str) -> Recovered<'a> {
+    let (
+        Parsed {
+            bases,
+            includes,
+            key,
+            value,
+            spans,
+            ..
+        },
+        errors,
+    ) = hand::parse_recovering(parser, s);
+    let vdf = PartialVdf {
+        key,
+        value,
+        bases,
+        includes,
+        spans,
     };
+    Recovered { vdf, errors }
+}
+
+pub(crate) fn parse_multi_with<'a>(
+    parser: &Parser,
+    s: &'a str,
+) -> Result<MultiVdf<'a>, ParseError> {
+    let ParsedMulti {
+        bases,
+        includes,
+        roots,
+    } = hand::parse_multi(parser, s)?;
+    let spans = parser.spans.then(|| {
+        roots
+            .iter()
+            .filter_map(|(_, _, _, spans)| spans.clone())
+            .collect()
+    });
+    let roots = roots
+        .into_iter()
+        .map(|(key, value, ..)| Vdf { key, value })
+        .collect();
+    Ok(MultiVdf {
+        roots,
+        bases,
+        includes,
+        spans,
+    })
 }
 
-// expose ^^ macro to the rest of the crate
-pub(crate) use common_parsing;
+pub(crate) fn parse_ordered_with<'a>(
+    parser: &Parser,
+    s: &'a str,
+) -> Result<ordered::PartialVdf<'a>, ParseError> {
+    // Spans mirror the layout of the sorted representation, so they're skipped here
+    let Parsed {
+        bases,
+        includes,
+        key,
+        value,
+        conditional,
+        ..
+    } = hand::parse(&parser.clone().with_spans(false), s)?;
+    Ok(ordered::PartialVdf {
+        key,
+        value,
+        conditional,
+        bases,
+        includes,
+    })
+}
 
 impl<'a> Vdf<'a> {
     /// Attempts to parse VDF text to a [`Vdf`]
-    pub fn parse(s: &'a str) -> Result<Self> {
+    pub fn parse(s: &'a str) -> Result<Self, ParseError> {
         Ok(Self::from(PartialVdf::parse(s)?))
     }
 
-    pub fn parse_raw(s: &'a str) -> Result<Self> {
+    pub fn parse_raw(s: &'a str) -> Result<Self, ParseError> {
         Ok(Self::from(PartialVdf::parse_raw(s)?))
     }
 }
 
 impl<'a> PartialVdf<'a> {
     /// Attempts to parse VDF text to a [`Vdf`]
-    pub fn parse(s: &'a str) -> Result<Self> {
+    pub fn parse(s: &'a str) -> Result<Self, ParseError> {
         #[expect(deprecated)]
         escaped_parse(s)
     }
 
-    pub fn parse_raw(s: &'a str) -> Result<Self> {
+    pub fn parse_raw(s: &'a str) -> Result<Self, ParseError> {
         #[expect(deprecated)]
         raw_parse(s)
     }
 }
+
+impl<'a> MultiVdf<'a> {
+    /// Attempts to parse VDF text with any number of top-level pairs to a [`MultiVdf`]
+    pub fn parse(s: &'a str) -> Result<Self, ParseError> {
+        Parser::new().parse_document(s)
+    }
+
+    pub fn parse_raw(s: &'a str) -> Result<Self, ParseError> {
+        Parser::new().literal_special_chars(true).parse_document(s)
+    }
+}
@@ -1,22 +1,56 @@
-use std::fmt::{self, Write};
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::btree_map,
+    fmt::{self, Write},
+    io, slice,
+};
 
-use crate::{error::Error, Obj, PartialVdf, Value, Vdf};
+use crate::{
+    conditional::Conditional,
+    error::{InvalidRawStr, RenderError},
+    ordered, Decoded, Escaping, Indent, Key, MultiVdf, PartialVdf, RenderOptions, Value, Vdf,
+};
 
-fn multiple_char(c: char, amount: usize) -> String {
-    std::iter::repeat(c).take(amount).collect()
+// Indentation and alignment get written in chunks of these instead of allocating a new `String`
+// every line
+const TABS: &str = "\t\t\t\t\t\t\t\t\t\t\t\t\t\t\t\t";
+const SPACES: &str = "                                                                ";
+
+fn write_repeated(writer: &mut impl Write, run: &'static str, mut amount: usize) -> fmt::Result {
+    while amount > 0 {
+        let len = amount.min(run.len());
+        writer.write_str(&run[..len])?;
+        amount -= len;
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, Clone, Copy)]
-enum RenderType {
+pub(crate) enum RenderType {
     Escaped,
     Raw,
 }
 
-fn find_invalid_raw_char(s: &str) -> Option<char> {
+pub(crate) fn find_invalid_raw_char(s: &str) -> Option<char> {
     s.chars().find(|&c| c == '"').to_owned()
 }
 
-fn write_str(writer: &mut impl Write, s: &str, render_type: RenderType) -> fmt::Result {
+/// Returns if `s` can be written without quotes and still parse back to the same string
+pub(crate) fn is_unquoted_safe(s: &str) -> bool {
+    !s.is_empty()
+        // Would be picked up as a comment (or look like one to `Parser::strict()`) or a macro
+        && !s.contains("//")
+        && !s.starts_with('#')
+        // Would be picked up as a conditional when following a value
+        && !s.starts_with('[')
+        && !s
+            .chars()
+            .any(|c| matches!(c, '"' | '{' | '}' | ' ' | '\t' | '\r' | '\n'))
+}
+
+pub(crate) fn write_str(writer: &mut impl Write, s: &str, render_type: RenderType) -> fmt::Result {
     writer.write_char('"')?;
 
     match render_type {
@@ -38,95 +72,721 @@ fn write_str(writer: &mut impl Write, s: &str, render_type: RenderType) -> fmt::
     writer.write_char('"')
 }
 
-fn write_pair(
+/// The last thing that was written which is all that [`RenderOptions::compact()`] needs to know
+/// to keep tokens from running together
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Last {
+    /// A brace, a newline, or nothing at all
+    Separator,
+    UnquotedStr,
+    /// A quoted string or a conditional
+    Token,
+}
+
+/// How a tree gets laid out along with how its strings get written
+#[derive(Clone, Debug)]
+struct Style {
+    options: RenderOptions,
+    render_type: RenderType,
+    last: Cell<Last>,
+}
+
+impl Style {
+    const fn new(render_type: RenderType) -> Self {
+        Self::with_options(RenderOptions::new(), render_type)
+    }
+
+    const fn with_options(options: RenderOptions, render_type: RenderType) -> Self {
+        Self {
+            options,
+            render_type,
+            last: Cell::new(Last::Separator),
+        }
+    }
+
+    fn newline(&self, writer: &mut impl Write) -> fmt::Result {
+        if self.options.compact {
+            return Ok(());
+        }
+        self.line_break(writer)
+    }
+
+    /// Writes a newline even when compact
+    fn line_break(&self, writer: &mut impl Write) -> fmt::Result {
+        writer.write_str(if self.options.crlf { "\r\n" } else { "\n" })
+    }
+
+    fn indent(&self, writer: &mut impl Write, num_indents: usize) -> fmt::Result {
+        if self.options.compact {
+            return Ok(());
+        }
+        match self.options.indent {
+            Indent::Tabs => write_repeated(writer, TABS, num_indents),
+            Indent::Spaces(width) => write_repeated(writer, SPACES, num_indents * width),
+        }
+    }
+
+    fn is_unquoted(&self, s: &str) -> bool {
+        (self.options.quote_only_when_needed || self.options.compact) && is_unquoted_safe(s)
+    }
+
+    fn str(&self, writer: &mut impl Write, s: &str) -> fmt::Result {
+        // Strings always get separated from what came before them to keep `Parser::strict()`
+        // happy even though only unquoted ones would actually run together
+        if self.options.compact && self.last.get() != Last::Separator {
+            writer.write_char(' ')?;
+        }
+        if self.is_unquoted(s) {
+            self.last.set(Last::UnquotedStr);
+            writer.write_str(s)
+        } else {
+            self.last.set(Last::Token);
+            write_str(writer, s, self.render_type)
+        }
+    }
+
+    /// Writes a brace
+    fn punct(&self, writer: &mut impl Write, c: char) -> fmt::Result {
+        self.last.set(Last::Separator);
+        writer.write_char(c)
+    }
+
+    fn conditional(&self, writer: &mut impl Write, conditional: &Conditional<'_>) -> fmt::Result {
+        // The tag would get glued onto the end of an unquoted string
+        if !self.options.compact || self.last.get() == Last::UnquotedStr {
+            writer.write_char(' ')?;
+        }
+        self.last.set(Last::Token);
+        write!(writer, "{conditional}")
+    }
+
+    /// Returns how many columns `s` takes up once it's written
+    fn width(&self, s: &str) -> usize {
+        struct Columns(usize);
+
+        impl Write for Columns {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0 += s.chars().count();
+                Ok(())
+            }
+        }
+
+        if self.is_unquoted(s) {
+            return s.chars().count();
+        }
+        let mut columns = Columns(0);
+        write_str(&mut columns, s, self.render_type).expect("Counting columns can't fail");
+        columns.0
+    }
+
+    fn tab_width(&self) -> usize {
+        self.options.tab_width.max(1)
+    }
+
+    /// Returns the column that the string values in `obj` start at if they're aligned
+    fn value_column<V: RenderValue>(&self, obj: &V) -> Option<usize> {
+        if !self.options.align_values || self.options.compact {
+            return None;
+        }
+
+        let max_width = obj
+            .pairs()
+            .filter(|(_, value, _)| value.get_str().is_some())
+            .map(|(key, _, _)| self.width(key))
+            .max()?;
+        Some(match self.options.indent {
+            Indent::Tabs => (max_width / self.tab_width() + 1) * self.tab_width(),
+            Indent::Spaces(_) => max_width + 1,
+        })
+    }
+
+    /// Writes the gap between `key` and its string value which lines up with `column` if there
+    /// is one
+    fn separator(&self, writer: &mut impl Write, key: &str, column: Option<usize>) -> fmt::Result {
+        if self.options.compact {
+            return Ok(());
+        }
+        match (self.options.indent, column) {
+            (Indent::Tabs, None) => writer.write_char('\t'),
+            (Indent::Spaces(_), None) => writer.write_char(' '),
+            (Indent::Tabs, Some(column)) => {
+                let num_tabs = column / self.tab_width() - self.width(key) / self.tab_width();
+                write_repeated(writer, TABS, num_tabs)
+            }
+            (Indent::Spaces(_), Some(column)) => {
+                write_repeated(writer, SPACES, column - self.width(key))
+            }
+        }
+    }
+}
+
+/// A key along with its value and conditional
+type Pair<'a, V> = (&'a str, &'a V, Option<&'a Conditional<'a>>);
+
+/// The parts of a value that rendering needs, so that the sorted and [`ordered`] trees can share
+/// the same rendering logic
+trait RenderValue: Sized {
+    type Pairs<'a>: Iterator<Item = Pair<'a, Self>>
+    where
+        Self: 'a;
+
+    fn get_str(&self) -> Option<&str>;
+
+    /// Returns each pair in the order they should be rendered. This is empty for strings
+    fn pairs(&self) -> Self::Pairs<'_>;
+
+    fn write_indented(
+        &self,
+        writer: &mut impl Write,
+        num_indents: usize,
+        style: &Style,
+    ) -> fmt::Result {
+        // Only `Obj` gets indented
+        match self.get_str() {
+            Some(s) => style.str(writer, s),
+            None => {
+                style.punct(writer, '{')?;
+                style.newline(writer)?;
+                write_obj_body(writer, self, num_indents, style)
+            }
+        }
+    }
+
+    fn find_invalid_raw_char(&self) -> Option<char> {
+        if let Some(s) = self.get_str() {
+            return find_invalid_raw_char(s);
+        }
+
+        // Walk the tree with an explicit stack so that deeply nested objects can't overflow
+        let mut stack = vec![self.pairs()];
+        while let Some(pairs) = stack.last_mut() {
+            let Some((key, value, _)) = pairs.next() else {
+                stack.pop();
+                continue;
+            };
+            if let Some(invalid_char) = find_invalid_raw_char(key) {
+                return Some(invalid_char);
+            }
+            match value.get_str() {
+                Some(s) => {
+                    if let Some(invalid_char) = find_invalid_raw_char(s) {
+                        return Some(invalid_char);
+                    }
+                }
+                None => stack.push(value.pairs()),
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterates over every value of every key in a sorted [`Obj`][crate::Obj]
+struct SortedPairs<'a, 'text> {
+    keys: Option<btree_map::Iter<'a, Key<'text>, Vec<Value<'text>>>>,
+    values: Option<(&'a str, slice::Iter<'a, Value<'text>>)>,
+}
+
+impl<'a, 'text> Iterator for SortedPairs<'a, 'text> {
+    type Item = Pair<'a, Value<'text>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, values)) = &mut self.values {
+                if let Some(value) = values.next() {
+                    return Some((key, value, None));
+                }
+            }
+            let (key, values) = self.keys.as_mut()?.next()?;
+            self.values = Some((key, values.iter()));
+        }
+    }
+}
+
+impl<'text> RenderValue for Value<'text> {
+    type Pairs<'a>
+        = SortedPairs<'a, 'text>
+    where
+        Self: 'a;
+
+    fn get_str(&self) -> Option<&str> {
+        self.get_str()
+    }
+
+    fn pairs(&self) -> Self::Pairs<'_> {
+        SortedPairs {
+            keys: match self {
+                Value::Obj(obj) => Some(obj.iter()),
+                Value::Str(_) => None,
+            },
+            values: None,
+        }
+    }
+}
+
+/// Iterates over the pairs in an [`ordered::Obj`]
+struct OrderedPairs<'a, 'text>(Option<slice::Iter<'a, ordered::Vdf<'text>>>);
+
+impl<'a, 'text> Iterator for OrderedPairs<'a, 'text> {
+    type Item = Pair<'a, ordered::Value<'text>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let vdf = self.0.as_mut()?.next()?;
+        Some((&vdf.key, &vdf.value, vdf.conditional.as_ref()))
+    }
+}
+
+impl<'text> RenderValue for ordered::Value<'text> {
+    type Pairs<'a>
+        = OrderedPairs<'a, 'text>
+    where
+        Self: 'a;
+
+    fn get_str(&self) -> Option<&str> {
+        self.get_str()
+    }
+
+    fn pairs(&self) -> Self::Pairs<'_> {
+        OrderedPairs(match self {
+            ordered::Value::Obj(obj) => Some(obj.iter()),
+            ordered::Value::Str(_) => None,
+        })
+    }
+}
+
+fn write_directives(
+    writer: &mut impl Write,
+    bases: &[Cow<'_, str>],
+    includes: &[Cow<'_, str>],
+    style: &Style,
+) -> fmt::Result {
+    // Directive paths are always quoted, so nothing needs to separate them when compact. Each
+    // directive still gets its own line though since `Parser::strict()` requires it
+    let gap = if style.options.compact { "" } else { " " };
+    let directives = bases
+        .iter()
+        .map(|base| ("#base", base))
+        .chain(includes.iter().map(|include| ("#include", include)));
+    for (keyword, path) in directives {
+        write!(writer, "{keyword}{gap}\"{path}\"")?;
+        style.line_break(writer)?;
+    }
+    style.last.set(Last::Separator);
+
+    if !bases.is_empty() || !includes.is_empty() {
+        style.newline(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the indented key followed by either its string value or the `{` that opens its object
+fn write_pair_start(
     writer: &mut impl Write,
     num_indents: usize,
     key: &str,
-    value: &Value<'_>,
-    render_type: RenderType,
+    value: &impl RenderValue,
+    style: &Style,
+    column: Option<usize>,
 ) -> fmt::Result {
-    // Write the indented key
-    writer.write_str(&multiple_char('\t', num_indents))?;
-    write_str(writer, key, render_type)?;
+    style.indent(writer, num_indents)?;
+    style.str(writer, key)?;
 
-    // Followed by the value
-    if value.is_str() {
-        writer.write_char('\t')?;
-    } else {
-        writer.write_char('\n')?;
+    match value.get_str() {
+        Some(s) => {
+            style.separator(writer, key, column)?;
+            style.str(writer, s)
+        }
+        None => {
+            if style.options.brace_on_same_line && !style.options.compact {
+                writer.write_char(' ')?;
+            } else {
+                style.newline(writer)?;
+                style.indent(writer, num_indents)?;
+            }
+            style.punct(writer, '{')?;
+            style.newline(writer)
+        }
     }
-    value.write_indented(writer, num_indents, render_type)?;
+}
 
-    writer.write_char('\n')
+fn write_pair_end(
+    writer: &mut impl Write,
+    conditional: Option<&Conditional<'_>>,
+    style: &Style,
+) -> fmt::Result {
+    if let Some(conditional) = conditional {
+        style.conditional(writer, conditional)?;
+    }
+    style.newline(writer)
 }
 
-fn write_obj(
+/// An object that's in the middle of being written
+struct Frame<'a, V: RenderValue + 'a> {
+    pairs: V::Pairs<'a>,
+    /// The conditional that follows the object's closing `}`
+    conditional: Option<&'a Conditional<'a>>,
+    column: Option<usize>,
+    prev_is_obj: Option<bool>,
+}
+
+impl<'a, V: RenderValue + 'a> Frame<'a, V> {
+    fn new(obj: &'a V, conditional: Option<&'a Conditional<'a>>, style: &Style) -> Self {
+        Self {
+            pairs: obj.pairs(),
+            conditional,
+            column: style.value_column(obj),
+            prev_is_obj: None,
+        }
+    }
+}
+
+/// Writes everything after the `{` of `obj` through its closing `}`
+///
+/// Nested objects are tracked with an explicit stack instead of recursing, so deeply nested trees
+/// can't overflow the stack
+fn write_obj_body<V: RenderValue>(
     writer: &mut impl Write,
+    obj: &V,
     num_indents: usize,
-    obj: &Obj<'_>,
-    render_type: RenderType,
+    style: &Style,
 ) -> fmt::Result {
-    for (key, values) in obj.iter() {
-        for value in values {
-            write_pair(writer, num_indents, key, value, render_type)?;
+    let mut stack = vec![Frame::new(obj, None, style)];
+    loop {
+        // Everything in the stack is one level deeper than the object it's in
+        let depth = num_indents + stack.len();
+        let Some(frame) = stack.last_mut() else {
+            break;
+        };
+        let Some((key, value, conditional)) = frame.pairs.next() else {
+            let frame = stack.pop().expect("Stack can't be empty");
+            style.indent(writer, depth - 1)?;
+            style.punct(writer, '}')?;
+            if !stack.is_empty() {
+                write_pair_end(writer, frame.conditional, style)?;
+            }
+            continue;
+        };
+
+        // Only the pairs in the outermost object get split up into blocks
+        let is_obj = value.get_str().is_none();
+        let blocks = style.options.blank_lines_between_blocks && depth == 1;
+        if blocks
+            && frame
+                .prev_is_obj
+                .map_or(false, |prev_is_obj| prev_is_obj || is_obj)
+        {
+            style.newline(writer)?;
+        }
+        frame.prev_is_obj = Some(is_obj);
+
+        write_pair_start(writer, depth, key, value, style, frame.column)?;
+        if is_obj {
+            stack.push(Frame::new(value, conditional, style));
+        } else {
+            write_pair_end(writer, conditional, style)?;
         }
     }
 
     Ok(())
 }
 
+fn write_pair(
+    writer: &mut impl Write,
+    num_indents: usize,
+    key: &str,
+    value: &impl RenderValue,
+    conditional: Option<&Conditional<'_>>,
+    style: &Style,
+) -> fmt::Result {
+    write_pair_start(writer, num_indents, key, value, style, None)?;
+    if value.get_str().is_none() {
+        write_obj_body(writer, value, num_indents, style)?;
+    }
+    write_pair_end(writer, conditional, style)
+}
+
+/// Picks how to write strings for [`RenderOptions::escaping()`]
+fn pick_render_type(
+    escaping: Escaping,
+    find_invalid_raw_char: impl FnOnce() -> Option<char>,
+) -> Result<RenderType, RenderError> {
+    match escaping {
+        Escaping::Escaped => Ok(RenderType::Escaped),
+        Escaping::Raw => match find_invalid_raw_char() {
+            Some(invalid_char) => Err(RenderError::InvalidRawChar { invalid_char }),
+            None => Ok(RenderType::Raw),
+        },
+        Escaping::Auto => Ok(match find_invalid_raw_char() {
+            Some(_) => RenderType::Escaped,
+            None => RenderType::Raw,
+        }),
+    }
+}
+
+/// Pushes every string in the pair that can't be rendered raw onto `found`
+fn find_invalid_raw_strs<V: RenderValue>(key: &str, value: &V, found: &mut Vec<InvalidRawStr>) {
+    let mut path = Vec::new();
+    let mut check = |path: &[&str], s: &str, in_key| {
+        if let Some(invalid_char) = find_invalid_raw_char(s) {
+            found.push(InvalidRawStr {
+                path: path.iter().map(|&key| key.to_owned()).collect(),
+                in_key,
+                invalid_char,
+            });
+        }
+    };
+
+    // Walk the tree with an explicit stack where each object keeps its key on the path until all
+    // of its pairs are checked
+    let mut stack = Vec::new();
+    let mut next = Some((key, value));
+    loop {
+        if let Some((key, value)) = next.take() {
+            path.push(key);
+            check(&path, key, true);
+            match value.get_str() {
+                Some(s) => {
+                    check(&path, s, false);
+                    path.pop();
+                }
+                None => stack.push(value.pairs()),
+            }
+        }
+
+        let Some(pairs) = stack.last_mut() else {
+            break;
+        };
+        match pairs.next() {
+            Some((key, value, _)) => next = Some((key, value)),
+            None => {
+                stack.pop();
+                path.pop();
+            }
+        }
+    }
+}
+
+/// Adapts an [`io::Write`] to a buffered [`fmt::Write`] while holding onto any [`io::Error`]
+struct IoWriter<W: io::Write> {
+    inner: io::BufWriter<W>,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        io::Write::write_all(&mut self.inner, s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+/// Calls `render` with a buffered writer that writes through to `writer`
+fn with_io_writer<W: io::Write>(
+    writer: W,
+    render: impl FnOnce(&mut IoWriter<W>) -> Result<(), RenderError>,
+) -> Result<(), RenderError> {
+    let mut io_writer = IoWriter {
+        inner: io::BufWriter::new(writer),
+        error: None,
+    };
+    let result = render(&mut io_writer);
+    if let Some(err) = io_writer.error.take() {
+        return Err(err.into());
+    }
+    result?;
+    io::Write::flush(&mut io_writer.inner).map_err(Into::into)
+}
+
 impl fmt::Display for PartialVdf<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self._render(f, RenderType::Raw)
+        self._render(f, &Style::new(RenderType::Raw))
     }
 }
 
 impl PartialVdf<'_> {
     // TODO: do we really want to return a crate error here? It will always be a formatting error
-    pub fn render(&self, writer: &mut impl Write) -> crate::error::Result<()> {
-        self._render(writer, RenderType::Raw).map_err(Into::into)
+    pub fn render(&self, writer: &mut impl Write) -> Result<(), RenderError> {
+        self._render(writer, &Style::new(RenderType::Raw))
+            .map_err(Into::into)
+    }
+
+    /// The same as [`PartialVdf::render()`], but writes to an [`io::Write`] through an internal buffer
+    pub fn render_to_io(&self, writer: &mut impl io::Write) -> Result<(), RenderError> {
+        with_io_writer(writer, |writer| self.render(writer))
+    }
+
+    /// Renders the document laid out according to `options`
+    ///
+    /// Special characters are written according to [`RenderOptions::escaping()`]
+    pub fn render_with(
+        &self,
+        writer: &mut impl Write,
+        options: &RenderOptions,
+    ) -> Result<(), RenderError> {
+        let render_type = pick_render_type(options.escaping, || self.find_invalid_raw_char())?;
+        let style = Style::with_options(*options, render_type);
+        self._render(writer, &style).map_err(Into::into)
+    }
+
+    /// Returns every string that keeps [`PartialVdf::render_raw()`] from working
+    pub fn validate_raw(&self) -> Vec<InvalidRawStr> {
+        let mut found = Vec::new();
+        find_invalid_raw_strs(&self.key, &self.value, &mut found);
+        found
     }
 
-    pub fn render_raw(&self, writer: &mut impl Write) -> crate::error::Result<()> {
+    pub fn render_raw(&self, writer: &mut impl Write) -> Result<(), RenderError> {
         match self.find_invalid_raw_char() {
-            Some(invalid_char) => Err(Error::RawRenderError { invalid_char }),
-            None => self._render(writer, RenderType::Raw).map_err(Into::into),
+            Some(invalid_char) => Err(RenderError::InvalidRawChar { invalid_char }),
+            None => self
+                ._render(writer, &Style::new(RenderType::Raw))
+                .map_err(Into::into),
+        }
+    }
+
+    fn _render(&self, writer: &mut impl Write, style: &Style) -> fmt::Result {
+        write_directives(writer, &self.bases, &self.includes, style)?;
+        write_pair(writer, 0, &self.key, &self.value, None, style)
+    }
+
+    fn find_invalid_raw_char(&self) -> Option<char> {
+        find_invalid_raw_char(&self.key).or_else(|| self.value.find_invalid_raw_char())
+    }
+}
+
+impl fmt::Display for MultiVdf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self._render(f, &Style::new(RenderType::Escaped))
+    }
+}
+
+impl MultiVdf<'_> {
+    pub fn render(&self, writer: &mut impl Write) -> Result<(), RenderError> {
+        write!(writer, "{self}").map_err(Into::into)
+    }
+
+    /// The same as [`MultiVdf::render()`], but writes to an [`io::Write`] through an internal buffer
+    pub fn render_to_io(&self, writer: &mut impl io::Write) -> Result<(), RenderError> {
+        with_io_writer(writer, |writer| self.render(writer))
+    }
+
+    /// Returns every string that keeps [`MultiVdf::render_raw()`] from working
+    pub fn validate_raw(&self) -> Vec<InvalidRawStr> {
+        let mut found = Vec::new();
+        for root in &self.roots {
+            find_invalid_raw_strs(&root.key, &root.value, &mut found);
         }
+        found
     }
 
-    fn _render(&self, writer: &mut impl Write, render_type: RenderType) -> fmt::Result {
-        for base in &self.bases {
-            writeln!(writer, "#base \"{base}\"")?;
+    pub fn render_raw(&self, writer: &mut impl Write) -> Result<(), RenderError> {
+        match self.find_invalid_raw_char() {
+            Some(invalid_char) => Err(RenderError::InvalidRawChar { invalid_char }),
+            None => self
+                ._render(writer, &Style::new(RenderType::Raw))
+                .map_err(Into::into),
         }
+    }
 
-        if !self.bases.is_empty() {
-            writer.write_char('\n')?;
+    fn _render(&self, writer: &mut impl Write, style: &Style) -> fmt::Result {
+        write_directives(writer, &self.bases, &self.includes, style)?;
+        for root in &self.roots {
+            root.write_indented(writer, 0, style)?;
         }
 
-        write_pair(writer, 0, &self.key, &self.value, render_type)
+        Ok(())
     }
 
     fn find_invalid_raw_char(&self) -> Option<char> {
-        find_invalid_raw_char(&self.key).or_else(|| self.value.find_invalid_raw_char())
+        self.roots.iter().find_map(Vdf::find_invalid_raw_char)
+    }
+}
+
+impl Decoded<'_> {
+    /// Renders the document back to bytes using the same encoding and byte order mark that it
+    /// was read with
+    pub fn render(&self) -> Result<Vec<u8>, RenderError> {
+        let mut text = String::new();
+        self.vdf
+            ._render(&mut text, &Style::new(RenderType::Escaped))?;
+        self.detected.encode(&text)
+    }
+
+    /// The same as [`Decoded::render()`], but writes special characters literally for documents
+    /// parsed with [`Parser::literal_special_chars()`][crate::Parser::literal_special_chars]
+    pub fn render_raw(&self) -> Result<Vec<u8>, RenderError> {
+        let mut text = String::new();
+        self.vdf.render_raw(&mut text)?;
+        self.detected.encode(&text)
     }
 }
 
 impl fmt::Display for Vdf<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.write_indented(f, 0, RenderType::Escaped)
+        self.write_indented(f, 0, &Style::new(RenderType::Escaped))
     }
 }
 
 impl Vdf<'_> {
-    pub fn render(&self, writer: &mut impl Write) -> crate::error::Result<()> {
+    pub fn render(&self, writer: &mut impl Write) -> Result<(), RenderError> {
         write!(writer, "{self}").map_err(Into::into)
     }
 
-    pub fn render_raw(&self, writer: &mut impl Write) -> crate::error::Result<()> {
+    /// The same as [`Vdf::render()`], but writes to an [`io::Write`] through an internal buffer
+    ///
+    /// Nothing is held onto between writes besides the buffer, so this is a good fit for
+    /// streaming large generated trees straight to a file
+    ///
+    /// ```
+    /// use keyvalues_parser::Vdf;
+    /// let vdf = Vdf::parse("outer { inner value }")?;
+    /// let mut bytes = Vec::new();
+    /// vdf.render_to_io(&mut bytes)?;
+    /// assert_eq!(bytes, vdf.to_string().as_bytes());
+    /// # Ok::<(), keyvalues_parser::error::Error>(())
+    /// ```
+    pub fn render_to_io(&self, writer: &mut impl io::Write) -> Result<(), RenderError> {
+        with_io_writer(writer, |writer| self.render(writer))
+    }
+
+    /// Renders the pair laid out according to `options`
+    ///
+    /// Special characters are written according to [`RenderOptions::escaping()`]
+    ///
+    /// ```
+    /// use keyvalues_parser::{RenderOptions, Vdf};
+    /// let vdf = Vdf::parse("key value")?;
+    /// let mut rendered = String::new();
+    /// vdf.render_with(&mut rendered, &RenderOptions::new().crlf(true))?;
+    /// assert_eq!(rendered, "\"key\"\t\"value\"\r\n");
+    /// # Ok::<(), keyvalues_parser::error::Error>(())
+    /// ```
+    pub fn render_with(
+        &self,
+        writer: &mut impl Write,
+        options: &RenderOptions,
+    ) -> Result<(), RenderError> {
+        let render_type = pick_render_type(options.escaping, || self.find_invalid_raw_char())?;
+        let style = Style::with_options(*options, render_type);
+        self.write_indented(writer, 0, &style).map_err(Into::into)
+    }
+
+    /// Returns every string that keeps [`Vdf::render_raw()`] from working
+    ///
+    /// An empty list means that the pair can be rendered raw
+    pub fn validate_raw(&self) -> Vec<InvalidRawStr> {
+        let mut found = Vec::new();
+        find_invalid_raw_strs(&self.key, &self.value, &mut found);
+        found
+    }
+
+    pub fn render_raw(&self, writer: &mut impl Write) -> Result<(), RenderError> {
         match self.find_invalid_raw_char() {
-            Some(invalid_char) => Err(Error::RawRenderError { invalid_char }),
+            Some(invalid_char) => Err(RenderError::InvalidRawChar { invalid_char }),
             None => self
-                .write_indented(writer, 0, RenderType::Raw)
+                .write_indented(writer, 0, &Style::new(RenderType::Raw))
                 .map_err(Into::into),
         }
     }
@@ -139,43 +799,133 @@ impl Vdf<'_> {
         &self,
         writer: &mut impl Write,
         num_indents: usize,
-        render_type: RenderType,
+        style: &Style,
     ) -> fmt::Result {
-        write_pair(writer, num_indents, &self.key, &self.value, render_type)
+        write_pair(writer, num_indents, &self.key, &self.value, None, style)
     }
 }
 
 impl fmt::Display for Value<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.write_indented(f, 0, RenderType::Escaped)
+        self.write_indented(f, 0, &Style::new(RenderType::Escaped))
     }
 }
 
 impl Value<'_> {
-    fn write_indented(
+    /// Renders just the value laid out according to `options`
+    ///
+    /// An object is written as its braces along with everything in between and a string is
+    /// written on its own. Special characters are written according to
+    /// [`RenderOptions::escaping()`]
+    pub fn render_with(
         &self,
         writer: &mut impl Write,
-        num_indents: usize,
-        render_type: RenderType,
-    ) -> fmt::Result {
-        // Only `Obj` gets indented
-        match self {
-            Value::Str(s) => write_str(writer, s, render_type),
-            Value::Obj(obj) => {
-                writeln!(writer, "{}{{", multiple_char('\t', num_indents))?;
-                write_obj(writer, num_indents + 1, obj, render_type)?;
-                write!(writer, "{}}}", multiple_char('\t', num_indents))
-            }
+        options: &RenderOptions,
+    ) -> Result<(), RenderError> {
+        let render_type = pick_render_type(options.escaping, || self.find_invalid_raw_char())?;
+        let style = Style::with_options(*options, render_type);
+        self.write_indented(writer, 0, &style).map_err(Into::into)
+    }
+}
+
+impl fmt::Display for ordered::PartialVdf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self._render(f, &Style::new(RenderType::Raw))
+    }
+}
+
+impl ordered::PartialVdf<'_> {
+    pub fn render(&self, writer: &mut impl Write) -> Result<(), RenderError> {
+        self._render(writer, &Style::new(RenderType::Raw))
+            .map_err(Into::into)
+    }
+
+    /// The same as [`ordered::PartialVdf::render()`], but writes to an [`io::Write`] through an internal buffer
+    pub fn render_to_io(&self, writer: &mut impl io::Write) -> Result<(), RenderError> {
+        with_io_writer(writer, |writer| self.render(writer))
+    }
+
+    /// Returns every string that keeps [`ordered::PartialVdf::render_raw()`] from working
+    pub fn validate_raw(&self) -> Vec<InvalidRawStr> {
+        let mut found = Vec::new();
+        find_invalid_raw_strs(&self.key, &self.value, &mut found);
+        found
+    }
+
+    pub fn render_raw(&self, writer: &mut impl Write) -> Result<(), RenderError> {
+        match self.find_invalid_raw_char() {
+            Some(invalid_char) => Err(RenderError::InvalidRawChar { invalid_char }),
+            None => self
+                ._render(writer, &Style::new(RenderType::Raw))
+                .map_err(Into::into),
         }
     }
 
+    fn _render(&self, writer: &mut impl Write, style: &Style) -> fmt::Result {
+        write_directives(writer, &self.bases, &self.includes, style)?;
+        write_pair(
+            writer,
+            0,
+            &self.key,
+            &self.value,
+            self.conditional.as_ref(),
+            style,
+        )
+    }
+
     fn find_invalid_raw_char(&self) -> Option<char> {
-        match self {
-            Self::Str(s) => find_invalid_raw_char(s),
-            Self::Obj(obj) => obj.iter().find_map(|(key, values)| {
-                find_invalid_raw_char(key)
-                    .or_else(|| values.iter().find_map(Value::find_invalid_raw_char))
-            }),
+        find_invalid_raw_char(&self.key).or_else(|| self.value.find_invalid_raw_char())
+    }
+}
+
+impl fmt::Display for ordered::Vdf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_pair(
+            f,
+            0,
+            &self.key,
+            &self.value,
+            self.conditional.as_ref(),
+            &Style::new(RenderType::Escaped),
+        )
+    }
+}
+
+impl ordered::Vdf<'_> {
+    pub fn render(&self, writer: &mut impl Write) -> Result<(), RenderError> {
+        write!(writer, "{self}").map_err(Into::into)
+    }
+
+    /// The same as [`ordered::Vdf::render()`], but writes to an [`io::Write`] through an internal buffer
+    pub fn render_to_io(&self, writer: &mut impl io::Write) -> Result<(), RenderError> {
+        with_io_writer(writer, |writer| self.render(writer))
+    }
+
+    /// Returns every string that keeps [`ordered::Vdf::render_raw()`] from working
+    pub fn validate_raw(&self) -> Vec<InvalidRawStr> {
+        let mut found = Vec::new();
+        find_invalid_raw_strs(&self.key, &self.value, &mut found);
+        found
+    }
+
+    pub fn render_raw(&self, writer: &mut impl Write) -> Result<(), RenderError> {
+        match find_invalid_raw_char(&self.key).or_else(|| self.value.find_invalid_raw_char()) {
+            Some(invalid_char) => Err(RenderError::InvalidRawChar { invalid_char }),
+            None => write_pair(
+                writer,
+                0,
+                &self.key,
+                &self.value,
+                self.conditional.as_ref(),
+                &Style::new(RenderType::Raw),
+            )
+            .map_err(Into::into),
         }
     }
 }
+
+impl fmt::Display for ordered::Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_indented(f, 0, &Style::new(RenderType::Escaped))
+    }
+}
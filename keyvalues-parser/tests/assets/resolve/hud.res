@@ -0,0 +1,10 @@
+#include "shared\hud_extra.res"
+#base "shared/hud_defaults.res"
+
+"Resource/HudLayout.res"
+{
+	"HudHealth"
+	{
+		"xpos"	"16"
+	}
+}
@@ -0,0 +1,12 @@
+"Resource/HudLayout.res"
+{
+	"HudHealth"
+	{
+		"xpos"	"0"
+		"ypos"	"0"
+	}
+	"HudAmmo"
+	{
+		"xpos"	"0"
+	}
+}
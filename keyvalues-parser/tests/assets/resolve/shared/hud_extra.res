@@ -0,0 +1,7 @@
+"Resource/HudLayout.res"
+{
+	"HudHealth"
+	{
+		"wide"	"100"
+	}
+}
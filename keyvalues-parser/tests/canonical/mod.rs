@@ -0,0 +1,111 @@
+use std::{fs, path::Path};
+
+use keyvalues_parser::{canonical::CanonicalOptions, Indent, Parser, RenderOptions, Vdf};
+use pretty_assertions::assert_eq;
+
+const MANIFEST: &str = r#"
+// comments and layout don't matter
+"AppState"
+{
+	"name"		"Portal 2"
+	"appid"		"620"
+	"dupe"		"1"
+	"UserConfig"	{ language english }
+	"dupe"		"2"
+}
+"#;
+
+#[test]
+fn formatting_is_ignored() {
+    let assets = Path::new("tests").join("assets");
+    let options = CanonicalOptions::new();
+    let layouts = [
+        RenderOptions::new(),
+        RenderOptions::new().compact(true),
+        RenderOptions::new()
+            .indent(Indent::Spaces(3))
+            .align_values(true)
+            .crlf(true)
+            .quote_only_when_needed(true)
+            .blank_lines_between_blocks(true),
+    ];
+    let mut num_checked = 0;
+    for entry in fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |ext| ext != "vdf") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        let Ok(vdf) = Parser::new().parse(&text) else {
+            continue;
+        };
+        let vdf = Vdf::from(vdf);
+
+        for layout in &layouts {
+            let mut rendered = String::new();
+            vdf.render_with(&mut rendered, layout).unwrap();
+            let reparsed = Vdf::from(Parser::new().parse(&rendered).unwrap());
+            assert!(vdf.semantic_eq(&reparsed, &options), "{path:?}");
+            assert_eq!(vdf.content_hash(&options), reparsed.content_hash(&options));
+        }
+        num_checked += 1;
+    }
+    assert!(num_checked > 5);
+}
+
+#[test]
+fn fold_keys() {
+    let vdf = Vdf::parse(MANIFEST).unwrap();
+    let shouted = Vdf::parse(
+        r#"APPSTATE { NAME "Portal 2" APPID 620 DUPE 1 USERCONFIG { LANGUAGE english } DUPE 2 }"#,
+    )
+    .unwrap();
+    let exact = CanonicalOptions::new();
+    let folded = exact.fold_keys(true);
+
+    assert!(!vdf.semantic_eq(&shouted, &exact));
+    assert_ne!(vdf.content_hash(&exact), shouted.content_hash(&exact));
+    assert!(vdf.semantic_eq(&shouted, &folded));
+    assert_eq!(vdf.content_hash(&folded), shouted.content_hash(&folded));
+
+    // Values of keys that fold together are merged in the order of their original spelling
+    let mixed = Vdf::parse("root { b 3 B 2 B 1 }").unwrap();
+    let canonical = mixed.canonicalize(&folded);
+    assert_eq!(canonical, Vdf::parse("root { b 2 b 1 b 3 }").unwrap());
+    assert_eq!(canonical.canonicalize(&folded), canonical);
+}
+
+#[test]
+fn content_differences() {
+    let options = CanonicalOptions::new();
+    let hash = |vdf_text| Vdf::parse(vdf_text).unwrap().content_hash(&options);
+
+    let distinct = [
+        hash("a { b c }"),
+        hash("a { b d }"),
+        hash("a { c b }"),
+        hash("a { b c b d }"),
+        // The order of values for the same key is kept
+        hash("a { b d b c }"),
+        hash("a { b { } }"),
+        hash(r#"a { b "" }"#),
+        hash("a b"),
+        hash("ab { }"),
+        hash("a { }"),
+    ];
+    for (i, a) in distinct.iter().enumerate() {
+        for b in &distinct[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+}
+
+#[test]
+fn pinned_hashes() {
+    // These must never change. Any change to how hashes are calculated would invalidate every
+    // hash that's been stored somewhere
+    let options = CanonicalOptions::new();
+    let hash = |vdf_text| Vdf::parse(vdf_text).unwrap().content_hash(&options);
+    assert_eq!(hash("key value"), 0xc7a4_77fc_067d_994d);
+    assert_eq!(hash(MANIFEST), 0xce75_53fa_19ba_ab23);
+}
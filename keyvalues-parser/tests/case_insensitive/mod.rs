@@ -0,0 +1,125 @@
+use keyvalues_parser::{error::ParseErrorKind, DuplicateKeys, Parser, Value, Vdf};
+use pretty_assertions::assert_eq;
+
+const MANIFEST: &str = r#"
+"AppState"
+{
+	"appid"		"620"
+	"StateFlags"		"4"
+	"installdir"		"Portal 2"
+	"InstalledDepots"
+	{
+		"621"	{ "manifest" "1" }
+	}
+	"installedDepots"
+	{
+		"622"	{ "manifest" "2" }
+	}
+	"InstallDir"		"Portal 2 Beta"
+}
+"#;
+
+#[test]
+fn lookups() {
+    let vdf = Parser::new().parse(MANIFEST).unwrap();
+    let obj = vdf.value.get_obj().unwrap();
+    assert!(obj.get("stateflags").is_none());
+    assert_eq!(
+        obj.get_ignore_case("STATEFLAGS").unwrap()[0].get_str(),
+        Some("4")
+    );
+    // Keys are checked in sorted order
+    let (key, _) = obj.get_key_value_ignore_case("installdir").unwrap();
+    assert_eq!(key, "InstallDir");
+    let dirs: Vec<_> = obj
+        .get_all_ignore_case("installdir")
+        .filter_map(Value::get_str)
+        .collect();
+    assert_eq!(dirs, ["Portal 2 Beta", "Portal 2"]);
+    assert!(obj.get_ignore_case("missing").is_none());
+
+    let mut vdf = vdf;
+    let obj = vdf.value.get_mut_obj().unwrap();
+    obj.get_mut_ignore_case("APPID")
+        .unwrap()
+        .push(Value::Str("400".into()));
+    assert_eq!(obj["appid"].len(), 2);
+}
+
+#[test]
+fn ordered_lookups() {
+    let vdf = Parser::new().parse_ordered(MANIFEST).unwrap();
+    let obj = vdf.value.get_obj().unwrap();
+    assert_eq!(
+        obj.get_ignore_case("INSTALLDIR")
+            .and_then(|value| value.get_str()),
+        Some("Portal 2")
+    );
+    assert_eq!(obj.get_all_ignore_case("installeddepots").count(), 2);
+}
+
+#[test]
+fn fold_keys() {
+    let vdf = Parser::new()
+        .fold_keys(true)
+        .with_spans(true)
+        .parse(MANIFEST)
+        .unwrap();
+    let obj = vdf.value.get_obj().unwrap();
+    assert_eq!(
+        obj.keys().collect::<Vec<_>>(),
+        ["InstalledDepots", "StateFlags", "appid", "installdir"]
+    );
+    let dirs: Vec<_> = obj["installdir"]
+        .iter()
+        .filter_map(Value::get_str)
+        .collect();
+    assert_eq!(dirs, ["Portal 2", "Portal 2 Beta"]);
+    assert_eq!(obj["InstalledDepots"].len(), 2);
+
+    // Spans follow the folded keys
+    let spans = vdf.spans.as_ref().unwrap();
+    let spans = spans.value.get_obj().unwrap();
+    let lines: Vec<_> = spans["installdir"]
+        .iter()
+        .map(|pair| pair.key.start.line)
+        .collect();
+    assert_eq!(lines, [6, 15]);
+
+    // The first spelling is what gets rendered
+    let rendered = Vdf::from(vdf).to_string();
+    assert!(rendered.contains("\"installdir\"\t\"Portal 2 Beta\""));
+    assert!(!rendered.contains("InstallDir"));
+}
+
+#[test]
+fn fold_keys_with_duplicate_policy() {
+    let parser = Parser::new().fold_keys(true);
+    let vdf = parser
+        .clone()
+        .duplicate_keys(DuplicateKeys::LastWins)
+        .parse(MANIFEST)
+        .unwrap();
+    let obj = vdf.value.get_obj().unwrap();
+    assert_eq!(obj["installdir"][0].get_str(), Some("Portal 2 Beta"));
+
+    let err = parser
+        .duplicate_keys(DuplicateKeys::Error)
+        .parse(MANIFEST)
+        .unwrap_err();
+    let ParseErrorKind::DuplicateKey { key, first } = err.kind() else {
+        panic!("unexpected error: {err:?}");
+    };
+    assert_eq!((key.as_str(), first.line), ("InstalledDepots", 7));
+    assert_eq!(err.span().as_str(MANIFEST), "\"installedDepots\"");
+}
+
+#[test]
+fn fold_ordered() {
+    let vdf = Parser::new()
+        .fold_keys(true)
+        .parse_ordered("k { Key 1 other 2 KEY 3 }")
+        .unwrap();
+    let obj = vdf.value.get_obj().unwrap();
+    assert_eq!(obj.keys().collect::<Vec<_>>(), ["Key", "other", "Key"]);
+}
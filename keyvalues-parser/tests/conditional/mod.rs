@@ -0,0 +1,192 @@
+use std::{fs, path::Path};
+
+use keyvalues_parser::{
+    conditional::{Conditional, Symbol},
+    error::ParseErrorKind,
+    ordered::{self, Value},
+    Parser, Vdf,
+};
+use pretty_assertions::assert_eq;
+
+fn read_asset_file(file_name: &str) -> String {
+    fs::read_to_string(Path::new("tests").join("assets").join(file_name)).unwrap()
+}
+
+fn keys_and_strs(obj: &ordered::Obj<'_>) -> Vec<(String, Option<String>)> {
+    obj.iter()
+        .map(|vdf| (vdf.key.to_string(), vdf.value.get_str().map(String::from)))
+        .collect()
+}
+
+#[test]
+fn parse_and_render() {
+    let vdf_text = read_asset_file("conditionals.vdf");
+    let vdf = Parser::new().parse_ordered(&vdf_text).unwrap();
+    let root = vdf.value.get_obj().unwrap();
+
+    let health = root.get("HudHealth").and_then(Value::get_obj).unwrap();
+    let conditionals: Vec<_> = health
+        .iter()
+        .map(|vdf| vdf.conditional.as_ref().map(ToString::to_string))
+        .collect();
+    assert_eq!(
+        conditionals,
+        [
+            None,
+            Some("[$WIN32]".to_owned()),
+            Some("[$X360]".to_owned()),
+            Some("[!$X360 && !$DECK]".to_owned()),
+            Some("[$WIN32 && !$X360 || $OSX]".to_owned()),
+        ]
+    );
+    let conditionals: Vec<_> = root.iter().map(|vdf| vdf.conditional.clone()).collect();
+    assert_eq!(
+        conditionals,
+        [
+            None,
+            Conditional::parse("[$X360||$PS3]"),
+            Some(Symbol::new("WIN32").into()),
+            None,
+        ]
+    );
+
+    // Things that aren't valid conditionals are left alone
+    assert_eq!(
+        root.get("[NotConditional]").unwrap().get_str(),
+        Some("[$ALSO_NOT]")
+    );
+
+    // Rendering normalizes the formatting of conditionals and they survive a round trip
+    let rendered = vdf.to_string();
+    assert!(rendered.contains("\t\t\"wide\"\t\"102\" [$WIN32 && !$X360 || $OSX]\n"));
+    assert!(rendered.contains("\t} [$X360 || $PS3]\n"));
+    let reparsed = Parser::new().parse_ordered(&rendered).unwrap();
+    assert_eq!(reparsed, vdf);
+}
+
+#[test]
+fn evaluate() {
+    let vdf_text = read_asset_file("conditionals.vdf");
+    let vdf = Parser::new().parse_ordered(&vdf_text).unwrap();
+
+    let pc = vdf.clone().evaluate_conditionals(&["WIN32"]).unwrap();
+    let root = pc.value.get_obj().unwrap();
+    assert!(root.iter().all(|vdf| vdf.conditional.is_none()));
+    assert_eq!(
+        root.keys().collect::<Vec<_>>(),
+        ["HudHealth", "HudMenu", "[NotConditional]"]
+    );
+    let health = root.get("HudHealth").and_then(Value::get_obj).unwrap();
+    assert!(health.iter().all(|vdf| vdf.conditional.is_none()));
+    assert_eq!(
+        keys_and_strs(health),
+        [
+            ("fieldName".to_owned(), Some("HudHealth".to_owned())),
+            ("xpos".to_owned(), Some("16".to_owned())),
+            ("visible".to_owned(), Some("1".to_owned())),
+            ("wide".to_owned(), Some("102".to_owned())),
+        ]
+    );
+
+    let console = vdf.evaluate_conditionals(&["x360"]).unwrap();
+    let root = console.value.get_obj().unwrap();
+    assert_eq!(
+        root.keys().collect::<Vec<_>>(),
+        ["HudHealth", "HudConsole", "[NotConditional]"]
+    );
+    let health = root.get("HudHealth").and_then(Value::get_obj).unwrap();
+    assert_eq!(
+        keys_and_strs(health),
+        [
+            ("fieldName".to_owned(), Some("HudHealth".to_owned())),
+            ("xpos".to_owned(), Some("32".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn top_level_conditional() {
+    let vdf = Parser::new().parse_ordered("key value [$WIN32]").unwrap();
+    assert_eq!(vdf.conditional, Some(Symbol::new("WIN32").into()));
+    assert_eq!(vdf.to_string(), "\"key\"\t\"value\" [$WIN32]\n");
+    assert_eq!(vdf.clone().evaluate_conditionals::<&str>(&[]), None);
+    let evaluated = vdf.evaluate_conditionals(&["WIN32"]).unwrap();
+    assert_eq!(evaluated.conditional, None);
+}
+
+#[test]
+fn sorted_representation_keeps_everything() {
+    let vdf_text = read_asset_file("conditionals.vdf");
+    let vdf = Vdf::parse(&vdf_text).unwrap();
+    let health = vdf.value.get_obj().unwrap()["HudHealth"][0]
+        .get_obj()
+        .unwrap();
+    assert_eq!(health["xpos"].len(), 2);
+
+    let raw = Vdf::parse_raw(&vdf_text).unwrap();
+    assert_eq!(raw, vdf);
+}
+
+#[test]
+fn sorted_representation_drops_conditionals() {
+    let vdf_text = read_asset_file("conditionals.vdf");
+    let sorted = Vdf::parse(&vdf_text).unwrap().to_string();
+    assert!(sorted.contains("\t\t\"xpos\"\t\"16\"\n"));
+    assert!(sorted.contains("\t\t\"enabled\"\t\"1\"\n\t}\n"));
+    // Only the text that merely looks like a conditional is left
+    assert_eq!(sorted.matches("[$").count(), 1);
+
+    let ordered = Parser::new().parse_ordered(&vdf_text).unwrap().to_string();
+    assert!(ordered.contains("\t\t\"xpos\"\t\"16\" [$WIN32]\n"));
+
+    // but not silently
+    let text = "root { k a [$WIN32] k b [$OSX] }";
+    let warned = Parser::new().parse_with_warnings(text).unwrap();
+    let dropped: Vec<_> = warned
+        .warnings
+        .iter()
+        .map(|warning| {
+            assert_eq!(warning.kind(), &ParseErrorKind::DroppedConditional);
+            warning.span().as_str(text)
+        })
+        .collect();
+    assert_eq!(dropped, ["[$WIN32]", "[$OSX]"]);
+    let warned = Parser::new()
+        .parse_with_warnings(&vdf_text)
+        .unwrap()
+        .warnings;
+    assert_eq!(warned.len(), 6);
+}
+
+#[test]
+fn invalid_conditionals() {
+    for invalid in [
+        "[]",
+        "[WIN32]",
+        "[$]",
+        "[$A &&]",
+        "[$A || || $B]",
+        "[! $A]",
+        "[$A",
+    ] {
+        assert_eq!(Conditional::parse(invalid), None, "{invalid}");
+    }
+
+    // These are treated as a regular key instead, so there's a value missing
+    Parser::new()
+        .parse_ordered("key value [$A &&]")
+        .unwrap_err();
+    Parser::new()
+        .parse_ordered("key value [WIN32]")
+        .unwrap_err();
+}
+
+#[test]
+fn spans() {
+    let vdf_text = "key\n{\n\ta b [$X]\n}";
+    let vdf = Parser::new().with_spans(true).parse(vdf_text).unwrap();
+    let spans = vdf.spans.unwrap();
+    assert_eq!(spans.conditional, None);
+    let inner = &spans.value.get_obj().unwrap()["a"][0];
+    assert_eq!(inner.conditional.as_ref().unwrap().as_str(vdf_text), "[$X]");
+}
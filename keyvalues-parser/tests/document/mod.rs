@@ -0,0 +1,123 @@
+use std::{fs, path::Path};
+
+use keyvalues_parser::{
+    error::{Expected, Found, ParseErrorKind},
+    MultiVdf, Parser, Vdf,
+};
+use pretty_assertions::assert_eq;
+
+const VMF: &str = r#"versioninfo
+{
+	"editorversion" "400"
+	"formatversion" "100"
+}
+world
+{
+	"id" "1"
+	"classname" "worldspawn"
+	solid
+	{
+		"id" "2"
+	}
+}
+entity
+{
+	"id" "3"
+	"classname" "info_player_start"
+}
+entity
+{
+	"id" "4"
+	"classname" "light"
+}
+"#;
+
+#[test]
+fn multiple_roots() {
+    let doc = Parser::new().parse_document(VMF).unwrap();
+    let keys: Vec<_> = doc.roots.iter().map(|vdf| vdf.key.as_ref()).collect();
+    assert_eq!(keys, ["versioninfo", "world", "entity", "entity"]);
+    assert_eq!(
+        doc.roots[3],
+        Vdf::parse(r#"entity { "id" "4" "classname" "light" }"#).unwrap()
+    );
+
+    // Only a single root is allowed with the regular parser
+    let err = Parser::new().parse(VMF).unwrap_err();
+    assert_eq!(err.line(), 6);
+}
+
+#[test]
+fn round_trip() {
+    let doc = MultiVdf::parse(VMF).unwrap();
+    let rendered = doc.to_string();
+    assert_eq!(MultiVdf::parse(&rendered).unwrap(), doc);
+    insta::assert_snapshot!(rendered);
+
+    let mut raw = String::new();
+    doc.render_raw(&mut raw).unwrap();
+    assert_eq!(MultiVdf::parse_raw(&raw).unwrap(), doc);
+}
+
+#[test]
+fn single_root_matches_parse() {
+    let assets = Path::new("tests").join("assets");
+    for entry in fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |ext| ext != "vdf") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+
+        for literal in [false, true] {
+            let parser = Parser::new()
+                .literal_special_chars(literal)
+                .with_spans(true);
+            let Ok(partial) = parser.parse(&text) else {
+                continue;
+            };
+            let doc = parser.parse_document(&text).unwrap();
+            assert_eq!(doc.bases, partial.bases, "{path:?}");
+            assert_eq!(doc.includes, partial.includes, "{path:?}");
+            assert_eq!(doc.spans, Some(vec![partial.spans.clone().unwrap()]));
+            assert_eq!(doc.roots, [Vdf::from(partial)], "{path:?}");
+        }
+    }
+}
+
+#[test]
+fn empty_documents() {
+    for text in ["", "  // only a comment\n", "#base base.vdf", "\0"] {
+        let doc = Parser::new().parse_document(text).unwrap();
+        assert!(doc.roots.is_empty(), "{text:?}");
+        assert_eq!(
+            doc.to_string(),
+            doc.bases.first().map_or("", |_| "#base \"base.vdf\"\n\n")
+        );
+    }
+}
+
+#[test]
+fn errors() {
+    let err = Parser::new().parse_document("a {} } b {}").unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ParseErrorKind::Unexpected {
+            found: Found::ObjClose,
+            expected: vec![Expected::Key, Expected::EndOfInput],
+        }
+    );
+    assert_eq!(err.offset(), 5);
+
+    let err = Parser::new().parse_document("a {} b").unwrap_err();
+    assert_eq!(err.offset(), 6);
+    // Directives can only come first, so afterwards they're regular pairs
+    let doc = Parser::new().parse_document("a {} #base file.vdf").unwrap();
+    assert!(doc.bases.is_empty());
+    assert_eq!(doc.roots[1].key, "#base");
+    // Limits apply to the whole document
+    Parser::new()
+        .max_pairs(2)
+        .parse_document("a b c d e f")
+        .unwrap_err();
+}
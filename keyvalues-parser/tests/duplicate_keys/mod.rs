@@ -0,0 +1,111 @@
+use keyvalues_parser::{error::ParseErrorKind, DuplicateKeys, Parser, PartialVdf, Value};
+use pretty_assertions::assert_eq;
+
+const VDF_TEXT: &str = r#"
+"config"
+{
+	"volume"	"0.5"
+	"video"
+	{
+		"volume"	"unrelated"
+	}
+	"volume"	"0.8"
+	"video"	"flat"
+	"volume"	"1.0"
+}
+"#;
+
+fn parse(policy: DuplicateKeys) -> PartialVdf<'static> {
+    Parser::new()
+        .duplicate_keys(policy)
+        .with_spans(true)
+        .parse(VDF_TEXT)
+        .unwrap()
+}
+
+fn volumes<'a>(vdf: &'a PartialVdf<'_>) -> Vec<&'a str> {
+    vdf.value.get_obj().unwrap()["volume"]
+        .iter()
+        .filter_map(Value::get_str)
+        .collect()
+}
+
+#[test]
+fn keep_all() {
+    let vdf = parse(DuplicateKeys::KeepAll);
+    assert_eq!(volumes(&vdf), ["0.5", "0.8", "1.0"]);
+    assert_eq!(vdf, Parser::new().with_spans(true).parse(VDF_TEXT).unwrap());
+}
+
+#[test]
+fn first_wins() {
+    let vdf = parse(DuplicateKeys::FirstWins);
+    assert_eq!(volumes(&vdf), ["0.5"]);
+    let obj = vdf.value.get_obj().unwrap();
+    assert!(obj["video"][0].get_obj().is_some());
+    assert_eq!(obj["video"].len(), 1);
+
+    let spans = vdf.spans.unwrap();
+    let spans = spans.value.get_obj().unwrap();
+    assert_eq!(spans["volume"].len(), 1);
+    assert_eq!(spans["volume"][0].key.start.line, 4);
+}
+
+#[test]
+fn last_wins() {
+    let vdf = parse(DuplicateKeys::LastWins);
+    assert_eq!(volumes(&vdf), ["1.0"]);
+    let obj = vdf.value.get_obj().unwrap();
+    assert_eq!(obj["video"][0].get_str(), Some("flat"));
+
+    // Spans still line up with the values that were kept
+    let spans = vdf.spans.unwrap();
+    let spans = spans.value.get_obj().unwrap();
+    let lines: Vec<_> = spans.values().map(|pair| pair[0].key.start.line).collect();
+    assert_eq!(lines, [10, 11]);
+}
+
+#[test]
+fn error() {
+    let err = Parser::new()
+        .duplicate_keys(DuplicateKeys::Error)
+        .parse(VDF_TEXT)
+        .unwrap_err();
+    let ParseErrorKind::DuplicateKey { key, first } = err.kind() else {
+        panic!("unexpected error: {err:?}");
+    };
+    assert_eq!(key, "volume");
+    assert_eq!(&VDF_TEXT[first.offset..][..8], "\"volume\"");
+    assert_eq!((first.line, err.line()), (4, 9));
+    insta::assert_snapshot!(err);
+
+    // Every duplicate gets reported when recovering
+    let recovered = Parser::new()
+        .duplicate_keys(DuplicateKeys::Error)
+        .parse_recovering(VDF_TEXT);
+    let lines: Vec<_> = recovered.errors.iter().map(|err| err.line()).collect();
+    assert_eq!(lines, [9, 10, 11]);
+
+    // Keys in different objects are fine
+    Parser::new()
+        .duplicate_keys(DuplicateKeys::Error)
+        .parse("a { b { c d } e { c d } }")
+        .unwrap();
+}
+
+#[test]
+fn ordered() {
+    let keys = |policy| {
+        let vdf = Parser::new()
+            .duplicate_keys(policy)
+            .parse_ordered("k { a 1 b 2 a 3 }")
+            .unwrap();
+        let obj = vdf.value.get_obj().unwrap();
+        obj.iter()
+            .map(|vdf| format!("{}={}", vdf.key, vdf.value.get_str().unwrap()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(keys(DuplicateKeys::KeepAll), ["a=1", "b=2", "a=3"]);
+    assert_eq!(keys(DuplicateKeys::FirstWins), ["a=1", "b=2"]);
+    assert_eq!(keys(DuplicateKeys::LastWins), ["b=2", "a=3"]);
+}
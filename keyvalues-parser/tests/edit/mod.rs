@@ -0,0 +1,321 @@
+use std::{fs, path::Path};
+
+use keyvalues_parser::{
+    conditional::{Conditional, Symbol},
+    edit::{Document, Obj, Pair},
+    error::RenderError,
+    Parser, PartialVdf,
+};
+use pretty_assertions::assert_eq;
+
+// Generates tests that check that a file renders back to exactly the same text
+macro_rules! round_trip_test_generator {
+    ( $parse_fn:path, $( $name:ident ),* $(,)? ) => {
+        $(
+            #[test]
+            fn $name() {
+                let vdf_text = read_asset_file(&format!("{}.vdf", stringify!($name)));
+                let doc = $parse_fn(&vdf_text).unwrap();
+                pretty_assertions::assert_eq!(doc.to_string(), vdf_text);
+            }
+        )*
+    }
+}
+
+fn read_asset_file(file_name: &str) -> String {
+    fs::read_to_string(Path::new("tests").join("assets").join(file_name)).unwrap()
+}
+
+fn root_obj<'doc, 'text>(doc: &'doc mut Document<'text>) -> &'doc mut Obj<'text> {
+    doc.root_mut().value_mut().as_obj_mut().unwrap()
+}
+
+mod round_trip {
+    use super::*;
+
+    round_trip_test_generator!(
+        Document::parse,
+        basic,
+        comments,
+        compact,
+        unquoted_strings,
+        special_characters,
+        null_byte,
+        base_multiple,
+        base_quoted,
+        base_unquoted,
+        conditionals,
+        include_mixed,
+    );
+
+    mod raw {
+        use super::*;
+
+        round_trip_test_generator!(Document::parse_raw, raw_strings, base_multiple_raw_strings);
+    }
+}
+
+#[test]
+fn matches_loosely_typed_parse() {
+    let vdf_text = read_asset_file("base_multiple.vdf");
+    let doc = Document::parse(&vdf_text).unwrap();
+    let parsed = PartialVdf::parse(&vdf_text).unwrap();
+    assert_eq!(doc.to_partial_vdf(), parsed);
+
+    let vdf_text = read_asset_file("base_multiple_raw_strings.vdf");
+    let doc = Document::parse_raw(&vdf_text).unwrap();
+    let parsed = PartialVdf::parse_raw(&vdf_text).unwrap();
+    assert_eq!(doc.to_partial_vdf(), parsed);
+}
+
+#[test]
+fn invalid_text_errors() {
+    // The errors match the regular parser's
+    let texts = [
+        "",
+        "key {",
+        "#base no_vdf",
+        "} key {}",
+        "{ key }",
+        "outer { key }",
+        "outer { { } }",
+        "outer {} trailing",
+        "outer {}\0 trailing",
+        "outer {\n\tkey \"unclosed\n}",
+        r#"outer { key "bad \escape" }"#,
+        "#base \"unclosed",
+    ];
+    for text in texts {
+        assert_eq!(
+            Document::parse(text).unwrap_err(),
+            Parser::new().parse(text).unwrap_err(),
+            "{text:?}"
+        );
+        if let Err(err) = Parser::new().literal_special_chars(true).parse(text) {
+            assert_eq!(Document::parse_raw(text).unwrap_err(), err, "{text:?}");
+        }
+    }
+}
+
+#[test]
+fn macrolike_key() {
+    let vdf_text = "#base {}";
+    let doc = Document::parse(vdf_text).unwrap();
+    assert_eq!(doc.bases().count(), 0);
+    assert_eq!(doc.root().key(), "#base");
+    assert_eq!(doc.to_string(), vdf_text);
+}
+
+#[test]
+fn glued_bases() {
+    let vdf_text = "#base\"foo.vdf\"#basebar.vdf\nkey val";
+    let doc = Document::parse(vdf_text).unwrap();
+    assert_eq!(doc.bases().collect::<Vec<_>>(), ["foo.vdf", "bar.vdf"]);
+    assert_eq!(doc.to_string(), vdf_text);
+}
+
+const COMMENTED: &str = r#"// leading comment
+"Outer"
+{
+    // about first
+    "first"     "1"   // trailing first
+    second      2
+    "nested"
+    {
+        "inner" "value"
+    }
+}
+"#;
+
+#[test]
+fn set_keeps_quoting_style() {
+    let mut doc = Document::parse(COMMENTED).unwrap();
+    let obj = root_obj(&mut doc);
+    obj.insert("first", "one");
+    obj.insert("second", "two");
+    assert_eq!(
+        doc.to_string(),
+        COMMENTED
+            .replace(r#""first"     "1""#, r#""first"     "one""#)
+            .replace("second      2", "second      two")
+    );
+
+    // Unquoted strings get quoted when they need to be
+    let obj = root_obj(&mut doc);
+    obj.insert("second", "needs quotes");
+    assert_eq!(
+        obj.get_pair("second").unwrap().value().as_str(),
+        Some("needs quotes")
+    );
+    assert!(doc.to_string().contains(r#"second      "needs quotes""#));
+}
+
+#[test]
+fn remove_takes_comments_with_it() {
+    let mut doc = Document::parse(COMMENTED).unwrap();
+    let removed = root_obj(&mut doc).remove("first").unwrap();
+    assert_eq!(removed.trailing_comment(), Some("// trailing first"));
+    assert_eq!(
+        doc.to_string(),
+        r#"// leading comment
+"Outer"
+{
+    second      2
+    "nested"
+    {
+        "inner" "value"
+    }
+}
+"#
+    );
+}
+
+#[test]
+fn insert_copies_sibling_formatting() {
+    let mut doc = Document::parse(COMMENTED).unwrap();
+    let nested = root_obj(&mut doc)
+        .get_mut("nested")
+        .unwrap()
+        .as_obj_mut()
+        .unwrap();
+    nested.insert("added", "new");
+    assert_eq!(
+        doc.to_string(),
+        COMMENTED.replace(
+            "        \"inner\" \"value\"\n",
+            "        \"inner\" \"value\"\n        \"added\" \"new\"\n"
+        )
+    );
+}
+
+#[test]
+fn new_objects_get_default_formatting() {
+    let mut doc = Document::parse("root{}").unwrap();
+    let mut new_obj = Obj::new();
+    new_obj.push(Pair::new("key", "value"));
+    new_obj.push(Pair::new("escaped", "\"quoted\"\n"));
+    root_obj(&mut doc).insert("child", new_obj);
+    assert_eq!(
+        doc.to_string(),
+        "root{\n\t\"child\"\n\t{\n\t\t\"key\"\t\"value\"\n\t\t\"escaped\"\t\"\\\"quoted\\\"\\n\"\n\t}\n}"
+    );
+
+    let rendered = doc.to_string();
+    let reparsed = Document::parse(&rendered).unwrap().to_partial_vdf();
+    assert_eq!(reparsed, doc.to_partial_vdf());
+}
+
+#[test]
+fn duplicate_keys_keep_their_order() {
+    let vdf_text = "root\n{\n\ta 1\n\tb 2\n\ta 3\n}\n";
+    let mut doc = Document::parse(vdf_text).unwrap();
+    let obj = root_obj(&mut doc);
+    let a_values: Vec<_> = obj.get_all("a").filter_map(|v| v.as_str()).collect();
+    assert_eq!(a_values, ["1", "3"]);
+    let keys: Vec<_> = obj.iter().map(Pair::key).collect();
+    assert_eq!(keys, ["a", "b", "a"]);
+
+    assert_eq!(obj.remove_all("a"), 2);
+    assert_eq!(doc.to_string(), "root\n{\n\tb 2\n}\n");
+}
+
+#[test]
+fn bases() {
+    let vdf_text = read_asset_file("base_multiple.vdf");
+    let mut doc = Document::parse(&vdf_text).unwrap();
+    assert!(doc.remove_base("../another_base.vdf"));
+    assert!(!doc.remove_base("../another_base.vdf"));
+    doc.push_base("new.vdf");
+    assert_eq!(
+        doc.to_string(),
+        "#base \"one_base.res\"\n#base other\\path\\sep.pop\n#base \"new.vdf\"\n\n\"Key\"\n{\n}\n"
+    );
+}
+
+#[test]
+fn conditionals() {
+    let vdf_text = "root\n{\n\ta 1 [$WIN32]\n\tb 2\n}\n";
+    let mut doc = Document::parse(vdf_text).unwrap();
+    let obj = root_obj(&mut doc);
+    assert_eq!(
+        obj.get_pair("a").unwrap().conditional(),
+        Conditional::parse("[$WIN32]").as_ref()
+    );
+    assert_eq!(obj.get_pair("b").unwrap().conditional(), None);
+
+    let old = obj
+        .get_pair_mut("a")
+        .unwrap()
+        .set_conditional(Some(Symbol::negated("X360").into()));
+    assert_eq!(old, Conditional::parse("[$WIN32]"));
+    obj.get_pair_mut("b")
+        .unwrap()
+        .set_conditional(Some(Symbol::new("OSX").into()));
+    assert_eq!(
+        doc.to_string(),
+        "root\n{\n\ta 1 [!$X360]\n\tb 2 [$OSX]\n}\n"
+    );
+}
+
+#[test]
+fn includes() {
+    let vdf_text = read_asset_file("include_mixed.vdf");
+    let mut doc = Document::parse(&vdf_text).unwrap();
+    assert_eq!(doc.bases().collect::<Vec<_>>(), ["hudlayout_base.res"]);
+    assert_eq!(
+        doc.includes().collect::<Vec<_>>(),
+        ["resource/ui/hud_shared.res", r"platform\hud_pc.res"]
+    );
+    assert_eq!(doc.to_partial_vdf(), PartialVdf::parse(&vdf_text).unwrap());
+
+    // Only the directives of the matching kind get removed
+    assert!(!doc.remove_include("hudlayout_base.res"));
+    assert!(doc.remove_include("resource/ui/hud_shared.res"));
+    doc.push_include("new.res");
+    assert_eq!(
+        doc.to_string(),
+        vdf_text
+            .replace("#include \"resource/ui/hud_shared.res\"\n", "")
+            .replace(
+                "#include platform\\hud_pc.res\n",
+                "#include platform\\hud_pc.res\n#include \"new.res\"\n"
+            )
+    );
+
+    let doc = Document::parse("#include {}").unwrap();
+    assert_eq!(doc.includes().count(), 0);
+    assert_eq!(doc.root().key(), "#include");
+}
+
+#[test]
+fn raw_documents_cant_render_quotes() {
+    let mut doc = Document::parse_raw("key value").unwrap();
+    doc.root_mut().set_value("\"quoted\"");
+    let mut rendered = String::new();
+    assert_eq!(
+        doc.render(&mut rendered),
+        Err(RenderError::InvalidRawChar { invalid_char: '"' })
+    );
+}
+
+#[test]
+fn directive_paths_cant_render_quotes() {
+    let mut doc = Document::parse("key { a b }\n").unwrap();
+    doc.push_base("we\"ird.vdf");
+    let mut rendered = String::new();
+    assert_eq!(
+        doc.render(&mut rendered),
+        Err(RenderError::InvalidRawChar { invalid_char: '"' })
+    );
+
+    // Anything that does render parses back the same
+    let mut doc = Document::parse("key { a b }\n").unwrap();
+    doc.push_base(r"dir\we'ird.vdf");
+    doc.push_include("inc lude.vdf");
+    let mut rendered = String::new();
+    doc.render(&mut rendered).unwrap();
+    let reparsed = Document::parse(&rendered).unwrap();
+    assert_eq!(reparsed.to_partial_vdf(), doc.to_partial_vdf());
+    assert_eq!(reparsed.bases().collect::<Vec<_>>(), [r"dir\we'ird.vdf"]);
+    assert_eq!(reparsed.includes().collect::<Vec<_>>(), ["inc lude.vdf"]);
+}
@@ -0,0 +1,156 @@
+use std::borrow::Cow;
+
+use keyvalues_parser::{
+    encoding::{Detected, Encoding},
+    error::{Error, RenderError},
+    Parser,
+};
+use pretty_assertions::assert_eq;
+
+const LOCALIZATION: &str = r#""lang"
+{
+	"Language"	"english"
+	"Tokens"
+	{
+		"greeting"	"Grüße \"Welt\" ✓"
+	}
+}
+"#;
+
+fn utf16(text: &str, bom: &[u8], to_bytes: fn(u16) -> [u8; 2]) -> Vec<u8> {
+    let mut bytes = bom.to_vec();
+    bytes.extend(text.encode_utf16().flat_map(to_bytes));
+    bytes
+}
+
+fn greeting(decoded: &keyvalues_parser::Decoded<'_>) -> String {
+    let tokens = decoded.vdf.value.get_obj().unwrap()["Tokens"][0]
+        .get_obj()
+        .unwrap();
+    tokens["greeting"][0].get_str().unwrap().to_owned()
+}
+
+#[test]
+fn utf16_le_round_trip() {
+    let bytes = utf16(LOCALIZATION, &[0xff, 0xfe], u16::to_le_bytes);
+    let decoded = Parser::new().parse_bytes(&bytes).unwrap();
+    assert_eq!(
+        decoded.detected,
+        Detected {
+            encoding: Encoding::Utf16Le,
+            bom: true
+        }
+    );
+    assert_eq!(greeting(&decoded), "Grüße \"Welt\" ✓");
+
+    let rendered = decoded.render().unwrap();
+    assert!(rendered.starts_with(&[0xff, 0xfe]));
+    let reparsed = Parser::new().parse_bytes(&rendered).unwrap();
+    assert_eq!(reparsed, decoded);
+}
+
+#[test]
+fn utf16_be() {
+    let bytes = utf16(LOCALIZATION, &[0xfe, 0xff], u16::to_be_bytes);
+    let decoded = Parser::new().parse_bytes(&bytes).unwrap();
+    assert_eq!(decoded.detected.encoding, Encoding::Utf16Be);
+    assert_eq!(greeting(&decoded), "Grüße \"Welt\" ✓");
+}
+
+#[test]
+fn utf8_bom_is_stripped() {
+    let mut bytes = vec![0xef, 0xbb, 0xbf];
+    bytes.extend_from_slice(LOCALIZATION.as_bytes());
+    let decoded = Parser::new().with_spans(true).parse_bytes(&bytes).unwrap();
+    assert_eq!(
+        decoded.detected,
+        Detected {
+            encoding: Encoding::Utf8,
+            bom: true
+        }
+    );
+    // UTF-8 gets borrowed straight from the input and spans skip over the BOM
+    assert!(matches!(decoded.vdf.key, Cow::Borrowed("lang")));
+    let key_span = decoded.vdf.spans.as_ref().unwrap().key;
+    assert_eq!(key_span.start.offset, 0);
+    assert!(decoded.render().unwrap().starts_with(&[0xef, 0xbb, 0xbf]));
+}
+
+#[test]
+fn plain_utf8() {
+    let decoded = Parser::new().parse_bytes(LOCALIZATION.as_bytes()).unwrap();
+    assert_eq!(
+        decoded.detected,
+        Detected {
+            encoding: Encoding::Utf8,
+            bom: false
+        }
+    );
+    let rendered = decoded.render().unwrap();
+    assert_eq!(Parser::new().parse_bytes(&rendered).unwrap(), decoded);
+}
+
+#[test]
+fn legacy_fallback() {
+    let bytes = b"key \"\x80 caf\xe9\"";
+
+    let decoded = Parser::new().parse_bytes(bytes).unwrap();
+    assert_eq!(decoded.detected.encoding, Encoding::Windows1252);
+    assert_eq!(decoded.vdf.value.get_str(), Some("€ café"));
+    assert_eq!(decoded.render().unwrap(), b"\"key\"\t\"\x80 caf\xe9\"\n");
+
+    let decoded = Parser::new()
+        .fallback_encoding(Encoding::Latin1)
+        .parse_bytes(bytes)
+        .unwrap();
+    assert_eq!(decoded.detected.encoding, Encoding::Latin1);
+    assert_eq!(decoded.vdf.value.get_str(), Some("\u{80} café"));
+
+    let err = Parser::new()
+        .fallback_encoding(Encoding::Utf8)
+        .parse_bytes(bytes)
+        .unwrap_err();
+    let Error::Decode(err) = err else {
+        panic!("expected a decode error: {err:?}");
+    };
+    assert_eq!((err.encoding(), err.offset()), (Encoding::Utf8, 5));
+}
+
+#[test]
+fn invalid_utf16() {
+    // An odd number of bytes after the BOM
+    let mut bytes = utf16("key value", &[0xff, 0xfe], u16::to_le_bytes);
+    bytes.push(b'!');
+    let err = Detected::detect(&bytes, Encoding::Windows1252)
+        .decode(&bytes)
+        .unwrap_err();
+    assert_eq!((err.encoding(), err.offset()), (Encoding::Utf16Le, 20));
+
+    // A lone high surrogate
+    let mut bytes = utf16("k ", &[0xfe, 0xff], u16::to_be_bytes);
+    bytes.extend([0xd8, 0x00, 0x00, b'v']);
+    let err = Parser::new().parse_bytes(&bytes).unwrap_err();
+    let Error::Decode(err) = err else {
+        panic!("expected a decode error: {err:?}");
+    };
+    assert_eq!((err.encoding(), err.offset()), (Encoding::Utf16Be, 6));
+}
+
+#[test]
+fn unencodable_char() {
+    let detected = Detected {
+        encoding: Encoding::Windows1252,
+        bom: false,
+    };
+    assert_eq!(detected.encode("€ ÿ").unwrap(), b"\x80 \xff");
+    assert_eq!(
+        detected.encode("✓"),
+        Err(RenderError::UnencodableChar {
+            invalid_char: '✓',
+            encoding: Encoding::Windows1252
+        })
+    );
+    // Latin-1 maps the C1 controls directly instead
+    assert_eq!(Encoding::Latin1.encode("\u{80}").unwrap(), b"\x80");
+    assert!(Encoding::Latin1.encode("€").is_err());
+}
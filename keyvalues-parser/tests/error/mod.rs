@@ -0,0 +1,211 @@
+use keyvalues_parser::{
+    error::{Expected, Found, ParseError, ParseErrorKind, RenderError},
+    span::Location,
+    Parser, Vdf,
+};
+use pretty_assertions::assert_eq;
+
+fn parse_err(text: &str) -> ParseError {
+    Parser::new().parse(text).unwrap_err()
+}
+
+fn position(err: &ParseError) -> (usize, usize, usize) {
+    (err.line(), err.column(), err.offset())
+}
+
+#[test]
+fn unclosed_object() {
+    let text = "outer\n{\n\tinner\n\t{\n\t\tkey value\n\t}\n";
+    let err = parse_err(text);
+    assert_eq!(
+        err.kind(),
+        &ParseErrorKind::UnclosedObject {
+            open: Location {
+                offset: 6,
+                line: 2,
+                column: 1
+            }
+        }
+    );
+    assert_eq!(position(&err), (7, 1, text.len()));
+    insta::assert_snapshot!(err);
+}
+
+#[test]
+fn unclosed_string() {
+    let err = parse_err("outer\n{\n\t\"key\" \"value\n}\n");
+    assert_eq!(err.kind(), &ParseErrorKind::UnclosedString);
+    assert_eq!(position(&err), (3, 8, 15));
+    insta::assert_snapshot!(err);
+}
+
+#[test]
+fn invalid_escape() {
+    let text = r#"outer { "path" "C:\Games" }"#;
+    let err = parse_err(text);
+    assert_eq!(
+        err.kind(),
+        &ParseErrorKind::InvalidEscape {
+            sequence: r"\G".to_owned()
+        }
+    );
+    assert_eq!(position(&err), (1, 19, 18));
+    assert_eq!(err.span().as_str(text), r"\G");
+    insta::assert_snapshot!(err);
+
+    // Fine when escapes aren't interpreted
+    Parser::new()
+        .literal_special_chars(true)
+        .parse(text)
+        .unwrap();
+}
+
+#[test]
+fn lenient_escapes() {
+    let text = r#"outer { "path" "C:\Games\n\Portal\\" "quote" "\"\q\"" }"#;
+    let parser = Parser::new().lenient_escapes(true);
+    let warned = parser.parse_with_warnings(text).unwrap();
+    let obj = warned.vdf.value.get_obj().unwrap();
+    assert_eq!(obj["path"][0].get_str(), Some("C:\\Games\n\\Portal\\"));
+    assert_eq!(obj["quote"][0].get_str(), Some("\"\\q\""));
+
+    let warnings: Vec<_> = warned
+        .warnings
+        .iter()
+        .map(|warning| (warning.span().as_str(text), warning.column()))
+        .collect();
+    assert_eq!(warnings, [(r"\G", 19), (r"\P", 27), (r"\q", 49)]);
+    assert_eq!(parser.parse(text).unwrap(), warned.vdf);
+
+    // Other errors are still errors
+    let err = parser.parse_with_warnings(r#"outer { "\q" }"#).unwrap_err();
+    assert!(matches!(err.kind(), ParseErrorKind::Unexpected { .. }));
+
+    // and strict parsing doesn't warn about anything
+    let warned = Parser::new().parse_with_warnings(r#"a "\n""#).unwrap();
+    assert!(warned.warnings.is_empty());
+}
+
+#[test]
+fn missing_value() {
+    let err = parse_err("outer\n{\n\tkey value\n\tlonely }");
+    assert_eq!(
+        err.kind(),
+        &ParseErrorKind::Unexpected {
+            found: Found::ObjClose,
+            expected: vec![Expected::Value],
+        }
+    );
+    assert_eq!(position(&err), (4, 9, 27));
+    insta::assert_snapshot!(err);
+
+    let err = parse_err("key");
+    assert_eq!(
+        err.kind().to_string(),
+        "expected a value, found end of input"
+    );
+    assert_eq!(position(&err), (1, 4, 3));
+}
+
+#[test]
+fn object_as_key() {
+    let err = parse_err("outer { { } }");
+    assert_eq!(
+        err.kind(),
+        &ParseErrorKind::Unexpected {
+            found: Found::ObjOpen,
+            expected: vec![Expected::Key, Expected::ObjClose],
+        }
+    );
+    insta::assert_snapshot!(err);
+}
+
+#[test]
+fn trailing_content() {
+    let err = parse_err("outer {}\nanother {}\n");
+    assert_eq!(
+        err.kind().to_string(),
+        "expected end of input, found a string"
+    );
+    assert_eq!(position(&err), (2, 1, 9));
+    insta::assert_snapshot!(err);
+
+    // A trailing null byte is still fine
+    Parser::new().parse("outer {}\n\0\n").unwrap();
+}
+
+#[test]
+fn empty_input() {
+    for text in ["", "  // just a comment\n"] {
+        let err = parse_err(text);
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::Unexpected {
+                found: Found::EndOfInput,
+                expected: vec![Expected::Key],
+            }
+        );
+        assert_eq!(err.offset(), text.len());
+    }
+}
+
+#[test]
+fn directive_without_root() {
+    let err = parse_err("#base \"file.vdf\"\n");
+    assert_eq!(err.kind().to_string(), "expected a key, found end of input");
+    assert_eq!(position(&err), (2, 1, 17));
+}
+
+#[test]
+fn snippet_keeps_tabs_and_unicode() {
+    let err = parse_err("outer\n{\n\t\"Ünïcödé\"\t}\n}");
+    assert_eq!(position(&err), (3, 12, 23));
+    assert_eq!(
+        err.snippet(),
+        "  |\n3 | \t\"Ünïcödé\"\t}\n  | \t         \t^"
+    );
+}
+
+#[test]
+fn crlf_line_endings() {
+    // Errors after the trimmed `\r` at the very end of the text
+    let err = parse_err("a\r");
+    assert_eq!(position(&err), (1, 3, 2));
+    assert_eq!(err.snippet(), "  |\n1 | a\n  |  ^");
+
+    let err = parse_err("a {\r");
+    assert_eq!(position(&err), (1, 5, 4));
+    assert_eq!(err.snippet(), "  |\n1 | a {\n  |    ^");
+
+    // and at the end of a line that's followed by more text
+    let err = parse_err("outer\r\n{\r\n\tkey \"value\r\n}\r\n");
+    assert_eq!(err.kind(), &ParseErrorKind::UnclosedString);
+    assert_eq!(position(&err), (3, 6, 15));
+    assert_eq!(err.snippet(), "  |\n3 | \tkey \"value\n  | \t    ^^^^^^");
+}
+
+#[test]
+fn all_parsers_agree() {
+    let text = "outer\n{\n\tkey \"unclosed\n";
+    let expected = parse_err(text);
+    assert_eq!(Vdf::parse(text).unwrap_err(), expected);
+    assert_eq!(
+        keyvalues_parser::ordered::Vdf::parse(text).unwrap_err(),
+        expected
+    );
+    assert_eq!(
+        keyvalues_parser::edit::Document::parse(text).unwrap_err(),
+        expected
+    );
+}
+
+#[test]
+fn render_error() {
+    let vdf = Vdf::parse(r#"key "quote\"d""#).unwrap();
+    let err = vdf.render_raw(&mut String::new()).unwrap_err();
+    assert_eq!(err, RenderError::InvalidRawChar { invalid_char: '"' });
+    assert_eq!(
+        err.to_string(),
+        "Encountered invalid character in raw string: '\"'"
+    );
+}
@@ -0,0 +1,235 @@
+use std::{borrow::Cow, fs, path::Path};
+
+use keyvalues_parser::{
+    error::{ParseError, ParseErrorKind},
+    events::Event,
+    Obj, Parser, PartialVdf, Value,
+};
+use pretty_assertions::assert_eq;
+
+/// Rebuilds a [`PartialVdf`] from the events without any recursion
+fn build<'text>(parser: &Parser, text: &'text str) -> Result<PartialVdf<'text>, ParseError> {
+    let mut bases = Vec::new();
+    let mut includes = Vec::new();
+    // Each open object along with the key that it belongs to
+    let mut stack: Vec<(Cow<str>, Obj)> = Vec::new();
+    let mut key = None;
+    let mut root = None;
+    for event in parser.events(text) {
+        let (k, value) = match event? {
+            Event::Base { path, .. } => {
+                bases.push(path);
+                continue;
+            }
+            Event::Include { path, .. } => {
+                includes.push(path);
+                continue;
+            }
+            Event::Key { key: k, .. } => {
+                key = Some(k);
+                continue;
+            }
+            Event::ObjBegin { .. } => {
+                stack.push((key.take().unwrap(), Obj::new()));
+                continue;
+            }
+            Event::Conditional { .. } => continue,
+            Event::Str { value, .. } => (key.take().unwrap(), Value::Str(value)),
+            Event::ObjEnd { .. } => {
+                let (k, obj) = stack.pop().unwrap();
+                (k, Value::Obj(obj))
+            }
+        };
+        match stack.last_mut() {
+            Some((_, obj)) => obj.entry(k).or_default().push(value),
+            None => root = Some((k, value)),
+        }
+    }
+
+    let (key, value) = root.unwrap();
+    Ok(PartialVdf {
+        key,
+        value,
+        bases,
+        includes,
+        spans: None,
+    })
+}
+
+fn kinds(text: &str) -> Vec<&'static str> {
+    Parser::new()
+        .events(text)
+        .map(|event| match event.unwrap() {
+            Event::Base { .. } => "base",
+            Event::Include { .. } => "include",
+            Event::Key { .. } => "key",
+            Event::Str { .. } => "str",
+            Event::ObjBegin { .. } => "begin",
+            Event::ObjEnd { .. } => "end",
+            Event::Conditional { .. } => "cond",
+        })
+        .collect()
+}
+
+#[test]
+fn assets_match_parse() {
+    let assets = Path::new("tests").join("assets");
+    for entry in fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |ext| ext != "vdf") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+
+        for literal in [false, true] {
+            let parser = Parser::new().literal_special_chars(literal);
+            assert_eq!(build(&parser, &text), parser.parse(&text), "{path:?}");
+        }
+    }
+}
+
+#[test]
+fn lenient_escapes_match_parse() {
+    let text = r#"outer { "path" "C:\Games\Portal\" "fine" "\n\\" }"#;
+    for lenient in [false, true] {
+        let parser = Parser::new().lenient_escapes(lenient);
+        assert_eq!(build(&parser, text), parser.parse(text), "{lenient}");
+    }
+}
+
+#[test]
+fn event_order() {
+    let text = "#base base.vdf\n#include \"inc.vdf\"\nouter { a 1 [$WIN32] b { } [!$X360] }";
+    assert_eq!(
+        kinds(text),
+        [
+            "base", "include", "key", "begin", "key", "str", "cond", "key", "begin", "end", "cond",
+            "end"
+        ]
+    );
+    assert_eq!(kinds("key value [$WIN32]"), ["key", "str", "cond"]);
+    // A directive without a path is the top-level key
+    assert_eq!(kinds("#base {}"), ["key", "begin", "end"]);
+}
+
+#[test]
+fn spans() {
+    let text = "#base\t\"base.vdf\"\n\"outer\"\n{\n\tkey \"multi\nline\" [$WIN32]\n}\n";
+    let spans: Vec<_> = Parser::new()
+        .events(text)
+        .map(|event| {
+            let span = event.unwrap().span();
+            (span.as_str(text), span.start.line, span.start.column)
+        })
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            ("\"base.vdf\"", 1, 7),
+            ("\"outer\"", 2, 1),
+            ("{", 3, 1),
+            ("key", 4, 2),
+            ("\"multi\nline\"", 4, 6),
+            ("[$WIN32]", 5, 7),
+            ("}", 6, 1),
+        ]
+    );
+
+    let spans = Parser::new().with_spans(true).parse(text).unwrap().spans;
+    let end = spans.unwrap().value.get_obj().unwrap().close;
+    let last = Parser::new().events(text).last().unwrap().unwrap();
+    assert_eq!(last.span(), end);
+}
+
+#[test]
+fn strings_are_borrowed_without_escapes() {
+    let events: Vec<_> = Parser::new()
+        .events(r#"key { a "plain" b "esc\taped" }"#)
+        .map(Result::unwrap)
+        .collect();
+    let strs: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Str { value, .. } => Some(matches!(value, Cow::Borrowed(_))),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(strs, [true, false]);
+}
+
+#[test]
+fn errors_match_parse() {
+    let texts = [
+        "",
+        "outer {",
+        "outer { key }",
+        "outer { { } }",
+        "outer {} trailing",
+        "outer {}\0",
+        "outer {}\0 trailing",
+        "} outer {}",
+        r#"outer { key "bad \escape" }"#,
+        "outer { key \"unclosed }\n",
+    ];
+    let parsers = [
+        Parser::new(),
+        Parser::new().literal_special_chars(true),
+        Parser::new().max_depth(0),
+        Parser::new().max_str_len(3),
+        Parser::new().max_pairs(1),
+        Parser::new().max_input_len(7),
+    ];
+    for text in texts {
+        for parser in &parsers {
+            let from_events = parser.events(text).find_map(Result::err);
+            assert_eq!(from_events, parser.parse(text).err(), "{text:?} {parser:?}");
+        }
+    }
+
+    // Iteration stops after an error
+    let mut reader = Parser::new().events("a { b }");
+    let last = reader.by_ref().last().unwrap();
+    assert!(last.is_err());
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn skipping() {
+    let text = "outer { skipped { a { b c } } kept 1 str_skipped 2 [$WIN32] rest { x y z w } }";
+    let mut reader = Parser::new().events(text);
+    let mut keys = Vec::new();
+    while let Some(event) = reader.next() {
+        match event.unwrap() {
+            Event::Key { key, .. } => {
+                if key.ends_with("skipped") {
+                    reader.skip_value().unwrap();
+                } else {
+                    keys.push(key);
+                }
+            }
+            Event::Str { value, .. } if value == "y" => reader.skip_obj().unwrap(),
+            Event::Conditional { .. } => keys.push(Cow::from("cond")),
+            _ => {}
+        }
+    }
+    assert_eq!(keys, ["outer", "kept", "cond", "rest", "x"]);
+
+    // Skipped text is still checked for errors
+    let mut reader = Parser::new().events(r#"outer { skipped { a "\q" } }"#);
+    reader.nth(2).unwrap().unwrap();
+    let err = reader.skip_value().unwrap_err();
+    assert!(matches!(err.kind(), ParseErrorKind::InvalidEscape { .. }));
+}
+
+#[test]
+fn deep_nesting_doesnt_overflow() {
+    let depth = 1_000_000;
+    let text = format!("{}{}", "k { ".repeat(depth), "} ".repeat(depth));
+    let mut reader = Parser::new().events(&text);
+    let mut max_depth = 0;
+    while let Some(event) = reader.next() {
+        event.unwrap();
+        max_depth = max_depth.max(reader.depth());
+    }
+    assert_eq!(max_depth, depth);
+}
@@ -5,6 +5,7 @@ const ONE_BASE_PER_LINE: &str = r##"
 key val
 "##;
 
+// `Parser::strict()` rejects this, but the default parser should too
 #[test]
 #[should_panic] // FIXME(cosmic): well it shouldn't parse right, but it currently does...
 fn one_base_per_line() {
@@ -0,0 +1,130 @@
+use std::{fs, path::Path};
+
+use keyvalues_parser::text::lex::{Lexer, TokenKind};
+use pretty_assertions::assert_eq;
+
+fn significant(lexer: Lexer<'_>, text: &str) -> Vec<(TokenKind, String)> {
+    lexer
+        .filter(|token| !token.kind.is_trivia())
+        .map(|token| (token.kind, token.span.as_str(text).to_owned()))
+        .collect()
+}
+
+fn lex(text: &str) -> Vec<(TokenKind, String)> {
+    significant(Lexer::new(text), text)
+}
+
+fn tokens<const N: usize>(expected: [(TokenKind, &str); N]) -> Vec<(TokenKind, String)> {
+    expected
+        .into_iter()
+        .map(|(kind, s)| (kind, s.to_owned()))
+        .collect()
+}
+
+#[test]
+fn assets_are_lossless() {
+    let assets = Path::new("tests").join("assets");
+    for entry in fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |ext| ext != "vdf") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+
+        for literal in [false, true] {
+            let mut end = 0;
+            for token in Lexer::new(&text).literal_special_chars(literal) {
+                assert_eq!(token.span.start.offset, end, "{path:?}");
+                end = token.span.end.offset;
+            }
+            assert_eq!(end, text.len(), "{path:?}");
+        }
+    }
+}
+
+#[test]
+fn trivia() {
+    let text = "key // comment\r\n\tvalue";
+    let kinds: Vec<_> = Lexer::new(text).map(|token| token.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::UnquotedStr,
+            TokenKind::Whitespace,
+            TokenKind::Comment,
+            TokenKind::Whitespace,
+            TokenKind::UnquotedStr,
+        ]
+    );
+}
+
+#[test]
+fn directives() {
+    use TokenKind::*;
+
+    assert_eq!(
+        lex("#base \"C:\\base.vdf\"\n#includeinc.vdf\n#base {}"),
+        tokens([
+            (Directive, "#base"),
+            (QuotedStr, "\"C:\\base.vdf\""),
+            (Directive, "#include"),
+            (UnquotedStr, "inc.vdf"),
+            // No path means that `#base` is the top-level key
+            (UnquotedStr, "#base"),
+            (ObjOpen, "{"),
+            (ObjClose, "}"),
+        ])
+    );
+
+    // Directives can only come before the top-level pair
+    assert_eq!(
+        lex("key { #base file }"),
+        tokens([
+            (UnquotedStr, "key"),
+            (ObjOpen, "{"),
+            (UnquotedStr, "#base"),
+            (UnquotedStr, "file"),
+            (ObjClose, "}"),
+        ])
+    );
+}
+
+#[test]
+fn conditionals() {
+    use TokenKind::*;
+
+    assert_eq!(
+        lex("key { a b [$X && !$Y] [$Z] c { } [$W] }"),
+        tokens([
+            (UnquotedStr, "key"),
+            (ObjOpen, "{"),
+            (UnquotedStr, "a"),
+            (UnquotedStr, "b"),
+            (Conditional, "[$X && !$Y]"),
+            // Only values get conditionals, so this is a key
+            (UnquotedStr, "[$Z]"),
+            (UnquotedStr, "c"),
+            (ObjOpen, "{"),
+            (ObjClose, "}"),
+            (Conditional, "[$W]"),
+            (ObjClose, "}"),
+        ])
+    );
+}
+
+#[test]
+fn errors() {
+    use TokenKind::*;
+
+    let text = "key {\n\tinner \"unterminated\n}";
+    let tokens: Vec<_> = Lexer::new(text).collect();
+    let last = tokens.last().unwrap();
+    assert_eq!(last.kind, UnterminatedStr);
+    assert!(last.kind.is_error());
+    assert_eq!(last.span.as_str(text), "\"unterminated\n}");
+    assert_eq!((last.span.start.line, last.span.start.column), (2, 8));
+    assert_eq!((last.span.end.line, last.span.end.column), (3, 2));
+
+    // Unbalanced braces are left for the parser to complain about
+    assert_eq!(lex("} {"), self::tokens([(ObjClose, "}"), (ObjOpen, "{")]));
+}
@@ -0,0 +1,114 @@
+use keyvalues_parser::{error::ParseErrorKind, Parser, Vdf};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn max_depth() {
+    let parser = Parser::new().max_depth(3);
+    let at_limit = "a { b { c { d e } } }";
+    parser.parse(at_limit).unwrap();
+
+    let text = "a { b { c { d { e f } } } }";
+    let err = parser.parse(text).unwrap_err();
+    assert_eq!(err.kind(), &ParseErrorKind::DepthLimitExceeded { limit: 3 });
+    assert_eq!(err.span().as_str(text), "{");
+    assert_eq!(err.column(), 15);
+    assert_eq!(
+        err.kind().to_string(),
+        "objects are nested deeper than the limit of 3"
+    );
+    parser.parse_ordered(text).unwrap_err();
+}
+
+#[test]
+fn deep_nesting_doesnt_overflow() {
+    let text = format!("{}{}", "k { ".repeat(1_000_000), "} ".repeat(1_000_000));
+    let parser = Parser::new().max_depth(64);
+
+    let err = parser.parse(&text).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ParseErrorKind::DepthLimitExceeded { limit: 64 }
+    );
+    assert_eq!(err.offset(), 64 * 4 + 2);
+
+    let recovered = parser.parse_recovering(&text);
+    assert_eq!(recovered.errors.len(), 1);
+}
+
+#[test]
+fn recovering_skips_objects_that_are_too_deep() {
+    let text = "a { b { c { d e } } f g }";
+    let recovered = Parser::new().max_depth(1).parse_recovering(text);
+    assert_eq!(recovered.errors.len(), 1);
+    assert_eq!(recovered.errors[0].span().range(), 6..7);
+    assert_eq!(
+        Vdf::from(recovered.vdf),
+        Vdf::parse("a { b {} f g }").unwrap()
+    );
+}
+
+#[test]
+fn max_str_len() {
+    let parser = Parser::new().max_str_len(5);
+    parser.parse(r#""12345" { 12345 "12345" }"#).unwrap();
+    // Escapes are counted before being replaced
+    parser.parse(r#"key "\"\"\"""#).unwrap_err();
+
+    let text = r#"key { inner "123456" }"#;
+    let err = parser.parse(text).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ParseErrorKind::StrLenLimitExceeded { limit: 5 }
+    );
+    assert_eq!(err.span().as_str(text), r#""123456""#);
+
+    // Every string that's too long gets reported when recovering
+    let recovered = parser.parse_recovering("too_long { still_too_long value }");
+    assert_eq!(recovered.errors.len(), 2);
+    assert_eq!(recovered.vdf.key, "too_long");
+}
+
+#[test]
+fn max_pairs() {
+    let parser = Parser::new().max_pairs(3);
+    parser.parse("a { b c d { } }").unwrap();
+
+    let text = "a { b c d { e f } }";
+    let err = parser.parse(text).unwrap_err();
+    assert_eq!(err.kind(), &ParseErrorKind::PairLimitExceeded { limit: 3 });
+    assert_eq!(err.span().as_str(text), "e");
+
+    // Parsing stops at the limit when recovering
+    let recovered = parser.parse_recovering("a { b c d { e f } g { h i } }");
+    assert_eq!(recovered.errors.len(), 1);
+    assert_eq!(
+        Vdf::from(recovered.vdf),
+        Vdf::parse("a { b c d { } }").unwrap()
+    );
+}
+
+#[test]
+fn max_input_len() {
+    let parser = Parser::new().max_input_len(8);
+    parser.parse("key val").unwrap();
+    parser.parse("key valu").unwrap();
+
+    let err = parser.parse("key value").unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ParseErrorKind::InputLenLimitExceeded { limit: 8 }
+    );
+    assert_eq!(err.offset(), 8);
+
+    // The limit can land in the middle of a multi-byte char
+    let err = parser.parse("key valäue").unwrap_err();
+    assert_eq!(err.offset(), 7);
+    assert_eq!(
+        err.to_string(),
+        "1:8: input is larger than the limit of 8 bytes\n  |\n1 | key val\n  |        ^"
+    );
+
+    let recovered = parser.parse_recovering("key value");
+    assert_eq!(recovered.errors.len(), 1);
+    assert_eq!(recovered.vdf.key, "");
+}
@@ -0,0 +1,115 @@
+use std::{borrow::Cow, fs, path::Path};
+
+use keyvalues_parser::{
+    error::RenderError,
+    ordered::{self, Obj, Value},
+    Parser, PartialVdf, Vdf,
+};
+use pretty_assertions::assert_eq;
+
+const INTERLEAVED: &str = r#""Outer"
+{
+	"b"	"1"
+	"a"	"2"
+	"b"	"3"
+	"nested"
+	{
+		"z"	"last"
+		"y"	"first"
+	}
+	"a"	"4"
+}
+"#;
+
+fn read_asset_file(file_name: &str) -> String {
+    fs::read_to_string(Path::new("tests").join("assets").join(file_name)).unwrap()
+}
+
+#[test]
+fn interleaved_duplicates_round_trip() {
+    let vdf = Parser::new().parse_ordered(INTERLEAVED).unwrap();
+    assert_eq!(vdf.to_string(), INTERLEAVED);
+
+    let obj = vdf.value.get_obj().unwrap();
+    assert_eq!(
+        obj.keys().collect::<Vec<_>>(),
+        ["b", "a", "b", "nested", "a"]
+    );
+    let a_values: Vec<_> = obj.get_all("a").filter_map(Value::get_str).collect();
+    assert_eq!(a_values, ["2", "4"]);
+    assert_eq!(obj.get("b").and_then(Value::get_str), Some("1"));
+}
+
+#[test]
+fn matches_sorted_parse() {
+    for file_name in [
+        "basic.vdf",
+        "comments.vdf",
+        "base_multiple.vdf",
+        "null_byte.vdf",
+    ] {
+        let vdf_text = read_asset_file(file_name);
+        let ordered = Parser::new().parse_ordered(&vdf_text).unwrap();
+        let sorted = Parser::new().parse(&vdf_text).unwrap();
+        assert_eq!(PartialVdf::from(ordered), sorted, "{file_name}");
+    }
+}
+
+#[test]
+fn raw_strings() {
+    let vdf_text = read_asset_file("raw_strings.vdf");
+    let vdf = ordered::Vdf::parse_raw(&vdf_text).unwrap();
+    assert_eq!(Vdf::from(vdf.clone()), Vdf::parse_raw(&vdf_text).unwrap());
+
+    let mut rendered = String::new();
+    vdf.render_raw(&mut rendered).unwrap();
+    assert_eq!(ordered::Vdf::parse_raw(&rendered).unwrap(), vdf);
+
+    let mut quoted = ordered::Vdf::new(Cow::from("key"), Value::Str(Cow::from("\"")));
+    assert_eq!(
+        quoted.render_raw(&mut String::new()),
+        Err(RenderError::InvalidRawChar { invalid_char: '"' })
+    );
+    quoted.value = Value::Obj(Obj::new());
+    quoted.render_raw(&mut String::new()).unwrap();
+}
+
+#[test]
+fn editing() {
+    let mut vdf = ordered::Vdf::parse(INTERLEAVED).unwrap();
+    let obj = vdf.value.get_mut_obj().unwrap();
+    let removed = obj.remove("b").unwrap();
+    assert_eq!(removed.value.get_str(), Some("1"));
+    *obj.get_mut("b").unwrap() = Value::Str(Cow::from("three"));
+    obj.push(Cow::from("0"), Value::Str(Cow::from("appended")));
+    obj.get_mut("nested")
+        .and_then(Value::get_mut_obj)
+        .unwrap()
+        .remove("z");
+
+    assert_eq!(
+        vdf.to_string(),
+        r#""Outer"
+{
+	"a"	"2"
+	"b"	"three"
+	"nested"
+	{
+		"y"	"first"
+	}
+	"a"	"4"
+	"0"	"appended"
+}
+"#
+    );
+}
+
+#[test]
+fn conversions() {
+    let sorted = Vdf::parse(INTERLEAVED).unwrap();
+    let ordered = ordered::Vdf::from(sorted.clone());
+    // Going through the sorted representation groups and sorts the keys
+    let keys: Vec<_> = ordered.value.get_obj().unwrap().keys().collect();
+    assert_eq!(keys, ["a", "a", "b", "b", "nested"]);
+    assert_eq!(Vdf::from(ordered), sorted);
+}
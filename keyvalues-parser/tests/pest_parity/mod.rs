@@ -0,0 +1,112 @@
+use std::{fs, path::Path};
+
+use keyvalues_parser::Parser;
+use pretty_assertions::assert_eq;
+
+fn parsers() -> impl Iterator<Item = Parser> {
+    [false, true].into_iter().flat_map(|literal| {
+        [false, true].into_iter().map(move |spans| {
+            Parser::new()
+                .literal_special_chars(literal)
+                .with_spans(spans)
+        })
+    })
+}
+
+#[test]
+fn assets_match_pest() {
+    let assets = Path::new("tests").join("assets");
+    for entry in fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |ext| ext != "vdf") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+
+        for parser in parsers() {
+            assert_eq!(
+                parser.parse(&text),
+                parser.parse_pest(&text),
+                "{path:?} {parser:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn directives_match_pest() {
+    let texts = [
+        "#base//c\n\"a.vdf\"\nk v",
+        "#include// note\ninc.vdf\nk v",
+        "#basefoo//c\nk v",
+        "#base//c\nk v",
+    ];
+    // The comment after the keyword isn't the path
+    let vdf = Parser::new().parse(texts[0]).unwrap();
+    assert_eq!(vdf.bases, ["a.vdf"]);
+
+    for text in texts {
+        for parser in parsers() {
+            assert_eq!(
+                parser.parse(text),
+                parser.parse_pest(text),
+                "{text:?} {parser:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn errors_match_pest() {
+    let texts = [
+        "",
+        "outer {",
+        "outer { key }",
+        "outer { { } }",
+        "outer {} trailing",
+        "} outer {}",
+        "outer {}\0 trailing",
+        r#"outer { key "bad \escape" }"#,
+        "outer { key \"unclosed }\n",
+        "#base",
+        "#base \"unclosed",
+        "key value [$WIN32",
+        "#base \"x\" {",
+        "a { b { c d } e",
+        "outer {\n    \"unclosed\n}",
+        "outer {\n    inner {\n        key value\n    }\n",
+        "#base a.vdf\n#include \"b.vdf\"\nouter { key \"bad \\z\" }",
+    ];
+    for text in texts {
+        for parser in parsers() {
+            assert_eq!(
+                parser.parse(text),
+                parser.parse_pest(text),
+                "{text:?} {parser:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn limits_match_pest() {
+    let texts = [
+        "a { b { c d } e f g { h long } }",
+        "#base \"long/path.vdf\"\n\"a\" { \"b\" { \"key\" \"long \\\"value\\\"\" } }",
+    ];
+    let limits = [
+        Parser::new().max_depth(1),
+        Parser::new().max_str_len(0),
+        Parser::new().max_str_len(3),
+        Parser::new().max_pairs(2),
+        Parser::new().max_input_len(4),
+    ];
+    for text in texts {
+        for limit in &limits {
+            for parser in [limit.clone(), limit.clone().literal_special_chars(true)] {
+                let err = parser.parse(text).unwrap_err();
+                assert_eq!(Err(err), parser.parse_pest(text), "{text:?} {parser:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::{fs, path::Path};
+
+use keyvalues_parser::{
+    error::{Expected, Found, ParseErrorKind},
+    Parser, Vdf,
+};
+use pretty_assertions::assert_eq;
+
+fn kinds(text: &str) -> Vec<ParseErrorKind> {
+    Parser::new()
+        .parse_recovering(text)
+        .errors
+        .into_iter()
+        .map(|err| err.kind().to_owned())
+        .collect()
+}
+
+#[test]
+fn valid_text_matches_regular_parse() {
+    let assets = Path::new("tests").join("assets");
+    for entry in fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |ext| ext != "vdf") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+
+        for literal in [false, true] {
+            let parser = Parser::new()
+                .literal_special_chars(literal)
+                .with_spans(true);
+            let Ok(expected) = parser.parse(&text) else {
+                continue;
+            };
+            let recovered = parser.parse_recovering(&text);
+            assert!(recovered.is_ok(), "{path:?}: {:?}", recovered.errors);
+            assert_eq!(recovered.vdf, expected, "{path:?}");
+        }
+    }
+}
+
+#[test]
+fn first_error_matches_regular_parse() {
+    let texts = [
+        "",
+        "outer {",
+        "outer { key }",
+        "outer { { } }",
+        "outer {} trailing",
+        "} outer {}",
+        r#"outer { key "bad \escape" }"#,
+        "outer { key \"unclosed }\n",
+    ];
+    for text in texts {
+        let err = Parser::new().parse(text).unwrap_err();
+        let recovered = Parser::new().parse_recovering(text);
+        assert_eq!(recovered.errors.first(), Some(&err), "{text:?}");
+    }
+}
+
+#[test]
+fn reports_every_error() {
+    let text = r#""Outer Key"
+{
+	"First"	"Bad \escape"
+	"Lonely"
+	"Second"	"Fine"
+}
+"Extra" {}
+"#;
+    let recovered = Parser::new().parse_recovering(text);
+    let positions: Vec<_> = recovered
+        .errors
+        .iter()
+        .map(|err| (err.line(), err.column()))
+        .collect();
+    assert_eq!(positions, [(3, 15), (6, 1), (7, 1)]);
+
+    // `"Lonely"` grabs `"Second"` as its value which leaves `"Fine"` without one
+    let expected = Vdf::parse(
+        r#""Outer Key"
+        {
+            "First" "Bad \\escape"
+            "Lonely" "Second"
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(Vdf::from(recovered.vdf), expected);
+}
+
+#[test]
+fn unclosed_string_ends_at_the_line() {
+    let text = "outer\n{\n\tkey \"unclosed\n\tnext value\n}\n";
+    let recovered = Parser::new().parse_recovering(text);
+    assert_eq!(recovered.errors.len(), 1);
+    assert_eq!(recovered.errors[0].kind(), &ParseErrorKind::UnclosedString);
+    assert_eq!(recovered.errors[0].span().as_str(text), "\"unclosed");
+    assert_eq!(
+        Vdf::from(recovered.vdf),
+        Vdf::parse("outer { key unclosed next value }").unwrap()
+    );
+}
+
+#[test]
+fn unclosed_objects_close_at_the_end() {
+    let text = "outer\n{\n\tinner\n\t{\n\t\tkey value\n";
+    let recovered = Parser::new().with_spans(true).parse_recovering(text);
+    let opens: Vec<_> = recovered
+        .errors
+        .iter()
+        .map(|err| match err.kind() {
+            ParseErrorKind::UnclosedObject { open } => (open.line, open.column),
+            other => panic!("Unexpected error: {other:?}"),
+        })
+        .collect();
+    assert_eq!(opens, [(4, 2), (2, 1)]);
+    assert_eq!(
+        Vdf::from(recovered.vdf.clone()),
+        Vdf::parse("outer { inner { key value } }").unwrap()
+    );
+
+    // The missing braces get an empty span at the end of the text
+    let spans = recovered.vdf.spans.unwrap();
+    let close = spans.value.get_obj().unwrap().close;
+    assert!(close.is_empty());
+    assert_eq!(close.start.offset, text.len());
+}
+
+#[test]
+fn keyless_objects_are_skipped() {
+    assert_eq!(
+        kinds("outer { { key } key value }"),
+        [
+            ParseErrorKind::Unexpected {
+                found: Found::ObjOpen,
+                expected: vec![Expected::Key, Expected::ObjClose],
+            },
+            ParseErrorKind::Unexpected {
+                found: Found::ObjClose,
+                expected: vec![Expected::Value],
+            },
+        ]
+    );
+
+    let recovered = Parser::new().parse_recovering("outer { { a b } key value }");
+    assert_eq!(
+        Vdf::from(recovered.vdf),
+        Vdf::parse("outer { key value }").unwrap()
+    );
+}
+
+#[test]
+fn missing_top_level_pair() {
+    for text in ["", "// only a comment", "#base file.vdf"] {
+        let recovered = Parser::new().parse_recovering(text);
+        assert_eq!(recovered.errors.len(), 1, "{text:?}");
+        assert_eq!(recovered.vdf.key, "");
+        assert_eq!(recovered.vdf.value.get_obj().map(|obj| obj.len()), Some(0));
+    }
+
+    // Stray braces before the top-level pair
+    let recovered = Parser::new().parse_recovering("} } outer { key value }");
+    assert_eq!(recovered.errors.len(), 2);
+    assert_eq!(
+        Vdf::from(recovered.vdf),
+        Vdf::parse("outer { key value }").unwrap()
+    );
+
+    // A top-level object without a key
+    let recovered = Parser::new().parse_recovering("#base file.vdf\n{ key value }");
+    assert_eq!(recovered.errors.len(), 1);
+    assert_eq!(recovered.vdf.bases, ["file.vdf"]);
+    assert_eq!(
+        Vdf::from(recovered.vdf),
+        Vdf::parse(r#""" { key value }"#).unwrap()
+    );
+}
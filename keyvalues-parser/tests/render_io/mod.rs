@@ -0,0 +1,112 @@
+use std::{borrow::Cow, fs, io, path::Path};
+
+use keyvalues_parser::{error::RenderError, Indent, Obj, Parser, RenderOptions, Value, Vdf};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn assets_match_render() {
+    let assets = Path::new("tests").join("assets");
+    let mut num_checked = 0;
+    for entry in fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |ext| ext != "vdf") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        let Ok(vdf) = Parser::new().parse(&text) else {
+            continue;
+        };
+
+        let mut bytes = Vec::new();
+        vdf.render_to_io(&mut bytes).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            vdf.to_string(),
+            "{path:?}"
+        );
+
+        let ordered = Parser::new().parse_ordered(&text).unwrap();
+        let mut bytes = Vec::new();
+        ordered.render_to_io(&mut bytes).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            ordered.to_string(),
+            "{path:?}"
+        );
+        num_checked += 1;
+    }
+    assert!(num_checked > 5);
+}
+
+struct FailingWriter {
+    remaining: usize,
+}
+
+impl io::Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() > self.remaining {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "disk full"));
+        }
+        self.remaining -= buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn io_errors() {
+    let vdf = Vdf::parse("outer { inner value }").unwrap();
+    let err = vdf
+        .render_to_io(&mut FailingWriter { remaining: 4 })
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RenderError::Io {
+            kind: io::ErrorKind::WriteZero,
+            message: "disk full".to_owned(),
+        }
+    );
+    assert_eq!(err.to_string(), "Failed writing output Error: disk full");
+
+    vdf.render_to_io(&mut FailingWriter { remaining: 1_024 })
+        .unwrap();
+}
+
+fn nested(depth: usize, innermost: Value<'static>) -> Vdf<'static> {
+    let mut value = innermost;
+    for _ in 1..depth {
+        let mut obj = Obj::new();
+        obj.insert(Cow::from("a"), vec![value]);
+        value = Value::Obj(obj);
+    }
+    Vdf::new(Cow::from("a"), value)
+}
+
+#[test]
+fn deeply_nested() {
+    // Rendering doesn't recurse
+    let depth = 100_000;
+    let vdf = nested(depth, Value::Obj(Obj::new()));
+    // Indenting would make the output quadratic in size
+    let options = RenderOptions::new()
+        .indent(Indent::Spaces(0))
+        .brace_on_same_line(true);
+    let mut rendered = String::new();
+    vdf.render_with(&mut rendered, &options).unwrap();
+    assert_eq!(
+        rendered,
+        format!("{}{}", "\"a\" {\n".repeat(depth), "}\n".repeat(depth))
+    );
+
+    // and neither does checking for chars that can't be rendered raw
+    let quoted = nested(depth, Value::Str(Cow::from("\"")));
+    let err = quoted.render_raw(&mut String::new()).unwrap_err();
+    assert_eq!(err, RenderError::InvalidRawChar { invalid_char: '"' });
+
+    // Dropping the trees does recurse though, so leak them instead
+    std::mem::forget(vdf);
+    std::mem::forget(quoted);
+}
@@ -0,0 +1,305 @@
+use std::{fs, path::Path};
+
+use keyvalues_parser::{
+    error::{InvalidRawStr, RenderError},
+    Escaping, Indent, MultiVdf, Obj, Parser, RenderOptions, Value, Vdf,
+};
+use pretty_assertions::assert_eq;
+
+const MANIFEST: &str = r#"
+"AppState"
+{
+	"appid"		"620"
+	"name"		"Portal 2"
+	"LastOwner"		"76561197960287930"
+	"UserConfig"
+	{
+		"language"		"english"
+		"BetaKey"		""
+	}
+	"InstalledDepots" { "621" { "manifest" "7026443431217358155" "size" "2048" } }
+}
+"#;
+
+fn every_options() -> Vec<RenderOptions> {
+    let mut all = Vec::new();
+    let escapings = [Escaping::Escaped, Escaping::Raw, Escaping::Auto];
+    for (indent, escaping) in [Indent::Tabs, Indent::Spaces(2)]
+        .into_iter()
+        .flat_map(|indent| escapings.map(|escaping| (indent, escaping)))
+    {
+        for bits in 0..64 {
+            let flag = |bit: u8| bits & (1 << bit) != 0;
+            let options = RenderOptions::new()
+                .indent(indent)
+                .align_values(flag(0))
+                .crlf(flag(1))
+                .brace_on_same_line(flag(2))
+                .quote_only_when_needed(flag(3))
+                .blank_lines_between_blocks(flag(4))
+                .compact(flag(5))
+                .escaping(escaping);
+            all.push(options);
+        }
+    }
+    all
+}
+
+#[test]
+fn assets_round_trip() {
+    let assets = Path::new("tests").join("assets");
+    let mut num_checked = 0;
+    for entry in fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |ext| ext != "vdf") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        // Some of the assets are only valid with `Parser::literal_special_chars()`
+        let Ok(vdf) = Parser::new().parse(&text) else {
+            continue;
+        };
+
+        let raw = vdf.validate_raw().is_empty();
+        for options in every_options() {
+            let mut rendered = String::new();
+            let result = vdf.render_with(&mut rendered, &options);
+            // Auto picks raw when it can
+            let parser = if options == options.escaping(Escaping::Escaped) {
+                Parser::new()
+            } else if raw {
+                Parser::new().literal_special_chars(true)
+            } else if options == options.escaping(Escaping::Raw) {
+                assert!(result.is_err());
+                continue;
+            } else {
+                Parser::new()
+            };
+            result.unwrap();
+            let reparsed = parser.parse(&rendered).unwrap();
+            assert_eq!(vdf, reparsed, "{path:?} with {options:?}");
+        }
+        num_checked += 1;
+    }
+    assert!(num_checked > 5);
+}
+
+#[test]
+fn defaults_match_render() {
+    let vdf = Parser::new().parse(MANIFEST).unwrap();
+    let vdf = Vdf::from(vdf);
+    let mut rendered = String::new();
+    vdf.render_with(&mut rendered, &RenderOptions::default())
+        .unwrap();
+    assert_eq!(rendered, vdf.to_string());
+}
+
+#[test]
+fn valve_style() {
+    let vdf = Parser::new().parse(MANIFEST).unwrap();
+    let mut rendered = String::new();
+    let options = RenderOptions::new()
+        .align_values(true)
+        .blank_lines_between_blocks(true);
+    vdf.render_with(&mut rendered, &options).unwrap();
+    insta::assert_snapshot!(rendered);
+}
+
+#[test]
+fn spaces_same_line_unquoted() {
+    let vdf = Parser::new().parse(MANIFEST).unwrap();
+    let mut rendered = String::new();
+    let options = RenderOptions::new()
+        .indent(Indent::Spaces(4))
+        .align_values(true)
+        .brace_on_same_line(true)
+        .quote_only_when_needed(true);
+    vdf.render_with(&mut rendered, &options).unwrap();
+    insta::assert_snapshot!(rendered);
+}
+
+#[test]
+fn crlf() {
+    let vdf = Parser::new().parse(MANIFEST).unwrap();
+    let mut rendered = String::new();
+    vdf.render_with(&mut rendered, &RenderOptions::new().crlf(true))
+        .unwrap();
+    assert!(rendered.ends_with("}\r\n"));
+    assert!(!rendered.replace("\r\n", "").contains('\n'));
+}
+
+#[test]
+fn quoting() {
+    let options = RenderOptions::new().quote_only_when_needed(true);
+    let render = |s: &str| {
+        let mut rendered = String::new();
+        Value::Str(s.into())
+            .render_with(&mut rendered, &options)
+            .unwrap();
+        rendered
+    };
+
+    assert_eq!(render("plain"), "plain");
+    assert_eq!(render(r"C:\Games"), r"C:\Games");
+    for needs_quotes in [
+        "",
+        "two words",
+        "{",
+        "//comment",
+        "#base",
+        "[$WIN32]",
+        "tab\t",
+    ] {
+        assert!(render(needs_quotes).starts_with('"'), "{needs_quotes:?}");
+    }
+}
+
+#[test]
+fn tab_stops() {
+    let vdf = Parser::new()
+        .parse("obj { a 1 abcdefg 2 abcdefgh 3 }")
+        .unwrap();
+    let render = |tab_width| {
+        let mut rendered = String::new();
+        let options = RenderOptions::new()
+            .align_values(true)
+            .tab_width(tab_width)
+            .quote_only_when_needed(true);
+        vdf.render_with(&mut rendered, &options).unwrap();
+        rendered
+    };
+
+    assert_eq!(
+        render(4),
+        "obj\n{\n\ta\t\t\t1\n\tabcdefg\t\t2\n\tabcdefgh\t3\n}\n"
+    );
+    assert_eq!(
+        render(8),
+        "obj\n{\n\ta\t\t1\n\tabcdefg\t\t2\n\tabcdefgh\t3\n}\n"
+    );
+}
+
+#[test]
+fn compact() {
+    let vdf = Parser::new().parse(MANIFEST).unwrap();
+    let mut rendered = String::new();
+    let options = RenderOptions::new().compact(true);
+    vdf.render_with(&mut rendered, &options).unwrap();
+    insta::assert_snapshot!(rendered);
+    assert_eq!(Parser::new().strict(true).parse(&rendered).unwrap(), vdf);
+
+    // Strings that need quotes or a separator to survive a round trip
+    let tricky = [
+        "",
+        " ",
+        "a b",
+        "{",
+        "}",
+        "//",
+        "a//b",
+        "#base",
+        "[$WIN32]",
+        "a[$WIN32]",
+        r"C:\",
+        "\"",
+        "\n",
+        "é",
+    ];
+    let mut obj = Obj::new();
+    for (i, key) in tricky.iter().enumerate() {
+        let values = tricky[i..].iter().map(|&s| Value::Str(s.into())).collect();
+        obj.insert((*key).into(), values);
+        let mut inner = Obj::new();
+        inner.insert((*key).into(), vec![Value::Obj(Obj::new())]);
+        obj.entry(format!("obj{i}").into())
+            .or_default()
+            .push(Value::Obj(inner));
+    }
+    let vdf = Vdf::new("root".into(), Value::Obj(obj));
+    let mut rendered = String::new();
+    vdf.render_with(&mut rendered, &options).unwrap();
+    assert!(!rendered.contains('\n'));
+    let strict = Parser::new().strict(true);
+    assert_eq!(Vdf::from(strict.parse(&rendered).unwrap()), vdf);
+
+    // Directives each get their own line since strict parsing requires it
+    let text = "#base a.vdf\n#base b.vdf\n#include c.vdf\nroot { key value }";
+    let partial = Parser::new().parse(text).unwrap();
+    let mut rendered = String::new();
+    partial.render_with(&mut rendered, &options).unwrap();
+    assert_eq!(
+        rendered,
+        "#base\"a.vdf\"\n#base\"b.vdf\"\n#include\"c.vdf\"\nroot{key value}"
+    );
+    assert_eq!(strict.parse(&rendered).unwrap(), partial);
+}
+
+#[test]
+fn escaping() {
+    let vdf = Parser::new()
+        .parse(r#"paths { game "C:\\Games" quoted "\"Portal\"" }"#)
+        .unwrap();
+    let render = |vdf: &Vdf, escaping| {
+        let mut rendered = String::new();
+        let options = RenderOptions::new().escaping(escaping);
+        vdf.render_with(&mut rendered, &options).map(|_| rendered)
+    };
+
+    let mut vdf = Vdf::from(vdf);
+    assert_eq!(
+        render(&vdf, Escaping::Raw),
+        Err(RenderError::InvalidRawChar { invalid_char: '"' })
+    );
+    let escaped =
+        "\"paths\"\n{\n\t\"game\"\t\"C:\\\\Games\"\n\t\"quoted\"\t\"\\\"Portal\\\"\"\n}\n";
+    assert_eq!(render(&vdf, Escaping::Escaped).unwrap(), escaped);
+    assert_eq!(render(&vdf, Escaping::Auto).unwrap(), escaped);
+
+    vdf.value.get_mut_obj().unwrap().remove("quoted");
+    let raw = "\"paths\"\n{\n\t\"game\"\t\"C:\\Games\"\n}\n";
+    assert_eq!(render(&vdf, Escaping::Raw).unwrap(), raw);
+    assert_eq!(render(&vdf, Escaping::Auto).unwrap(), raw);
+}
+
+#[test]
+fn validate_raw() {
+    let vdf_text = r#"
+    "root"
+    {
+        "fine"    "C:\\Games"
+        "a\"b"   "fine"
+        "dupe"    "\"one\""
+        "dupe"    "\"two\""
+        "nested"  { "\"both\"" "\"both\"" }
+    }
+    "#;
+    let invalid = |path: &[&str], in_key| InvalidRawStr {
+        path: path.iter().map(|&key| key.to_owned()).collect(),
+        in_key,
+        invalid_char: '"',
+    };
+    let expected = [
+        invalid(&["root", "a\"b"], true),
+        invalid(&["root", "dupe"], false),
+        invalid(&["root", "dupe"], false),
+        invalid(&["root", "nested", "\"both\""], true),
+        invalid(&["root", "nested", "\"both\""], false),
+    ];
+
+    let vdf = Parser::new().parse(vdf_text).unwrap();
+    assert_eq!(vdf.validate_raw(), expected);
+    assert_eq!(Vdf::from(vdf).validate_raw(), expected);
+    assert_eq!(
+        Parser::new()
+            .parse_ordered(vdf_text)
+            .unwrap()
+            .validate_raw(),
+        expected
+    );
+    let doc_text = format!("{vdf_text} other {{ fine fine }} {vdf_text}");
+    let doc = MultiVdf::parse(&doc_text).unwrap();
+    assert_eq!(doc.validate_raw(), [expected.clone(), expected].concat());
+
+    let fine = Parser::new().parse("root { fine { fine fine } }").unwrap();
+    assert!(fine.validate_raw().is_empty());
+}
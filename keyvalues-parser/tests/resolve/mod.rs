@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+
+use keyvalues_parser::{
+    resolve::{MemoryFileLoader, ResolveError, Resolver, StdFileLoader},
+    Parser, Vdf,
+};
+use pretty_assertions::assert_eq;
+
+fn memory_loader(files: &[(&str, &str)]) -> MemoryFileLoader {
+    files.iter().copied().collect()
+}
+
+#[test]
+fn bases_merge_as_defaults() {
+    let loader = memory_loader(&[
+        (
+            "main.vdf",
+            "#base base.vdf\nroot { a 1 obj { x 1 } str str }",
+        ),
+        (
+            "base.vdf",
+            "other_key { a 2 b 2 obj { x 2 y 2 } str { z 2 } }",
+        ),
+    ]);
+    let resolved = Resolver::new(loader).resolve("main.vdf").unwrap();
+    assert_eq!(
+        resolved.vdf,
+        Vdf::parse("root { a 1 b 2 obj { x 1 y 2 } str str }").unwrap()
+    );
+}
+
+#[test]
+fn includes_append() {
+    let loader = memory_loader(&[
+        ("main.vdf", "#include inc.vdf\nroot { a 1 obj { x 1 } }"),
+        ("inc.vdf", "root { a 2 obj { y 2 } }"),
+    ]);
+    let resolved = Resolver::new(loader).resolve("main.vdf").unwrap();
+    assert_eq!(
+        resolved.vdf,
+        Vdf::parse("root { a 1 a 2 obj { x 1 } obj { y 2 } }").unwrap()
+    );
+    assert_eq!(
+        resolved.provenance.source_at(&[("a", 1)]),
+        Some(Path::new("inc.vdf"))
+    );
+}
+
+#[test]
+fn nested_relative_paths() {
+    let loader = memory_loader(&[
+        (
+            "game/ui/main.res",
+            r"#base ..\shared\base.res
+            root { from_main 1 }",
+        ),
+        (
+            "game/shared/base.res",
+            "#base ./deeper/base.res\nroot { from_base 1 }",
+        ),
+        (
+            "game/shared/deeper/base.res",
+            "root { from_deeper 1 from_base 2 }",
+        ),
+    ]);
+    let resolved = Resolver::new(loader).resolve("game/ui/main.res").unwrap();
+    assert_eq!(
+        resolved.vdf,
+        Vdf::parse("root { from_main 1 from_base 1 from_deeper 1 }").unwrap()
+    );
+
+    let provenance = resolved.provenance;
+    assert_eq!(
+        provenance.files(),
+        [
+            PathBuf::from("game/ui/main.res"),
+            PathBuf::from("game/shared/base.res"),
+            PathBuf::from("game/shared/deeper/base.res"),
+        ]
+    );
+    assert_eq!(provenance.source(&[]), Some(Path::new("game/ui/main.res")));
+    assert_eq!(
+        provenance.source(&["from_base"]),
+        Some(Path::new("game/shared/base.res"))
+    );
+    assert_eq!(
+        provenance.source(&["from_deeper"]),
+        Some(Path::new("game/shared/deeper/base.res"))
+    );
+    assert_eq!(provenance.source(&["missing"]), None);
+    assert_eq!(provenance.iter().count(), 4);
+}
+
+#[test]
+fn cycles_are_detected() {
+    let loader = memory_loader(&[
+        ("a.vdf", "#base b.vdf\nroot {}"),
+        ("b.vdf", "#include ./a.vdf\nroot {}"),
+    ]);
+    let err = Resolver::new(loader).resolve("a.vdf").unwrap_err();
+    match &err {
+        ResolveError::Cycle { chain } => assert_eq!(
+            chain,
+            &[
+                PathBuf::from("a.vdf"),
+                PathBuf::from("b.vdf"),
+                PathBuf::from("a.vdf"),
+            ]
+        ),
+        other => panic!("Unexpected error: {other:?}"),
+    }
+    assert_eq!(
+        err.to_string(),
+        "Encountered a cycle of files: a.vdf -> b.vdf -> a.vdf"
+    );
+
+    // Referencing the same file from multiple places is fine
+    let loader = memory_loader(&[
+        ("a.vdf", "#base b.vdf\n#base c.vdf\nroot {}"),
+        ("b.vdf", "#base c.vdf\nroot { b 1 }"),
+        ("c.vdf", "root { c 1 }"),
+    ]);
+    let resolved = Resolver::new(loader).resolve("a.vdf").unwrap();
+    assert_eq!(resolved.vdf, Vdf::parse("root { b 1 c 1 }").unwrap());
+    assert_eq!(resolved.provenance.files().len(), 3);
+}
+
+#[test]
+fn sandbox() {
+    let loader = memory_loader(&[
+        ("root/main.vdf", "#base ../secret.vdf\nroot {}"),
+        ("secret.vdf", "root { secret 1 }"),
+        ("root/ok.vdf", "#base dir/../main2.vdf\nroot {}"),
+        ("root/main2.vdf", "root { fine 1 }"),
+    ]);
+    let resolver = Resolver::new(&loader).sandbox("root");
+    match resolver.resolve("main.vdf").unwrap_err() {
+        ResolveError::OutsideSandbox { path } => assert_eq!(path, PathBuf::from("secret.vdf")),
+        other => panic!("Unexpected error: {other:?}"),
+    }
+    let resolved = resolver.resolve("ok.vdf").unwrap();
+    assert_eq!(resolved.vdf, Vdf::parse("root { fine 1 }").unwrap());
+
+    // Without a sandbox the file is loaded like normal
+    Resolver::new(&loader).resolve("root/main.vdf").unwrap();
+}
+
+#[test]
+fn errors_include_the_path() {
+    let loader = memory_loader(&[
+        ("main.vdf", "#base missing.vdf\n#base invalid.vdf\nroot {}"),
+        ("invalid.vdf", "root {"),
+    ]);
+    let err = Resolver::new(&loader).resolve("main.vdf").unwrap_err();
+    assert!(
+        matches!(&err, ResolveError::Load { path, .. } if path == Path::new("missing.vdf")),
+        "{err:?}"
+    );
+
+    let loader = memory_loader(&[
+        ("main.vdf", "#base invalid.vdf\nroot {}"),
+        ("invalid.vdf", "root {"),
+    ]);
+    let err = Resolver::new(&loader).resolve("main.vdf").unwrap_err();
+    assert!(
+        matches!(&err, ResolveError::Parse { path, .. } if path == Path::new("invalid.vdf")),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn custom_parser() {
+    let loader = memory_loader(&[
+        (
+            "main.vdf",
+            r"#base base.vdf
+        root { path C:\Windows }",
+        ),
+        ("base.vdf", r#"root { other "D:\Games" }"#),
+    ]);
+    let resolver = Resolver::new(loader).parser(Parser::new().literal_special_chars(true));
+    let resolved = resolver.resolve("main.vdf").unwrap();
+    assert_eq!(
+        resolved.vdf,
+        Vdf::parse_raw(r#"root { path C:\Windows other "D:\Games" }"#).unwrap()
+    );
+}
+
+#[test]
+fn std_file_loader() {
+    let root = Path::new("tests").join("assets").join("resolve");
+    let resolved = Resolver::new(StdFileLoader)
+        .sandbox(&root)
+        .resolve("hud.res")
+        .unwrap();
+    assert_eq!(
+        resolved.vdf,
+        Vdf::parse(
+            r#""Resource/HudLayout.res"
+            {
+                "HudHealth" { "xpos" "16" "ypos" "0" }
+                "HudHealth" { "wide" "100" }
+                "HudAmmo" { "xpos" "0" }
+            }"#
+        )
+        .unwrap()
+    );
+    // `#base` merges into the first matching object
+    assert_eq!(
+        resolved
+            .provenance
+            .source_at(&[("HudHealth", 0), ("ypos", 0)]),
+        Some(root.join("shared").join("hud_defaults.res").as_path())
+    );
+    assert_eq!(
+        resolved
+            .provenance
+            .source_at(&[("HudHealth", 1), ("wide", 0)]),
+        Some(root.join("shared").join("hud_extra.res").as_path())
+    );
+}
@@ -0,0 +1,92 @@
+use keyvalues_parser::{
+    span::{PairSpans, Span},
+    Parser,
+};
+use pretty_assertions::assert_eq;
+
+const VDF_TEXT: &str = r#"#base "base.vdf"
+"Outer Key"
+{
+	"Seq Key"	"Str Val"
+	ObjKey
+	{
+	}
+	"Ünïcödé"	"Val"
+}
+"#;
+
+fn top_level_spans(text: &str, literal_special_chars: bool) -> PairSpans<'_> {
+    Parser::new()
+        .literal_special_chars(literal_special_chars)
+        .with_spans(true)
+        .parse(text)
+        .unwrap()
+        .spans
+        .unwrap()
+}
+
+fn line_col(span: &Span) -> ((usize, usize), (usize, usize)) {
+    (
+        (span.start.line, span.start.column),
+        (span.end.line, span.end.column),
+    )
+}
+
+#[test]
+fn spans_are_opt_in() {
+    let vdf = Parser::new().parse(VDF_TEXT).unwrap();
+    assert_eq!(vdf.spans, None);
+}
+
+#[test]
+fn spans_dont_affect_the_parsed_tree() {
+    let plain = Parser::new().parse(VDF_TEXT).unwrap();
+    let spanned = Parser::new().with_spans(true).parse(VDF_TEXT).unwrap();
+    assert_eq!(plain.key, spanned.key);
+    assert_eq!(plain.value, spanned.value);
+    assert_eq!(plain.bases, spanned.bases);
+}
+
+#[test]
+fn every_key_value_and_brace() {
+    for literal_special_chars in [false, true] {
+        let spans = top_level_spans(VDF_TEXT, literal_special_chars);
+        assert_eq!(spans.key.as_str(VDF_TEXT), r#""Outer Key""#);
+        assert_eq!(line_col(&spans.key), ((2, 1), (2, 12)));
+
+        let outer = spans.value.get_obj().unwrap();
+        assert_eq!(line_col(&outer.open), ((3, 1), (3, 2)));
+        assert_eq!(line_col(&outer.close), ((9, 1), (9, 2)));
+
+        let str_val = &outer["Seq Key"][0];
+        assert_eq!(str_val.key.as_str(VDF_TEXT), r#""Seq Key""#);
+        assert_eq!(str_val.value.span().as_str(VDF_TEXT), r#""Str Val""#);
+        assert_eq!(line_col(&str_val.value.span()), ((4, 12), (4, 21)));
+
+        // Unquoted keys don't have any quotes to include
+        let obj_val = &outer["ObjKey"][0];
+        assert_eq!(obj_val.key.as_str(VDF_TEXT), "ObjKey");
+        assert_eq!(obj_val.value.span().as_str(VDF_TEXT), "{\n\t}");
+
+        let unicode = &outer["Ünïcödé"][0];
+        // Columns count `char`s while offsets count bytes
+        assert_eq!(line_col(&unicode.key), ((8, 2), (8, 11)));
+        assert_eq!(unicode.key.len(), 13);
+    }
+}
+
+#[test]
+fn duplicate_keys_mirror_obj() {
+    let text = "outer { key first key { } key third }";
+    let vdf = Parser::new().with_spans(true).parse(text).unwrap();
+    let values = &vdf.value.get_obj().unwrap()["key"];
+    let spans = &vdf.spans.as_ref().unwrap().value.get_obj().unwrap()["key"];
+
+    assert_eq!(values.len(), spans.len());
+    for (value, spans) in values.iter().zip(spans) {
+        assert_eq!(value.is_obj(), spans.value.get_obj().is_some());
+    }
+    assert_eq!(spans[0].value.span().as_str(text), "first");
+    assert_eq!(spans[1].value.span().as_str(text), "{ }");
+    assert_eq!(spans[2].value.span().as_str(text), "third");
+}
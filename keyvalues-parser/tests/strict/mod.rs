@@ -0,0 +1,121 @@
+use std::{fs, path::Path};
+
+use keyvalues_parser::{
+    error::{ParseError, ParseErrorKind},
+    Parser,
+};
+use pretty_assertions::assert_eq;
+
+fn strict_err(text: &str) -> ParseError {
+    // Everything here is fine without being strict
+    Parser::new().parse(text).unwrap();
+    Parser::new().strict(true).parse(text).unwrap_err()
+}
+
+#[test]
+fn directives_on_one_line() {
+    let text = "#base\"foo.vdf\"#base\"bar.vdf\"\nkey val";
+    let err = strict_err(text);
+    assert_eq!(err.kind(), &ParseErrorKind::DirectiveSharesLine);
+    assert_eq!((err.line(), err.column()), (1, 15));
+
+    // The top-level key can't share the line either
+    let text = "#include inc.vdf key val";
+    let err = strict_err(text);
+    assert_eq!(err.kind(), &ParseErrorKind::DirectiveSharesLine);
+    assert_eq!(err.span().as_str(text), "key");
+
+    // but comments can
+    let text = "#base base.vdf // defaults\n#basefile.vdf\nkey val";
+    Parser::new().strict(true).parse(text).unwrap();
+}
+
+#[test]
+fn comment_in_unquoted_str() {
+    let text = "links { home http://example.com }";
+    let err = strict_err(text);
+    assert_eq!(err.kind(), &ParseErrorKind::CommentInUnquotedStr);
+    assert_eq!(err.span().range(), 18..20);
+
+    // Quoted strings are unambiguous
+    Parser::new()
+        .strict(true)
+        .parse(r#"links { home "http://example.com" }"#)
+        .unwrap();
+}
+
+#[test]
+fn missing_separator() {
+    let text = r#"outer { "key"value }"#;
+    let err = strict_err(text);
+    assert_eq!(err.kind(), &ParseErrorKind::MissingSeparator);
+    assert_eq!(err.offset(), 13);
+
+    for text in [r#"outer { key"value" }"#, r#"outer { a b [$WIN32]c d }"#] {
+        assert_eq!(strict_err(text).kind(), &ParseErrorKind::MissingSeparator);
+    }
+    // Braces are separators
+    Parser::new().strict(true).parse("outer{key{}}").unwrap();
+
+    // The error covers the whole first character of the string
+    let text = "\"k\"\u{e9}";
+    let err = strict_err(text);
+    assert_eq!(err.kind(), &ParseErrorKind::MissingSeparator);
+    assert_eq!(err.span().as_str(text), "\u{e9}");
+    let strict = Parser::new().strict(true);
+    assert_eq!(strict.tape(text).unwrap_err(), err);
+    assert_eq!(strict.parse_recovering(text).errors, [err]);
+}
+
+#[test]
+fn non_printable_char() {
+    let text = "outer { key \"bell\u{7}\" }";
+    let err = strict_err(text);
+    assert_eq!(
+        err.kind(),
+        &ParseErrorKind::NonPrintableChar {
+            invalid_char: '\u{7}'
+        }
+    );
+    assert_eq!(err.span().range(), 17..18);
+    insta::assert_snapshot!(err);
+
+    // Tabs and newlines are fine along with the trailing null byte
+    let text = "outer { key \"multi\n\tline\" }\0";
+    Parser::new().strict(true).parse(text).unwrap();
+}
+
+#[test]
+fn recovering_reports_everything() {
+    let text = "#base a.vdf #base b.vdf\nouter { \"a\"b c http://d e \"\u{1}\" }";
+    let recovered = Parser::new().strict(true).parse_recovering(text);
+    let kinds: Vec<_> = recovered.errors.iter().map(ParseError::kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            &ParseErrorKind::DirectiveSharesLine,
+            &ParseErrorKind::MissingSeparator,
+            &ParseErrorKind::CommentInUnquotedStr,
+            &ParseErrorKind::NonPrintableChar {
+                invalid_char: '\u{1}'
+            },
+        ]
+    );
+}
+
+#[test]
+fn assets_are_strict() {
+    let assets = Path::new("tests").join("assets");
+    for entry in fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        // `compact.vdf` is all about leaving out the separators
+        if path.extension().map_or(true, |ext| ext != "vdf") || name == "compact.vdf" {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        let parser = Parser::new().literal_special_chars(name.contains("raw"));
+        let strict = parser.clone().strict(true);
+        assert_eq!(strict.parse(&text), parser.parse(&text), "{name}");
+    }
+}
@@ -1,4 +1,26 @@
+mod canonical;
+mod case_insensitive;
+mod conditional;
+mod document;
+mod duplicate_keys;
+mod edit;
+mod encoding;
+mod error;
+mod events;
 mod known_issues;
+mod lex;
+mod limits;
+mod ordered;
+#[cfg(feature = "pest")]
+mod pest_parity;
+mod recover;
 mod regressions;
+mod render_io;
+mod render_options;
+mod resolve;
+mod spans;
+mod strict;
+mod tape;
 mod text_parser;
+mod valve_compat;
 mod vdf_iteration;
@@ -0,0 +1,219 @@
+use std::{fs, path::Path};
+
+use keyvalues_parser::{DuplicateKeys, Parser};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn assets_match_parse() {
+    let assets = Path::new("tests").join("assets");
+    for entry in fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |ext| ext != "vdf") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+
+        for literal in [false, true] {
+            let parser = Parser::new().literal_special_chars(literal);
+            let tape = parser.tape(&text).map(|tape| tape.to_vdf());
+            assert_eq!(tape, parser.parse(&text), "{path:?}");
+        }
+    }
+}
+
+#[test]
+fn valve_compat_assets_match_parse() {
+    let assets = Path::new("tests").join("assets").join("valve_compat");
+    for entry in fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        let text = fs::read_to_string(&path).unwrap();
+        let parser = Parser::new().valve_compat(true);
+        let tape = parser.tape(&text).map(|tape| tape.to_vdf());
+        assert_eq!(tape, parser.parse(&text), "{path:?}");
+        assert!(tape.is_ok(), "{path:?}");
+    }
+}
+
+#[test]
+fn errors_match_parse() {
+    let cases = [
+        "",
+        "key",
+        "outer { key }",
+        "outer { key value",
+        "outer { { a b } }",
+        "outer { a b } trailing",
+        r#"outer { "bad" "\q" }"#,
+        "outer { \"unterminated }",
+        "a { b { c { d e } } }",
+        "a { b c d e f g }",
+    ];
+    let parser = Parser::new().max_depth(2).max_pairs(3).max_str_len(8);
+    for text in cases {
+        let tape_err = parser.tape(text).unwrap_err();
+        assert_eq!(tape_err, parser.parse(text).unwrap_err(), "{text:?}");
+    }
+
+    let parser = Parser::new().max_input_len(4);
+    assert_eq!(
+        parser.tape("outer {}").unwrap_err(),
+        parser.parse("outer {}").unwrap_err()
+    );
+}
+
+#[test]
+fn options_match_parse() {
+    let long = "x".repeat(5_000);
+    let long_text = format!(r#"outer {{ "{long}" "\\{long}" }}"#);
+    let cases = [
+        // Duplicate and case-folded keys
+        "outer { a 1 A 2 b { c 3 } a 4 B { C 5 c 6 } b 7 }",
+        "outer { a { x 1 } b 2 a { y 2 } a 3 }",
+        r#"outer { "a\"b" 1 "A\"B" 2 }"#,
+        "outer { inner { a 1 a 2 } inner { a 3 } }",
+        // Valve quirks
+        "outer { a 1 b } trailing",
+        "outer { a { b 1 c",
+        "outer { a 1 } } extra",
+        &long_text,
+        // Strict
+        "#base a.vdf #base b.vdf\nouter { a 1 }",
+        "#base a.vdf\nouter { url http://example.com }",
+        r#"outer { "a"b }"#,
+        "outer { a \"\u{7}\" }",
+        // Plain errors still come first
+        r#"outer { a "\q" a 1 }"#,
+        "outer { a 1 a",
+    ];
+    let policies = [
+        DuplicateKeys::KeepAll,
+        DuplicateKeys::FirstWins,
+        DuplicateKeys::LastWins,
+        DuplicateKeys::Error,
+    ];
+    for text in cases {
+        for policy in policies {
+            for (fold, valve, strict) in [
+                (false, false, false),
+                (true, false, false),
+                (false, true, false),
+                (false, false, true),
+                (true, true, true),
+            ] {
+                let parser = Parser::new()
+                    .duplicate_keys(policy)
+                    .fold_keys(fold)
+                    .valve_compat(valve)
+                    .strict(strict);
+                let tape = parser.tape(text).map(|tape| tape.to_vdf());
+                let context = (text, policy, fold, valve, strict);
+                assert_eq!(tape, parser.parse(text), "{context:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn folded_duplicates() {
+    let text = "outer { Name a other { x 1 } NAME b name { c d } other 2 }";
+    let parser = Parser::new()
+        .fold_keys(true)
+        .duplicate_keys(DuplicateKeys::LastWins);
+    let tape = parser.tape(text).unwrap();
+    let obj = tape.root().value().get_obj().unwrap();
+    assert_eq!(obj.len(), 2);
+
+    // The last pair is kept with the first spelling of its key
+    let keys: Vec<_> = obj.iter().map(|pair| pair.key().raw()).collect();
+    assert_eq!(keys, ["Name", "other"]);
+    let name = obj.get("Name").unwrap().get_obj().unwrap();
+    assert_eq!(name.get("c").unwrap().get_str().unwrap(), "d");
+    assert!(obj.get("name").is_none());
+    assert_eq!(obj.get("other").unwrap().get_str().unwrap(), "2");
+}
+
+#[test]
+fn navigation() {
+    let text = r#"
+#base "base.vdf"
+"controller_mappings"
+{
+	"version"		"3"
+	"group" { "id" "0" "mode" "four_buttons" }
+	"group" { "id" "1" "mode" "dpad" } [$WIN32]
+	"title"		"Tab\tseparated"
+	"group" { "id" "2" }
+}
+"#;
+    let tape = Parser::new().tape(text).unwrap();
+    assert_eq!(tape.bases().collect::<Vec<_>>(), ["base.vdf"]);
+    assert_eq!(tape.includes().count(), 0);
+
+    let root = tape.root();
+    assert_eq!(root.key(), "controller_mappings");
+    let obj = root.value().get_obj().unwrap();
+    assert_eq!(obj.len(), 5);
+
+    // Pairs come back in document order including duplicate keys
+    let keys: Vec<_> = obj.iter().map(|pair| pair.key().raw()).collect();
+    assert_eq!(keys, ["version", "group", "group", "title", "group"]);
+    let ids: Vec<_> = obj
+        .get_all("group")
+        .filter_map(|group| group.get_obj()?.get("id")?.get_str())
+        .map(|id| id.raw())
+        .collect();
+    assert_eq!(ids, ["0", "1", "2"]);
+
+    // Strings are only decoded when asked
+    let title = obj.get("title").unwrap().get_str().unwrap();
+    assert_eq!(title.raw(), r"Tab\tseparated");
+    assert_eq!(title.decode(), "Tab\tseparated");
+    assert_eq!(title, "Tab\tseparated");
+    assert_eq!(title.to_string(), "Tab\tseparated");
+
+    let mode = root.value().get_path(&["group", "mode"]).unwrap();
+    assert_eq!(mode.get_str().unwrap(), "four_buttons");
+    assert!(root.value().get_path(&["group", "missing"]).is_none());
+    assert!(root.value().get_path(&["version", "nested"]).is_none());
+    assert!(root.value().get_path(&[]).unwrap().is_obj());
+
+    // Parts can be converted on their own
+    let group = obj.get("group").unwrap().to_value();
+    assert_eq!(
+        group.get_obj().unwrap()["mode"][0].get_str(),
+        Some("four_buttons")
+    );
+}
+
+#[test]
+fn escaped_keys() {
+    let text = r#"outer { "a\"b" 1 "a\\b" 2 }"#;
+    let tape = Parser::new().tape(text).unwrap();
+    let obj = tape.root().value().get_obj().unwrap();
+    assert_eq!(obj.get("a\"b").unwrap().get_str().unwrap(), "1");
+    assert_eq!(obj.get("a\\b").unwrap().get_str().unwrap(), "2");
+
+    // Nothing gets unescaped when escapes are literal
+    let tape = Parser::new()
+        .literal_special_chars(true)
+        .tape(r#"outer { "C:\Dir\" 1 }"#)
+        .unwrap();
+    let obj = tape.root().value().get_obj().unwrap();
+    assert_eq!(obj.get(r"C:\Dir\").unwrap().get_str().unwrap(), "1");
+}
+
+#[test]
+fn deeply_nested() {
+    // Building the tape doesn't recurse
+    let depth = 100_000;
+    let text = format!("{}{}", "a {".repeat(depth), "}".repeat(depth));
+    let tape = Parser::new().tape(&text).unwrap();
+
+    let mut value = tape.root().value();
+    for _ in 1..depth {
+        let obj = value.get_obj().unwrap();
+        assert_eq!(obj.len(), 1);
+        value = obj.get("a").unwrap();
+    }
+    assert!(value.get_obj().unwrap().is_empty());
+}
@@ -4,7 +4,7 @@ use insta::{assert_debug_snapshot, assert_snapshot};
 
 use std::{borrow::Cow, collections::BTreeMap, error::Error, fs, path::Path};
 
-use keyvalues_parser::{Obj, PartialVdf, Value, Vdf};
+use keyvalues_parser::{Obj, Parser, PartialVdf, Value, Vdf};
 
 type BoxedResult<T> = Result<T, Box<dyn Error>>;
 
@@ -20,15 +20,23 @@ struct PartialVdfDef<'a> {
     key: Cow<'a, str>,
     value: ValueDef<'a>,
     bases: Vec<Cow<'a, str>>,
+    includes: Vec<Cow<'a, str>>,
 }
 
 impl<'a> From<PartialVdf<'a>> for PartialVdfDef<'a> {
     fn from(partial_vdf: PartialVdf<'a>) -> Self {
-        let PartialVdf { key, value, bases } = partial_vdf;
+        let PartialVdf {
+            key,
+            value,
+            bases,
+            includes,
+            ..
+        } = partial_vdf;
         Self {
             key,
             value: ValueDef::from(value),
             bases,
+            includes,
         }
     }
 }
@@ -173,13 +181,28 @@ parse_test_generator!(
     null_byte
 );
 
+// The sorted representation drops conditionals, so the ordered one is used for rendering to show
+// the text that actually round-trips
+#[test]
+fn conditionals() -> BoxedResult<()> {
+    let vdf_text = read_asset_file("conditionals.vdf")?;
+    let vdf = Vdf::parse(&vdf_text)?;
+    assert_debug_snapshot!("parsed-conditionals", VdfDef::from(vdf));
+
+    let rendered = Parser::new().parse_ordered(&vdf_text)?.to_string();
+    assert_snapshot!("rendered-conditionals", rendered);
+
+    Ok(())
+}
+
 parse_test_generator!(snapshot_test_raw_parse_render, raw_strings);
 
 parse_test_generator!(
     snapshot_test_partial_parse_and_render,
     base_multiple,
     base_quoted,
-    base_unquoted
+    base_unquoted,
+    include_mixed
 );
 
 parse_test_generator!(
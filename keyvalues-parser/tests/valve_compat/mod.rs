@@ -0,0 +1,66 @@
+//! Each file in `tests/assets/valve_compat` documents a quirk of the engine's
+//! `KeyValues::LoadFromBuffer()` that gets emulated by `Parser::valve_compat()`
+
+use std::{fs, path::Path};
+
+use keyvalues_parser::{Parser, Vdf};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn corpus() {
+    let corpus = Path::new("tests").join("assets").join("valve_compat");
+    let mut paths: Vec<_> = fs::read_dir(corpus)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(
+            Parser::new().parse(&text).is_err(),
+            "{name} should be invalid"
+        );
+
+        let vdf = Parser::new().valve_compat(true).parse(&text).unwrap();
+        insta::assert_snapshot!(name, Vdf::from(vdf).to_string());
+    }
+}
+
+#[test]
+fn truncated_tokens() {
+    // Multi-byte `char`s straddling the limit get dropped entirely
+    let long_key = "k".repeat(5_000);
+    let long_value = format!("{}é{}", "v".repeat(4_094), "v".repeat(100));
+    let text = format!("outer {{ {long_key} \"{long_value}\" short \"\\\"{long_value}\" }}");
+
+    let vdf = Parser::new().valve_compat(true).parse(&text).unwrap();
+    let obj = vdf.value.get_obj().unwrap();
+    let keys: Vec<_> = obj.keys().map(|key| key.len()).collect();
+    assert_eq!(keys, [4_095, 5]);
+    assert_eq!(
+        obj[&long_key[..4_095]][0].get_str(),
+        Some("v".repeat(4_094).as_str())
+    );
+    let escaped = format!("\"{}", "v".repeat(4_094));
+    assert_eq!(obj["short"][0].get_str(), Some(escaped.as_str()));
+
+    // Nothing gets truncated normally
+    let vdf = Parser::new().parse(&text).unwrap();
+    let obj = vdf.value.get_obj().unwrap();
+    assert_eq!(
+        obj[long_key.as_str()][0].get_str(),
+        Some(long_value.as_str())
+    );
+}
+
+#[test]
+fn still_invalid() {
+    let parser = Parser::new().valve_compat(true);
+    // The top-level pair still needs a value
+    assert!(parser.parse("\"AppState\"").is_err());
+    // and strings still need to be terminated within the top-level pair
+    assert!(parser.parse("outer { \"unterminated }").is_err());
+}
@@ -3,7 +3,7 @@
 // TODO: figure this out before the next breaking release
 #![allow(clippy::large_enum_variant)]
 
-use keyvalues_parser::error::Error as ParserError;
+use keyvalues_parser::error::ParseError as ParserError;
 use serde_core::{de, ser};
 
 use std::{
@@ -80,7 +80,7 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Message(msg) => f.write_str(msg),
-            Self::Parse(_) => f.write_str("Failed parsing VDF text"),
+            Self::Parse(e) => write!(f, "Failed parsing VDF text: {e}"),
             Self::Io(e) => write!(f, "Encountered I/O Error: {e}"),
             Self::NonFiniteFloat(non_finite) => {
                 write!(
@@ -23,4 +23,7 @@ pub use de::{
 #[doc(inline)]
 pub use error::{Error, Result};
 #[doc(inline)]
-pub use ser::{to_string, to_string_with_key, to_writer, to_writer_with_key, Serializer};
+pub use ser::{
+    to_string, to_string_with_key, to_string_with_options, to_writer, to_writer_with_key,
+    to_writer_with_options, Serializer,
+};
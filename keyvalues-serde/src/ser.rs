@@ -4,6 +4,8 @@ use serde_core::{ser, Serialize};
 
 use std::io::Write;
 
+use keyvalues_parser::RenderOptions;
+
 use crate::{
     error::{Error, Result},
     tokens::{naive::vdf_from_naive_tokens, NaiveToken},
@@ -35,7 +37,7 @@ where
     W: Write,
     T: Serialize,
 {
-    _to_writer(writer, value, None)
+    _to_writer(writer, value, None, &RenderOptions::new())
 }
 
 /// Serialize the `value` into an IO stream of VDF text with a custom top level VDF key
@@ -48,7 +50,24 @@ where
     W: Write,
     T: Serialize,
 {
-    _to_writer(writer, value, Some(key))
+    _to_writer(writer, value, Some(key), &RenderOptions::new())
+}
+
+/// Serialize the `value` into an IO stream of VDF text laid out according to `options`
+///
+/// # Errors
+///
+/// This will return an error if the input can't be represented with valid VDF
+pub fn to_writer_with_options<W, T>(
+    writer: &mut W,
+    value: &T,
+    options: &RenderOptions,
+) -> Result<()>
+where
+    W: Write,
+    T: Serialize,
+{
+    _to_writer(writer, value, None, options)
 }
 
 // Serialization process goes as follows:
@@ -58,7 +77,12 @@ where
 // -> Formatted
 // Which is a bit of a long-winded process just to serialize some text, but it comes with
 // validation (NaiveTokenStream -> Vdf) and reuses portions from the parser (Vdf -> Formatted)
-fn _to_writer<W, T>(writer: &mut W, value: &T, maybe_key: Option<&str>) -> Result<()>
+fn _to_writer<W, T>(
+    writer: &mut W,
+    value: &T,
+    maybe_key: Option<&str>,
+    options: &RenderOptions,
+) -> Result<()>
 where
     W: Write,
     T: Serialize,
@@ -79,7 +103,10 @@ where
     }
 
     let vdf = vdf_from_naive_tokens(&serializer.tokens)?;
-    write!(writer, "{vdf}")?;
+    let mut rendered = String::new();
+    vdf.render_with(&mut rendered, options)
+        .expect("Rendering to a `String` can't fail");
+    writer.write_all(rendered.as_bytes())?;
 
     Ok(())
 }
@@ -100,6 +127,39 @@ where
     Ok(s)
 }
 
+/// Attempts to serialize some input to VDF text laid out according to `options`
+///
+/// ```
+/// use keyvalues_serde::{parser::RenderOptions, to_string_with_options};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Config {
+///     name: String,
+///     volume: f32,
+/// }
+///
+/// let config = Config { name: "Portal 2".to_owned(), volume: 0.5 };
+/// let options = RenderOptions::new().compact(true);
+/// let vdf_text = to_string_with_options(&config, &options)?;
+/// assert_eq!(vdf_text, r#"Config{name "Portal 2" volume 0.5}"#);
+/// # Ok::<(), keyvalues_serde::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// This will return an error if the input can't be represented with valid VDF
+pub fn to_string_with_options<T>(value: &T, options: &RenderOptions) -> Result<String>
+where
+    T: Serialize,
+{
+    let mut buffer = Vec::new();
+    to_writer_with_options(&mut buffer, value, options)?;
+    let s = String::from_utf8(buffer).expect("Input was all valid UTF-8");
+
+    Ok(s)
+}
+
 /// Attempts to serialize some input to VDF text with a custom top level VDF key
 ///
 /// # Errors
@@ -4,11 +4,11 @@ use crate::utils::{read_asset_file, test_vdf_deserialization, BoxedResult, Conta
 
 use insta::{assert_debug_snapshot, assert_snapshot};
 use keyvalues_serde::{
-    from_str, from_str_raw, from_str_with_key, to_string, to_string_with_key, to_writer,
-    to_writer_with_key, Error,
+    from_str, from_str_raw, from_str_with_key, parser::RenderOptions, to_string,
+    to_string_with_key, to_string_with_options, to_writer, to_writer_with_key, Error,
 };
 use pretty_assertions::assert_eq;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 // TODO: what happens if you try to serialize a hashmap without providing a key?
 
@@ -168,3 +168,29 @@ fn borrowed_string_is_borrowed() -> BoxedResult<()> {
     assert!(matches!(vdf.inner, Cow::Borrowed(_)));
     Ok(())
 }
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Compact {
+    name: String,
+    tags: Vec<String>,
+    nested: Container<Option<u32>>,
+    missing: Option<u32>,
+}
+
+#[test]
+fn compact_round_trip() -> BoxedResult<()> {
+    let val = Compact {
+        name: "\"Half-Life\" 2".to_owned(),
+        tags: vec!["fps".to_owned(), String::new(), "{}".to_owned()],
+        nested: Container::new(Some(2004)),
+        missing: None,
+    };
+    let options = RenderOptions::new().compact(true);
+    let vdf_text = to_string_with_options(&val, &options)?;
+    assert_eq!(
+        vdf_text,
+        r#"Compact{name "\"Half-Life\" 2" nested{inner 2004}tags fps tags "" tags "{}"}"#
+    );
+    assert_eq!(from_str::<Compact>(&vdf_text)?, val);
+    Ok(())
+}